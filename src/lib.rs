@@ -18,6 +18,45 @@
 //!
 //! Biggest flaws it has right now is that it is *not async* and it is *not optimized* for
 //! performance. So it is **not recommended** to use it in production.
+//!
+//! ## Logging
+//!
+//! Krustie reports its diagnostics, like compression errors or responses without a `Content-Type`,
+//! through the [`log`](https://docs.rs/log) facade. They are only printed if the application
+//! installs a logger such as `env_logger`.
+//!
+//! ```rust
+//! use krustie::Response;
+//! use log::{ Level, Log, Metadata, Record };
+//! use std::sync::Mutex;
+//!
+//! static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+//!
+//! struct CapturingLogger;
+//!
+//! impl Log for CapturingLogger {
+//!   fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+//!     metadata.level() <= Level::Warn
+//!   }
+//!
+//!   fn log(&self, record: &Record<'_>) {
+//!     if self.enabled(record.metadata()) {
+//!       WARNINGS.lock().unwrap().push(record.args().to_string());
+//!     }
+//!   }
+//!
+//!   fn flush(&self) {}
+//! }
+//!
+//! log::set_logger(&CapturingLogger).unwrap();
+//! log::set_max_level(log::LevelFilter::Warn);
+//!
+//! let mut response = Response::default();
+//! response.get_body_mut().extend(b"Hello");
+//! response.write_to(&mut Vec::new()).unwrap();
+//!
+//! assert_eq!(WARNINGS.lock().unwrap()[0], "Content-Type not found even though body is present");
+//! ```
 
 pub mod server;
 pub mod router;
@@ -11,14 +11,37 @@ use crate::{ server::route_handler::{ HandlerResult, RouteHandler }, Request, Re
 pub mod gzip;
 pub mod statics;
 pub mod rate_limiter;
+pub mod logger;
+pub mod deadline;
+pub mod body_metrics;
+pub mod maintenance;
+pub mod canonical_host;
+pub mod method_override;
+mod http_date;
 
-pub use self::{ gzip::GzipEncoder, statics::ServeStatic, rate_limiter::RateLimiter };
+pub use self::{
+    gzip::GzipEncoder,
+    statics::ServeStatic,
+    rate_limiter::RateLimiter,
+    logger::Logger,
+    deadline::Deadline,
+    body_metrics::BodyMetrics,
+    maintenance::Maintenance,
+    canonical_host::CanonicalHost,
+    method_override::MethodOverride,
+};
 
 /// Middleware trait to be implemented for creating middleware.
 ///
 /// If there is no property declared in the struct, struct can be used directly.
 /// Or it can be used as a value if it needs to be initialized.
 ///
+/// Routers and servers store middlewares as `Box<dyn Middleware + Send + Sync>`, so a middleware
+/// needs to be `Send` and `Sync` to be added to them.
+///
+/// The server runs the requests of different connections at the same time, so a middleware only
+/// gets `&self`. State that changes with the requests is kept in atomics or behind a `Mutex`.
+///
 /// # Example
 ///
 /// - In this example `AddKrustieHeader` can be used as `server.add_handler(AddKrustieHeader)`
@@ -35,7 +58,7 @@ pub use self::{ gzip::GzipEncoder, statics::ServeStatic, rate_limiter::RateLimit
 /// }
 ///
 /// impl Middleware for AddKrustieHeader {
-///   fn middleware(&mut self, req: &Request, res: &mut Response) -> HandlerResult {
+///   fn middleware(&self, req: &Request, res: &mut Response) -> HandlerResult {
 ///     AddKrustieHeader::add_header(res);
 ///     HandlerResult::Next
 ///   }
@@ -59,7 +82,7 @@ pub use self::{ gzip::GzipEncoder, statics::ServeStatic, rate_limiter::RateLimit
 /// }
 ///
 /// impl Middleware for AddHeader {
-///   fn middleware(&mut self, _: &Request, res: &mut Response) -> HandlerResult {
+///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
 ///     res.insert_header(&self.server, &self.value);
 ///     HandlerResult::Next
 ///   }
@@ -72,13 +95,13 @@ pub trait Middleware {
     /// For the middleware to be executed and continue the execution, it should return `HandlerResult::Next`.
     ///
     /// If the middleware should stop the execution (e.g. return 404), it should return `HandlerResult::Stop`.
-    fn middleware(&mut self, request: &Request, response: &mut Response) -> HandlerResult;
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult;
 }
 
 impl<T> RouteHandler for T where T: Middleware {
     fn handle(
-        &mut self,
-        request: &Request,
+        &self,
+        request: &mut Request,
         response: &mut Response,
         _: &[String]
     ) -> HandlerResult {
@@ -0,0 +1,79 @@
+//! A middleware for counting the bytes of request and response bodies
+
+use std::sync::{ atomic::{ AtomicUsize, Ordering }, Arc };
+
+use crate::{ server::route_handler::HandlerResult, Middleware, Request, Response };
+
+/// A middleware that counts the body bytes of the requests and responses
+///
+/// It should be added as a response middleware, so the response body is complete. Request bodies
+/// are counted as they were received. Response bodies are counted as they are when the middleware
+/// runs, so it counts the compressed size if it is added after `GzipEncoder` and the uncompressed
+/// size if it is added before. Streamed response bodies are not counted.
+///
+/// The counters are shared between the clones of the middleware, so a clone can be kept to read
+/// them while the server is running.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::{ BodyMetrics, GzipEncoder } };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+/// let uncompressed = BodyMetrics::new();
+/// let compressed = BodyMetrics::new();
+///
+/// router.post(|_, res| {
+///   res.status(StatusCode::Ok).body_text(&"Hello, World! ".repeat(100));
+/// });
+/// router.use_response_middleware(uncompressed.clone());
+/// router.use_response_middleware(GzipEncoder);
+/// router.use_response_middleware(compressed.clone());
+/// server.use_handler(router);
+///
+/// let raw = b"POST / HTTP/1.1\r\nAccept-Encoding: gzip\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nHello";
+/// let mut request = Request::parse_from_bytes(raw).unwrap();
+/// server.dispatch(&mut request);
+///
+/// assert_eq!(uncompressed.request_bytes(), 5);
+/// assert_eq!(uncompressed.response_bytes(), 1400);
+/// assert!(compressed.response_bytes() < 1400);
+///
+/// let mut request = Request::parse_from_bytes(raw).unwrap();
+/// server.dispatch(&mut request);
+///
+/// assert_eq!(uncompressed.request_bytes(), 10);
+/// assert_eq!(uncompressed.response_bytes(), 2800);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BodyMetrics {
+    request_bytes: Arc<AtomicUsize>,
+    response_bytes: Arc<AtomicUsize>,
+}
+
+impl BodyMetrics {
+    /// Creates a new instance of BodyMetrics with zero counters
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the total number of bytes of the request bodies
+    pub fn request_bytes(&self) -> usize {
+        self.request_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Returns the total number of bytes of the response bodies
+    pub fn response_bytes(&self) -> usize {
+        self.response_bytes.load(Ordering::SeqCst)
+    }
+}
+
+impl Middleware for BodyMetrics {
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult {
+        self.request_bytes.fetch_add(request.raw_body_len(), Ordering::SeqCst);
+        self.response_bytes.fetch_add(response.body_len(), Ordering::SeqCst);
+
+        HandlerResult::Next
+    }
+}
@@ -0,0 +1,104 @@
+//! A middleware for redirecting requests to the canonical host
+
+use crate::{ server::route_handler::HandlerResult, Middleware, Request, Response, StatusCode };
+
+/// A middleware that redirects requests for a non-canonical host to the canonical one
+///
+/// Requests whose host differs from the canonical host, e.g. `www.example.com` or
+/// `Example.com`, are answered with `301 Moved Permanently`. The path, the query and the port of
+/// the request are kept in the `Location` header. Requests without a host are handled normally.
+///
+/// The host is read with `Request::host`, so a host forwarded by a trusted proxy is used.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::CanonicalHost };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+///
+/// router.get(|_, res| {
+///   res.status(StatusCode::Ok).body_text("Hello");
+/// });
+/// server.use_handler(CanonicalHost::new("example.com"));
+/// server.use_handler(router);
+///
+/// let raw = b"GET /menu/burgers?size=large HTTP/1.1\r\nHost: www.example.com\r\n\r\n";
+/// let mut request = Request::parse_from_bytes(raw).unwrap();
+/// let response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_status(), StatusCode::MovedPermanently);
+/// assert_eq!(
+///   response.get_header("Location").unwrap(),
+///   "http://example.com/menu/burgers?size=large"
+/// );
+///
+/// let raw = b"GET / HTTP/1.1\r\nHost: EXAMPLE.com:8080\r\n\r\n";
+/// let mut request = Request::parse_from_bytes(raw).unwrap();
+/// let response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_status(), StatusCode::MovedPermanently);
+/// assert_eq!(response.get_header("Location").unwrap(), "http://example.com:8080/");
+///
+/// let raw = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+/// let mut request = Request::parse_from_bytes(raw).unwrap();
+///
+/// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::Ok);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CanonicalHost {
+    host: String,
+}
+
+impl CanonicalHost {
+    /// Creates a new instance of CanonicalHost
+    ///
+    /// The host is given without a port and is lowercased, so hosts that only differ in case are
+    /// redirected too.
+    pub fn new(host: &str) -> Self {
+        Self { host: host.to_lowercase() }
+    }
+}
+
+/// Splits a `Host` value into the name and the port, e.g. `example.com:8080`
+fn split_port(host: &str) -> (&str, Option<&str>) {
+    match host.rsplit_once(':') {
+        // Only digits are a port, so the last group of an IPv6 address like `[::1]` is not
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()) => {
+            (name, Some(port))
+        }
+        _ => (host, None),
+    }
+}
+
+impl Middleware for CanonicalHost {
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult {
+        let Some(host) = request.host() else {
+            return HandlerResult::Next;
+        };
+
+        let (name, port) = split_port(host);
+
+        if name == self.host {
+            return HandlerResult::Next;
+        }
+
+        let scheme = if request.is_secure() { "https" } else { "http" };
+        let mut location = format!("{scheme}://{}", self.host);
+
+        if let Some(port) = port {
+            location.push_str(&format!(":{port}"));
+        }
+
+        location.push_str(request.get_path());
+
+        if let Some(query) = request.get_query() {
+            location.push_str(&format!("?{query}"));
+        }
+
+        response.status(StatusCode::MovedPermanently).insert_header("Location", &location);
+
+        HandlerResult::End
+    }
+}
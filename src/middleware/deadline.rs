@@ -0,0 +1,63 @@
+//! A handler for setting a deadline to requests
+
+use std::time::{ Duration, Instant };
+
+use crate::{
+    server::route_handler::{ HandlerResult, RouteHandler },
+    Request,
+    Response,
+};
+
+/// A handler that sets a deadline to each request
+///
+/// The deadline doesn't interrupt the controllers. Instead, long-running controllers can check it
+/// with `Request::deadline_exceeded` or `Request::check_deadline` and return early with
+/// `504 Gateway Timeout`.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::Deadline };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+///
+/// router.get(|req, res| {
+///   for _ in 0..1000 {
+///     if req.check_deadline(res) {
+///       return;
+///     }
+///     std::thread::sleep(std::time::Duration::from_millis(1));
+///   }
+///
+///   res.status(StatusCode::Ok).body_text("Done");
+/// });
+///
+/// server.use_handler(Deadline::new(10));
+/// server.use_handler(router);
+///
+/// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// let response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_status(), StatusCode::GatewayTimeout);
+/// ```
+#[derive(Debug)]
+pub struct Deadline {
+    timeout: Duration,
+}
+
+impl Deadline {
+    /// Creates a new instance of Deadline with the given timeout in milliseconds
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+}
+
+impl RouteHandler for Deadline {
+    fn handle(&self, request: &mut Request, _: &mut Response, _: &[String]) -> HandlerResult {
+        request.set_deadline(Instant::now() + self.timeout);
+        HandlerResult::Next
+    }
+}
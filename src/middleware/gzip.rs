@@ -1,14 +1,47 @@
 //! A middleware for compressing response body using gzip
+//!
+//! The module also contains `decode_body` and `encode_body` for transforming bodies with a
+//! `Content-Encoding`, e.g. in a proxy that modifies compressed upstream responses.
+//!
+//! ```rust
+//! use krustie::middleware::gzip::{ decode_body, encode_body };
+//!
+//! let upstream = encode_body(b"Hello, World!", "gzip, gzip").unwrap();
+//! let body = decode_body(&upstream, "gzip, gzip").unwrap();
+//!
+//! assert_eq!(body, b"Hello, World!");
+//!
+//! let modified = String::from_utf8(body).unwrap().replace("World", "Krusty");
+//! let recompressed = encode_body(modified.as_bytes(), "gzip").unwrap();
+//!
+//! assert_eq!(decode_body(&recompressed, "gzip").unwrap(), b"Hello, Krusty!");
+//! assert!(decode_body(b"Hello", "br").is_err());
+//! ```
 
-use std::io::Write;
-use flate2::{ write::GzEncoder, Compression };
+use std::{ fmt::{ Display, Formatter, Result as fResult }, io::{ Read, Write } };
+use flate2::{ read::{ GzDecoder, ZlibDecoder }, write::{ GzEncoder, ZlibEncoder }, Compression };
 
 use super::Middleware;
 use crate::{ request::Request, response::Response, server::route_handler::HandlerResult };
 
+const STREAM_CHUNK_SIZE: usize = 8 * 1024;
+
 /// A middleware for compressing response body using gzip.
 ///
-/// # ExampleF
+/// The body is compressed if the `Accept-Encoding` header accepts `gzip` with a non-zero quality
+/// that isn't lower than the quality of `identity`. `Accept-Encoding` is added to the `Vary`
+/// header of every response that could be compressed, whether the client accepted gzip or not.
+///
+/// Bodies shorter than the compression threshold of the server configuration and bodies with a
+/// content type that is compressed already, like images, audio, video and archives, are sent as
+/// they are. For streams, the length given to `Response::body_stream` is compared to the
+/// threshold before the stream is read.
+///
+/// Streamed bodies are compressed chunk by chunk while the response is written, so they are
+/// never buffered. Each chunk is flushed from the encoder and the compressed body is sent with
+/// chunked encoding.
+///
+/// # Example
 ///
 /// ```rust
 /// use krustie::{server::Server, middleware::gzip::GzipEncoder};
@@ -16,15 +49,129 @@ use crate::{ request::Request, response::Response, server::route_handler::Handle
 /// let mut server = Server::create();
 ///
 /// server.use_handler(GzipEncoder);
+/// ```
+///
+/// Clients can refuse compression with `identity` or `gzip;q=0`
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::GzipEncoder };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+///
+/// router.get(|_, res| {
+///   res.status(StatusCode::Ok).body_text("Hello, World!");
+/// });
+/// router.use_response_middleware(GzipEncoder);
+/// server.use_handler(router);
+///
+/// for (accept_encoding, compressed) in [
+///   ("gzip", true),
+///   ("gzip;q=0.5, br", true),
+///   ("identity", false),
+///   ("gzip;q=0, identity", false),
+///   ("gzip;q=0.5, identity", false),
+/// ] {
+///   let raw = format!("GET / HTTP/1.1\r\nAccept-Encoding: {accept_encoding}\r\n\r\n");
+///   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+///   let mut response = server.dispatch(&mut request);
+///
+///   assert_eq!(response.get_header("Content-Encoding").is_some(), compressed, "{accept_encoding}");
+///   assert_eq!(response.get_body() == b"Hello, World!", !compressed, "{accept_encoding}");
+/// }
+/// ```
+///
+/// Compressing a streamed body
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::{ GzipEncoder, gzip::decode_body } };
+/// use std::io::{ repeat, Read };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+///
+/// router.get(|_, res| {
+///   let reader = repeat(b'k').take(1024 * 1024);
+///   res.status(StatusCode::Ok).body_stream(Box::new(reader), Some(1024 * 1024));
+/// });
+/// router.use_response_middleware(GzipEncoder);
+/// server.use_handler(router);
+///
+/// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\nAccept-Encoding: gzip\r\n\r\n").unwrap();
+/// let bytes: Vec<u8> = server.dispatch(&mut request).into();
+/// let head_end = bytes.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+/// let head = String::from_utf8_lossy(&bytes[..head_end]);
+///
+/// assert!(head.contains("Content-Encoding: gzip\r\n"));
+/// assert!(head.contains("Transfer-Encoding: chunked\r\n"));
+/// assert!(!head.contains("Content-Length"));
+///
+/// // Joins the chunks of the body
+/// let mut rest = &bytes[head_end..];
+/// let mut compressed = Vec::new();
+/// let mut chunks = 0;
+///
+/// loop {
+///   let line_end = rest.windows(2).position(|window| window == b"\r\n").unwrap();
+///   let size = usize::from_str_radix(std::str::from_utf8(&rest[..line_end]).unwrap(), 16).unwrap();
+///   if size == 0 {
+///     break;
+///   }
+///   compressed.extend_from_slice(&rest[line_end + 2..line_end + 2 + size]);
+///   rest = &rest[line_end + 4 + size..];
+///   chunks += 1;
+/// }
+///
+/// assert!(chunks > 1);
+/// assert!(compressed.len() < 1024 * 1024);
+/// assert_eq!(decode_body(&compressed, "gzip").unwrap(), vec![b'k'; 1024 * 1024]);
+/// ```
+///
+/// Small declared stream lengths and compressed content types are skipped
 ///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, HttpMethod, server::ServerConfig, middleware::GzipEncoder };
+/// use std::io::{ repeat, Read };
+///
+/// let mut server = Server::with_config(ServerConfig {
+///   compression_threshold: 1024,
+///   ..ServerConfig::default()
+/// });
+/// let mut router = Router::new();
+///
+/// router.route(HttpMethod::GET, "/small", |_, res| {
+///   res.status(StatusCode::Ok).body_stream(Box::new(repeat(b'k').take(100)), Some(100));
+/// });
+/// router.route(HttpMethod::GET, "/large", |_, res| {
+///   res.status(StatusCode::Ok).body_stream(Box::new(repeat(b'k').take(4096)), Some(4096));
+/// });
+/// router.route(HttpMethod::GET, "/image", |_, res| {
+///   res.status(StatusCode::Ok).insert_header("Content-Type", "image/png").append_body(&[0; 4096]);
+/// });
+/// router.use_response_middleware(GzipEncoder);
+/// server.use_handler(router);
+///
+/// for (path, compressed) in [("/small", false), ("/large", true), ("/image", false)] {
+///   let raw = format!("GET {path} HTTP/1.1\r\nAccept-Encoding: gzip\r\n\r\n");
+///   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+///   let response = server.dispatch(&mut request);
+///
+///   assert_eq!(response.get_header("Content-Encoding").is_some(), compressed, "{path}");
+/// }
+/// ```
 #[derive(Debug)]
 pub struct GzipEncoder;
 
 impl GzipEncoder {
-    fn encode(body: &Vec<u8>) -> Result<Vec<u8>, String> {
+    /// Compresses the body with gzip
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoder fails.
+    pub fn encode(body: &[u8]) -> Result<Vec<u8>, String> {
         let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
 
-        if encoder.write_all(body.as_slice()).is_err() {
+        if encoder.write_all(body).is_err() {
             return Err("Error while writing to encoder".to_string());
         }
 
@@ -39,36 +186,302 @@ impl GzipEncoder {
     }
 }
 
+/// Returns false for content types that are compressed already, like images, audio, video and
+/// archives, where gzip only adds overhead
+fn is_compressible(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+
+    match media_type.split_once('/') {
+        Some(("image", subtype)) => subtype == "svg+xml",
+        Some(("audio" | "video", _)) => false,
+        Some(("font", subtype)) => !matches!(subtype, "woff" | "woff2"),
+        Some(("application", subtype)) => {
+            !matches!(
+                subtype,
+                "zip" | "gzip" | "x-gzip" | "x-bzip2" | "x-7z-compressed" | "x-rar-compressed" | "zstd"
+            )
+        }
+        _ => true,
+    }
+}
+
+/// Returns true if the `Accept-Encoding` header prefers the coding over no compression
+///
+/// `x-gzip` is treated as an alias of `gzip`.
+pub(crate) fn accepts_encoding(accept_encoding: &str, coding: &str) -> bool {
+    let mut accepted = None;
+    let mut identity = 0.0;
+    let mut wildcard = None;
+
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let item_coding = parts.next().unwrap_or("").trim().to_lowercase();
+        let quality = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|quality| quality.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        match item_coding.as_str() {
+            "identity" => {
+                identity = quality;
+            }
+            "*" => {
+                wildcard = Some(quality);
+            }
+            "x-gzip" if coding == "gzip" => {
+                accepted = Some(quality);
+            }
+            item_coding if item_coding == coding => {
+                accepted = Some(quality);
+            }
+            _ => {}
+        }
+    }
+
+    match accepted.or(wildcard) {
+        Some(quality) => quality > 0.0 && quality >= identity,
+        None => false,
+    }
+}
+
 impl Middleware for GzipEncoder {
-    fn middleware(&mut self, request: &Request, response: &mut Response) -> HandlerResult {
-        let body = response.get_body_mut();
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult {
+        // The body is already encoded, e.g. a precompressed static file
+        if response.get_header("Content-Encoding").is_some() {
+            return HandlerResult::Next;
+        }
+
+        let is_compressed_type = response
+            .get_header("Content-Type")
+            .is_some_and(|content_type| !is_compressible(content_type));
 
-        if body.is_empty() {
+        if is_compressed_type {
             return HandlerResult::Next;
         }
 
-        if let Some(str_encodings) = request.get_header("accept-encoding") {
-            let encodings = str_encodings
-                .split(',')
-                .map(|item| item.trim())
-                .collect::<Vec<&str>>();
+        // The length of a stream is only known if it was declared, so it is checked before the
+        // stream is read
+        let length = match response.has_stream() {
+            true => response.stream_length(),
+            false => Some(response.body_len()),
+        };
+        let threshold = response.get_compression_threshold();
 
-            if !encodings.contains(&"gzip") {
-                return HandlerResult::Next;
-            }
+        if length.is_some_and(|length| length == 0 || length < threshold) {
+            return HandlerResult::Next;
+        }
+
+        response.add_vary("Accept-Encoding");
 
-            match Self::encode(body) {
-                Ok(compressed_bytes) => {
-                    response.insert_header("Content-Encoding", "gzip");
+        let accepts_gzip = request
+            .get_header("accept-encoding")
+            .is_some_and(|accept_encoding| accepts_encoding(accept_encoding, "gzip"));
+
+        if !accepts_gzip {
+            return HandlerResult::Next;
+        }
+
+        // Streamed bodies are compressed while they are written
+        if response.has_stream() {
+            response.map_stream(|reader| Box::new(GzipStream::new(reader)));
+            response.insert_header("Content-Encoding", "gzip");
+
+            return HandlerResult::Next;
+        }
+
+        match Self::encode(response.body_bytes()) {
+            Ok(compressed_bytes) => {
+                response.insert_header("Content-Encoding", "gzip");
 
-                    let _ = response.update_body(compressed_bytes);
-                }
-                Err(err) => {
-                    eprintln!("Error while compressing: {}", err);
-                }
+                let _ = response.update_body(compressed_bytes);
+            }
+            Err(err) => {
+                log::warn!("Error while compressing: {}", err);
             }
         }
 
         return HandlerResult::Next;
     }
 }
+
+/// Compresses a body stream with gzip while it is read
+///
+/// Every chunk read from the inner stream is compressed and flushed, so the client can decompress
+/// each chunk as soon as it is received.
+struct GzipStream {
+    reader: Box<dyn Read + Send>,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl GzipStream {
+    fn new(reader: Box<dyn Read + Send>) -> Self {
+        Self {
+            reader,
+            encoder: Some(GzEncoder::new(Vec::new(), Compression::default())),
+            output: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for GzipStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.output.len() {
+            let Some(encoder) = self.encoder.as_mut() else {
+                return Ok(0);
+            };
+
+            let mut chunk = [0; STREAM_CHUNK_SIZE];
+            let read = self.reader.read(&mut chunk)?;
+
+            self.output = if read == 0 {
+                // Writes the gzip trailer after the last chunk
+                self.encoder.take().map_or(Ok(Vec::new()), |encoder| encoder.finish())?
+            } else {
+                encoder.write_all(&chunk[..read])?;
+                encoder.flush()?;
+                std::mem::take(encoder.get_mut())
+            };
+            self.position = 0;
+        }
+
+        let read = buf.len().min(self.output.len() - self.position);
+        buf[..read].copy_from_slice(&self.output[self.position..self.position + read]);
+        self.position += read;
+
+        Ok(read)
+    }
+}
+
+/// Decodes a body with the codings of a `Content-Encoding` header
+///
+/// Codings are removed in the reverse order they are listed, so `gzip, gzip` is decompressed
+/// twice. Supported codings are `gzip`, `x-gzip`, `deflate` and `identity`.
+///
+/// # Errors
+///
+/// Returns an error if a coding is not supported or the body is not valid for a coding.
+pub fn decode_body(body: &[u8], content_encoding: &str) -> Result<Vec<u8>, String> {
+    decode_body_limited(body, content_encoding, usize::MAX).map_err(|err| err.to_string())
+}
+
+/// Decodes a body like `decode_body`, without inflating it beyond the maximum size
+///
+/// A small compressed body can expand to gigabytes, so decoding stops as soon as the output of
+/// any coding exceeds `max_size` bytes.
+///
+/// # Errors
+///
+/// Returns `DecodeError::TooLarge` if the decoded body would be larger than `max_size`, and the
+/// errors of `decode_body` otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::middleware::gzip::{ decode_body_limited, encode_body, DecodeError };
+///
+/// let bomb = encode_body(&vec![0; 10 * 1024 * 1024], "gzip").unwrap();
+///
+/// assert!(bomb.len() < 20 * 1024);
+/// assert_eq!(decode_body_limited(&bomb, "gzip", 1024 * 1024), Err(DecodeError::TooLarge));
+/// assert_eq!(decode_body_limited(&bomb, "gzip", 16 * 1024 * 1024).unwrap().len(), 10 * 1024 * 1024);
+/// ```
+pub fn decode_body_limited(
+    body: &[u8],
+    content_encoding: &str,
+    max_size: usize
+) -> Result<Vec<u8>, DecodeError> {
+    let mut body = body.to_vec();
+    // Reading one more byte than allowed tells if the output exceeds the limit
+    let limit = (max_size as u64).saturating_add(1);
+
+    for coding in codings(content_encoding).rev() {
+        let mut decoded = Vec::new();
+
+        let result = match coding.as_str() {
+            "gzip" | "x-gzip" => {
+                GzDecoder::new(body.as_slice()).take(limit).read_to_end(&mut decoded)
+            }
+            "deflate" => ZlibDecoder::new(body.as_slice()).take(limit).read_to_end(&mut decoded),
+            "identity" => {
+                continue;
+            }
+            _ => {
+                return Err(DecodeError::Unsupported(coding));
+            }
+        };
+
+        result.map_err(|err| DecodeError::Invalid(format!("{}: {}", coding, err)))?;
+
+        if decoded.len() > max_size {
+            return Err(DecodeError::TooLarge);
+        }
+
+        body = decoded;
+    }
+
+    Ok(body)
+}
+
+/// Error returned while decoding a body with `decode_body_limited`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The coding is not supported
+    Unsupported(String),
+    /// The body is not valid for the coding
+    Invalid(String),
+    /// The decoded body is larger than the maximum size
+    TooLarge,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::Unsupported(coding) => write!(f, "Unsupported content encoding: {}", coding),
+            Self::Invalid(err) => write!(f, "Error while decoding {}", err),
+            Self::TooLarge => write!(f, "Decoded body exceeds the maximum size"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Encodes a body with the codings of a `Content-Encoding` header
+///
+/// Codings are applied in the order they are listed. Supported codings are the same as
+/// `decode_body`.
+///
+/// # Errors
+///
+/// Returns an error if a coding is not supported or the encoder fails.
+pub fn encode_body(body: &[u8], content_encoding: &str) -> Result<Vec<u8>, String> {
+    let mut body = body.to_vec();
+
+    for coding in codings(content_encoding) {
+        body = match coding.as_str() {
+            "gzip" | "x-gzip" => GzipEncoder::encode(&body)?,
+            "deflate" => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder
+                    .write_all(&body)
+                    .and_then(|_| encoder.finish())
+                    .map_err(|err| format!("Error while encoding deflate: {}", err))?
+            }
+            "identity" => body,
+            _ => {
+                return Err(format!("Unsupported content encoding: {}", coding));
+            }
+        };
+    }
+
+    Ok(body)
+}
+
+fn codings(content_encoding: &str) -> impl DoubleEndedIterator<Item = String> + '_ {
+    content_encoding
+        .split(',')
+        .map(|coding| coding.trim().to_lowercase())
+        .filter(|coding| !coding.is_empty())
+}
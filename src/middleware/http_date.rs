@@ -0,0 +1,124 @@
+//! Formatting and parsing of the dates used in headers and logs
+
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A UTC date and time
+struct DateTime {
+    /// Days since the epoch
+    days: u64,
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+impl DateTime {
+    fn from_system_time(time: SystemTime) -> Option<Self> {
+        let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let days = secs / 86400;
+        let secs_of_day = secs % 86400;
+
+        // Converts days since the epoch to a civil date
+        let z = days + 719468;
+        let era = z / 146097;
+        let day_of_era = z % 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+        Some(Self {
+            days,
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3600,
+            minute: (secs_of_day % 3600) / 60,
+            second: secs_of_day % 60,
+        })
+    }
+
+    fn month_name(&self) -> &'static str {
+        MONTHS[(self.month - 1) as usize]
+    }
+}
+
+/// Formats the time as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub(crate) fn format(time: SystemTime) -> Option<String> {
+    let date = DateTime::from_system_time(time)?;
+
+    Some(
+        format!(
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[(date.days % 7) as usize],
+            date.day,
+            date.month_name(),
+            date.year,
+            date.hour,
+            date.minute,
+            date.second
+        )
+    )
+}
+
+/// Formats the time as a Common Log Format date, e.g. `06/Nov/1994:08:49:37 +0000`
+pub(crate) fn format_clf(time: SystemTime) -> Option<String> {
+    let date = DateTime::from_system_time(time)?;
+
+    Some(
+        format!(
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            date.day,
+            date.month_name(),
+            date.year,
+            date.hour,
+            date.minute,
+            date.second
+        )
+    )
+}
+
+/// Parses an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`, into seconds since the epoch
+///
+/// Only the preferred format is supported.
+pub(crate) fn parse(date: &str) -> Option<u64> {
+    let (_, date) = date.trim().split_once(", ")?;
+    let parts = date.split(' ').collect::<Vec<_>>();
+
+    if parts.len() != 5 || parts[4] != "GMT" {
+        return None;
+    }
+
+    let day = parts[0].parse::<u64>().ok()?;
+    let month = (MONTHS.iter().position(|month| *month == parts[1])? as u64) + 1;
+    let year = parts[2].parse::<u64>().ok()?;
+    let time = parts[3]
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    if time.len() != 3 || year < 1970 || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Converts the civil date to days since the epoch
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era).checked_sub(719468)?;
+
+    Some(days * 86400 + time[0] * 3600 + time[1] * 60 + time[2])
+}
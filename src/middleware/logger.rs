@@ -0,0 +1,317 @@
+//! A middleware for logging requests
+
+use std::{ fmt::{ Debug, Formatter, Result as fmtResult }, time::SystemTime };
+
+use super::http_date;
+use crate::{
+    json::JsonValue,
+    request::RequestBody,
+    server::route_handler::HandlerResult,
+    Middleware,
+    Request,
+    Response,
+};
+
+/// Format of the log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// The method, path and status code, e.g. `GET /users 200`
+    #[default]
+    Short,
+    /// Common Log Format, e.g.
+    /// `127.0.0.1 - - [06/Nov/1994:08:49:37 +0000] "GET /users HTTP/1.1" 200 1024`
+    Common,
+    /// Combined Log Format, which adds the quoted `Referer` and `User-Agent` headers to the
+    /// Common Log Format
+    Combined,
+}
+
+/// A middleware for logging requests
+///
+/// Logs a line containing the method, path and status code of each request. It should be added
+/// as a response middleware or after the routers so the final status code is logged. Access log
+/// formats can be selected with `with_format`.
+///
+/// Request bodies are not logged by default. They can be logged with `with_body`, which redacts
+/// the given JSON fields and truncates long bodies.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Router, middleware::Logger };
+///
+/// let mut router = Router::new();
+///
+/// router.use_response_middleware(Logger::new());
+/// ```
+pub struct Logger {
+    format: LogFormat,
+    log_body: bool,
+    redacted_fields: Vec<String>,
+    max_body_length: usize,
+    output: Box<dyn Fn(&str) + Send + Sync>,
+}
+
+impl Logger {
+    /// Creates a new logger that writes to the `log` facade at the info level
+    pub fn new() -> Self {
+        Self {
+            format: LogFormat::Short,
+            log_body: false,
+            redacted_fields: Vec::new(),
+            max_body_length: 0,
+            output: Box::new(|line| log::info!("{}", line)),
+        }
+    }
+
+    /// Enables logging of request bodies
+    ///
+    /// Values of the JSON fields whose names are in `redacted_fields` are replaced with `***` at
+    /// any depth. Field names are compared case-insensitively. Logged bodies longer than
+    /// `max_body_length` bytes are truncated. Non-JSON bodies are labeled with their type and
+    /// size.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode, middleware::Logger };
+    /// use std::sync::{ Arc, Mutex };
+    ///
+    /// let logs = Arc::new(Mutex::new(Vec::new()));
+    /// let output = logs.clone();
+    ///
+    /// let logger = Logger::new()
+    ///   .with_body(&["password", "token"], 256)
+    ///   .with_output(move |line| output.lock().unwrap().push(line.to_string()));
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|_, res| { res.status(StatusCode::Ok).body_text("Welcome"); });
+    /// router.use_response_middleware(logger);
+    /// server.use_handler(router);
+    ///
+    /// let body = r#"{"user":"krusty","password":"hunter2"}"#;
+    /// let raw = format!(
+    ///   "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+    ///   body.len()
+    /// );
+    /// let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    /// server.dispatch(&mut request);
+    ///
+    /// let logs = logs.lock().unwrap();
+    ///
+    /// assert_eq!(logs[0], r#"POST / 200 body: {"password":"***","user":"krusty"}"#);
+    /// assert!(!logs[0].contains("hunter2"));
+    /// ```
+    pub fn with_body(mut self, redacted_fields: &[&str], max_body_length: usize) -> Self {
+        self.log_body = true;
+        self.redacted_fields = redacted_fields
+            .iter()
+            .map(|field| field.to_lowercase())
+            .collect();
+        self.max_body_length = max_body_length;
+        self
+    }
+
+    /// Sets the format of the log lines
+    ///
+    /// In the access log formats, the host is the IP address of the peer, the identity and the
+    /// user are `-`, and the size is the number of bytes of the response body at the time the
+    /// logger runs, or `-` if there is none. Request bodies are only logged in the short format.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode, middleware::{ Logger, logger::LogFormat } };
+    /// use std::sync::{ Arc, Mutex };
+    ///
+    /// let logs = Arc::new(Mutex::new(Vec::new()));
+    /// let output = logs.clone();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| { res.status(StatusCode::Ok).body_text("Hello"); });
+    /// router.use_response_middleware(
+    ///   Logger::new()
+    ///     .with_format(LogFormat::Combined)
+    ///     .with_output(move |line| output.lock().unwrap().push(line.to_string()))
+    /// );
+    /// server.use_handler(router);
+    ///
+    /// let raw = b"GET /?page=2 HTTP/1.1\r\nUser-Agent: curl/8.0\r\n\r\n";
+    /// let mut request = Request::parse_from_bytes(raw).unwrap();
+    /// server.dispatch(&mut request);
+    ///
+    /// let line = logs.lock().unwrap()[0].clone();
+    ///
+    /// // host ident user [day/month/year:hour:minute:second zone] "request" status bytes
+    /// let (start, rest) = line.split_once(" [").unwrap();
+    /// let (date, rest) = rest.split_once("] ").unwrap();
+    /// let (time, zone) = date.split_once(' ').unwrap();
+    /// let fields = time.split(['/', ':']).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(start, "0.0.0.0 - -");
+    /// assert_eq!(zone, "+0000");
+    /// assert_eq!(fields.len(), 6);
+    /// assert_eq!(fields[0].len(), 2);
+    /// assert!(["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"]
+    ///   .contains(&fields[1]));
+    /// assert!(fields.iter().enumerate().all(|(i, field)| {
+    ///   i == 1 || field.bytes().all(|byte| byte.is_ascii_digit())
+    /// }));
+    /// assert_eq!(rest, r#""GET /?page=2 HTTP/1.1" 200 5 "-" "curl/8.0""#);
+    /// ```
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the function that receives the log lines instead of the `log` facade
+    pub fn with_output(mut self, output: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    fn format_body(&self, body: &RequestBody) -> Option<String> {
+        let body = match body {
+            RequestBody::Json(json) => {
+                let mut json = json.clone();
+                self.redact(&mut json);
+                self.truncate(json.to_string())
+            }
+            RequestBody::Text(text) => {
+                let label = format!("(text, {} bytes) ", text.len());
+                label + &self.truncate(String::from_utf8_lossy(text).to_string())
+            }
+            RequestBody::Form(form) => {
+                let mut form = form.clone();
+                for (key, value) in form.iter_mut() {
+                    if self.redacted_fields.contains(&key.to_lowercase()) {
+                        *value = "***".to_string();
+                    }
+                }
+                self.truncate(format!("{:?}", form))
+            }
+            RequestBody::Bytes(bytes) => format!("(bytes, {} bytes)", bytes.len()),
+            RequestBody::None => {
+                return None;
+            }
+        };
+
+        Some(body)
+    }
+
+    fn redact(&self, json: &mut JsonValue) {
+        match json {
+            JsonValue::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.redacted_fields.contains(&key.to_lowercase()) {
+                        *value = JsonValue::String("***".to_string());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            JsonValue::Array(values) => {
+                values.iter_mut().for_each(|value| self.redact(value));
+            }
+            _ => {}
+        }
+    }
+
+    fn truncate(&self, mut body: String) -> String {
+        if body.len() > self.max_body_length {
+            let mut end = self.max_body_length;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+            body.push_str("...(truncated)");
+        }
+        body
+    }
+
+    /// Formats the Common Log Format line of the request
+    fn format_common(request: &Request, response: &mut Response) -> String {
+        let date = http_date::format_clf(SystemTime::now()).unwrap_or_else(|| "-".to_string());
+        let size = match response.body_len() {
+            0 => "-".to_string(),
+            size => size.to_string(),
+        };
+
+        format!(
+            "{} - - [{}] \"{}\" {} {}",
+            request.get_peer_addr().ip(),
+            date,
+            escape_quotes(&request.request_line()),
+            response.get_status(),
+            size
+        )
+    }
+}
+
+/// Escapes the quotes and backslashes of a quoted log field
+fn escape_quotes(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Middleware for Logger {
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult {
+        let line = match self.format {
+            LogFormat::Short => {
+                let mut line = format!(
+                    "{} {} {}",
+                    request.get_method(),
+                    request.get_path(),
+                    response.get_status()
+                );
+
+                if self.log_body {
+                    if let Some(body) = self.format_body(request.get_body()) {
+                        line.push_str(&format!(" body: {}", body));
+                    }
+                }
+
+                line
+            }
+            LogFormat::Common => Self::format_common(request, response),
+            LogFormat::Combined => {
+                let header = |name| {
+                    request.get_header(name).map_or("-".to_string(), |value| escape_quotes(value))
+                };
+
+                format!(
+                    "{} \"{}\" \"{}\"",
+                    Self::format_common(request, response),
+                    header("referer"),
+                    header("user-agent")
+                )
+            }
+        };
+
+        (self.output)(&line);
+
+        HandlerResult::Next
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Logger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmtResult {
+        write!(
+            f,
+            "Logger {{ format: {:?}, log_body: {}, redacted_fields: {:?}, max_body_length: {} }}",
+            self.format,
+            self.log_body,
+            self.redacted_fields,
+            self.max_body_length
+        )
+    }
+}
@@ -0,0 +1,106 @@
+//! A middleware for switching the server into maintenance mode at runtime
+
+use std::sync::{ atomic::{ AtomicBool, Ordering }, Arc };
+
+use crate::{ server::route_handler::HandlerResult, Middleware, Request, Response, StatusCode };
+
+/// A middleware that answers requests with `503 Service Unavailable` while maintenance mode is on
+///
+/// The responses have a `Retry-After` header with the given number of seconds. Requests to the
+/// excepted paths, like health checks, are still handled.
+///
+/// The flag is shared between the clones of the middleware, so a clone can be kept to toggle
+/// maintenance mode while the server is running. It should be added before the other handlers, so
+/// they don't run during maintenance.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::Maintenance };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+/// let maintenance = Maintenance::new(120).except("/health");
+///
+/// router.get(|_, res| {
+///   res.status(StatusCode::Ok).body_text("Hello");
+/// });
+/// router.route(krustie::HttpMethod::GET, "/health", |_, res| {
+///   res.status(StatusCode::Ok).body_text("OK");
+/// });
+/// server.use_handler(maintenance.clone());
+/// server.use_handler(router);
+///
+/// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::Ok);
+///
+/// maintenance.enable();
+///
+/// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// let response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_status(), StatusCode::ServiceUnavailable);
+/// assert_eq!(response.get_header("Retry-After").unwrap(), "120");
+///
+/// let mut request = Request::parse_from_bytes(b"GET /health HTTP/1.1\r\n\r\n").unwrap();
+/// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::Ok);
+///
+/// maintenance.disable();
+///
+/// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::Ok);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Maintenance {
+    enabled: Arc<AtomicBool>,
+    retry_after: u64,
+    excepted_paths: Vec<String>,
+}
+
+impl Maintenance {
+    /// Creates a new instance of Maintenance that is disabled
+    ///
+    /// `retry_after` is the number of seconds sent in the `Retry-After` header.
+    pub fn new(retry_after: u64) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            retry_after,
+            excepted_paths: Vec::new(),
+        }
+    }
+
+    /// Adds a path that is handled during maintenance, e.g. a health check
+    pub fn except(mut self, path: &str) -> Self {
+        self.excepted_paths.push(path.to_string());
+        self
+    }
+
+    /// Turns maintenance mode on
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Turns maintenance mode off
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true if maintenance mode is on
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+}
+
+impl Middleware for Maintenance {
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult {
+        if !self.is_enabled() || self.excepted_paths.contains(request.get_path()) {
+            return HandlerResult::Next;
+        }
+
+        response
+            .status(StatusCode::ServiceUnavailable)
+            .insert_header("Retry-After", &self.retry_after.to_string());
+
+        HandlerResult::End
+    }
+}
@@ -0,0 +1,92 @@
+//! A middleware for overriding the method of `POST` requests from HTML forms
+
+use crate::{
+    request::RequestBody,
+    server::route_handler::{ HandlerResult, RouteHandler },
+    HttpMethod,
+    Request,
+    Response,
+};
+
+/// A handler that changes the method of `POST` requests before they are routed
+///
+/// HTML forms can only send `GET` and `POST` requests, so the intended method is sent in the
+/// `X-HTTP-Method-Override` header or in a `_method` form field. The header is used if both are
+/// present. Only `PUT`, `PATCH` and `DELETE` are accepted, other values are ignored and the
+/// request stays a `POST`.
+///
+/// It needs to change the request, so it is a `RouteHandler` instead of a `Middleware`. It should
+/// be added before the routers.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Router, Request, StatusCode, middleware::MethodOverride };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+///
+/// router
+///   .post(|_, res| {
+///     res.status(StatusCode::Created).body_text("Created");
+///   })
+///   .delete(|_, res| {
+///     res.status(StatusCode::Ok).body_text("Deleted");
+///   });
+/// server.use_handler(MethodOverride);
+/// server.use_handler(router);
+///
+/// let body = "_method=DELETE";
+/// let raw = format!(
+///   "POST / HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+///   body.len()
+/// );
+/// let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+/// let mut response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_body(), b"Deleted");
+///
+/// // Only PUT, PATCH and DELETE can be used
+/// let raw = b"POST / HTTP/1.1\r\nX-HTTP-Method-Override: GET\r\n\r\n";
+/// let mut request = Request::parse_from_bytes(raw).unwrap();
+/// let mut response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_body(), b"Created");
+/// ```
+#[derive(Debug)]
+pub struct MethodOverride;
+
+impl MethodOverride {
+    /// Returns the method to use instead of `POST`, if an allowed one was sent
+    fn requested_method(request: &Request) -> Option<HttpMethod> {
+        let requested = match request.get_header("x-http-method-override") {
+            Some(header) => header.as_str(),
+            None =>
+                match request.get_body() {
+                    RequestBody::Form(fields) => fields.get("_method")?.as_str(),
+                    _ => {
+                        return None;
+                    }
+                }
+        };
+
+        match HttpMethod::try_from(requested.trim()) {
+            Ok(method @ (HttpMethod::PUT | HttpMethod::PATCH | HttpMethod::DELETE)) => Some(method),
+            _ => None,
+        }
+    }
+}
+
+impl RouteHandler for MethodOverride {
+    fn handle(&self, request: &mut Request, _: &mut Response, _: &[String]) -> HandlerResult {
+        if request.get_method() != &HttpMethod::POST {
+            return HandlerResult::Next;
+        }
+
+        if let Some(method) = Self::requested_method(request) {
+            request.set_method(method);
+        }
+
+        HandlerResult::Next
+    }
+}
@@ -1,17 +1,25 @@
 //! A middleware for rate limiting requests based on IP address
 
-use std::{ collections::HashMap, net::IpAddr, time::{ Duration, Instant } };
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{ Mutex, PoisonError },
+    time::{ Duration, Instant },
+};
 
 use crate::{ server::route_handler::HandlerResult, Middleware, StatusCode };
 
 /// A rate limiter middleware
 ///
 /// Limits the number of requests from an IP address based on the token number and token refill time.
+///
+/// Requests without a peer IP address, e.g. from a Unix socket, are not limited, since they would all
+/// share the `0.0.0.0` address.
 #[derive(Debug)]
 pub struct RateLimiter {
     token_number: u16,
     token_refill_duration: Duration,
-    requests: HashMap<IpAddr, (Instant, u16)>,
+    requests: Mutex<HashMap<IpAddr, (Instant, u16)>>,
 }
 
 impl RateLimiter {
@@ -32,14 +40,15 @@ impl RateLimiter {
         Self {
             token_number,
             token_refill_duration: Duration::from_millis(token_refill_ms),
-            requests: HashMap::new(),
+            requests: Mutex::new(HashMap::new()),
         }
     }
 
-    fn check(&mut self, ip: IpAddr) -> bool {
+    fn check(&self, ip: IpAddr) -> bool {
         let now: Instant = Instant::now();
+        let mut requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
 
-        let entry = self.requests.entry(ip).or_insert_with(|| (now, self.token_number));
+        let entry = requests.entry(ip).or_insert_with(|| (now, self.token_number));
 
         if now.duration_since(entry.0) >= self.token_refill_duration {
             // Duration passed, token refreshed
@@ -59,11 +68,17 @@ impl RateLimiter {
 
 impl Middleware for RateLimiter {
     fn middleware(
-        &mut self,
+        &self,
         request: &crate::Request,
         response: &mut crate::Response
     ) -> HandlerResult {
-        match Self::check(self, request.get_peer_addr().ip()) {
+        let ip = request.get_peer_addr().ip();
+
+        if ip.is_unspecified() {
+            return HandlerResult::Next;
+        }
+
+        match Self::check(self, ip) {
             true => HandlerResult::Next,
             false => {
                 response.status(StatusCode::TooManyRequests);
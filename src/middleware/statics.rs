@@ -1,6 +1,7 @@
 //! Static files middleware.
 //!
-//! This middleware serves static files from a specified folder.
+//! This middleware serves static files from a specified folder. Files can also be served from
+//! other sources, like assets embedded in the binary, see the `source` module.
 //!
 //! ## Supported Content Types
 //!
@@ -9,10 +10,216 @@
 //! - **File:** `pdf`, `zip`, `gzip`
 //! - **Media:** `mp3`, `wav`, `mp4`, `mpeg`, `webm`
 //! - **Font:** `woff`, `woff2`, `ttf`, `otf`, `eot`
+//!
+//! ## Partial Content
+//!
+//! Byte ranges requested with the `Range` header are served with `206 Partial Content`. Multiple
+//! ranges are sent as a `multipart/byteranges` body with a part for each range, after overlapping
+//! and adjacent ranges are merged. Requests with more than 16 ranges, or with ranges adding up to
+//! more than the file, get the full file instead. Files are served
+//! with `Accept-Ranges: bytes` to advertise it, while directory listings, which can't be requested
+//! in ranges, have `Accept-Ranges: none`. Responses include `ETag` and `Last-Modified` headers, so clients can send `If-Range` to only
+//! receive the range if the file is unchanged. Otherwise the full file is returned.
+//!
+//! ```rust
+//! use krustie::{ Server, Request, StatusCode, middleware::ServeStatic };
+//! use std::fs;
+//!
+//! let folder = std::env::temp_dir().join("krustie-statics-range");
+//! fs::create_dir_all(&folder).unwrap();
+//! fs::write(folder.join("hello.txt"), "Hello, World!").unwrap();
+//!
+//! let mut server = Server::create();
+//! server.use_handler(ServeStatic::new(folder.to_str().unwrap()));
+//!
+//! let mut request = Request::parse_from_bytes(b"GET /hello.txt HTTP/1.1\r\n\r\n").unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::Ok);
+//! assert_eq!(response.get_header("Accept-Ranges").unwrap(), "bytes");
+//!
+//! let raw = b"GET /hello.txt HTTP/1.1\r\nRange: bytes=0-4\r\n\r\n";
+//! let mut request = Request::parse_from_bytes(raw).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::PartialContent);
+//! assert_eq!(response.get_header("Content-Range").unwrap(), "bytes 0-4/13");
+//! assert_eq!(response.body_bytes(), b"Hello");
+//!
+//! // If-Range matches the current ETag, so the range is served
+//! let etag = response.get_header("ETag").unwrap().clone();
+//! let raw = format!("GET /hello.txt HTTP/1.1\r\nRange: bytes=7-\r\nIf-Range: {etag}\r\n\r\n");
+//! let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::PartialContent);
+//! assert_eq!(response.body_bytes(), b"World!");
+//!
+//! // If-Range doesn't match, so the range is ignored and the full file is served
+//! let raw = b"GET /hello.txt HTTP/1.1\r\nRange: bytes=7-\r\nIf-Range: \"outdated\"\r\n\r\n";
+//! let mut request = Request::parse_from_bytes(raw).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::Ok);
+//! assert_eq!(response.body_bytes(), b"Hello, World!");
+//!
+//! // A weak tag never matches If-Range, so the full file is served
+//! let raw = format!("GET /hello.txt HTTP/1.1\r\nRange: bytes=7-\r\nIf-Range: W/{etag}\r\n\r\n");
+//! let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+//!
+//! assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::Ok);
+//! ```
+//!
+//! Requesting multiple ranges
+//!
+//! ```rust
+//! use krustie::{ Server, Request, StatusCode, middleware::ServeStatic };
+//! use std::fs;
+//!
+//! let folder = std::env::temp_dir().join("krustie-statics-multirange");
+//! fs::create_dir_all(&folder).unwrap();
+//! fs::write(folder.join("hello.txt"), "Hello, World!").unwrap();
+//!
+//! let mut server = Server::create();
+//! server.use_handler(ServeStatic::new(folder.to_str().unwrap()));
+//!
+//! let raw = b"GET /hello.txt HTTP/1.1\r\nRange: bytes=0-4, -6\r\n\r\n";
+//! let mut request = Request::parse_from_bytes(raw).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::PartialContent);
+//! assert!(response.get_header("Content-Range").is_none());
+//!
+//! let content_type = response.get_header("Content-Type").unwrap().clone();
+//! let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+//! let body = String::from_utf8(response.body_bytes().to_vec()).unwrap();
+//!
+//! let parts = body
+//!   .strip_suffix(&format!("\r\n--{boundary}--\r\n"))
+//!   .unwrap()
+//!   .split(&format!("--{boundary}\r\n"))
+//!   .skip(1)
+//!   .map(|part| part.trim_end_matches("\r\n").split_once("\r\n\r\n").unwrap())
+//!   .collect::<Vec<_>>();
+//!
+//! assert_eq!(parts.len(), 2);
+//! assert_eq!(parts[0].0, "Content-Type: text/plain\r\nContent-Range: bytes 0-4/13");
+//! assert_eq!(parts[0].1, "Hello");
+//! assert_eq!(parts[1].0, "Content-Type: text/plain\r\nContent-Range: bytes 7-12/13");
+//! assert_eq!(parts[1].1, "World!");
+//!
+//! // Overlapping and adjacent ranges are merged into a single one
+//! let raw = b"GET /hello.txt HTTP/1.1\r\nRange: bytes=5-8, 0-2, 3-4, 6-7\r\n\r\n";
+//! let mut request = Request::parse_from_bytes(raw).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::PartialContent);
+//! assert_eq!(response.get_header("Content-Range").unwrap(), "bytes 0-8/13");
+//! assert_eq!(response.body_bytes(), b"Hello, Wo");
+//!
+//! // Too many ranges, or ranges requesting the file more than once, get the full file
+//! let many = (0..17).map(|i| format!("{i}-{i}")).collect::<Vec<_>>().join(", ");
+//! let repeated = vec!["0-12"; 2].join(", ");
+//!
+//! for ranges in [many, repeated] {
+//!   let raw = format!("GET /hello.txt HTTP/1.1\r\nRange: bytes={ranges}\r\n\r\n");
+//!   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+//!   let response = server.dispatch(&mut request);
+//!
+//!   assert_eq!(response.get_status(), StatusCode::Ok);
+//!   assert_eq!(response.body_bytes(), b"Hello, World!");
+//! }
+//! ```
+//!
+//! ## Precompressed Files
+//!
+//! If the `Accept-Encoding` header allows it, a `.br` or `.gz` sibling of the requested file is
+//! served instead, with the matching `Content-Encoding` and the content type of the original file.
+//! Brotli is preferred over gzip. Otherwise the original file is served. Files with a precompressed
+//! sibling are served with `Vary: Accept-Encoding` either way, so caches keep the variants apart.
+//!
+//! ```rust
+//! use krustie::{ Server, Request, StatusCode, middleware::{ ServeStatic, GzipEncoder } };
+//! use krustie::middleware::gzip::decode_body;
+//! use std::fs;
+//!
+//! let folder = std::env::temp_dir().join("krustie-statics-precompressed");
+//! fs::create_dir_all(&folder).unwrap();
+//! fs::write(folder.join("app.js"), "console.log(42);").unwrap();
+//! fs::write(folder.join("app.js.gz"), GzipEncoder::encode(b"console.log(42);").unwrap()).unwrap();
+//!
+//! let mut server = Server::create();
+//! server.use_handler(ServeStatic::new(folder.to_str().unwrap()));
+//!
+//! let raw = b"GET /app.js HTTP/1.1\r\nAccept-Encoding: gzip, deflate\r\n\r\n";
+//! let mut request = Request::parse_from_bytes(raw).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::Ok);
+//! assert_eq!(response.get_header("Content-Encoding").unwrap(), "gzip");
+//! assert_eq!(response.get_header("Content-Type").unwrap(), "text/javascript");
+//! assert_eq!(decode_body(response.body_bytes(), "gzip").unwrap(), b"console.log(42);");
+//!
+//! assert_eq!(response.get_header("Vary").unwrap(), "Accept-Encoding");
+//!
+//! let mut request = Request::parse_from_bytes(b"GET /app.js HTTP/1.1\r\n\r\n").unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_header("Content-Encoding"), None);
+//! assert_eq!(response.get_header("Vary").unwrap(), "Accept-Encoding");
+//! assert_eq!(response.body_bytes(), b"console.log(42);");
+//! ```
+//!
+//! ## Conditional Requests
+//!
+//! If the `If-None-Match` header matches the current `ETag` with the weak comparison, the file is
+//! not sent and `304 Not Modified` is returned instead. Without `If-None-Match`, the same applies
+//! if the file wasn't modified after the `If-Modified-Since` date.
+//!
+//! ```rust
+//! use krustie::{ Server, Request, StatusCode, middleware::ServeStatic };
+//! use std::fs;
+//!
+//! let folder = std::env::temp_dir().join("krustie-statics-etag");
+//! fs::create_dir_all(&folder).unwrap();
+//! fs::write(folder.join("hello.txt"), "Hello, World!").unwrap();
+//!
+//! let mut server = Server::create();
+//! server.use_handler(ServeStatic::new(folder.to_str().unwrap()));
+//!
+//! let mut request = Request::parse_from_bytes(b"GET /hello.txt HTTP/1.1\r\n\r\n").unwrap();
+//! let etag = server.dispatch(&mut request).get_header("ETag").unwrap().clone();
+//!
+//! let raw = format!("GET /hello.txt HTTP/1.1\r\nIf-None-Match: W/{etag}\r\n\r\n");
+//! let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+//! let response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::NotModified);
+//! assert!(response.body_bytes().is_empty());
+//!
+//! let mut request = Request::parse_from_bytes(b"GET /hello.txt HTTP/1.1\r\n\r\n").unwrap();
+//! let last_modified = server.dispatch(&mut request).get_header("Last-Modified").unwrap().clone();
+//!
+//! let raw = format!("GET /hello.txt HTTP/1.1\r\nIf-Modified-Since: {last_modified}\r\n\r\n");
+//! let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+//!
+//! assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::NotModified);
+//!
+//! let raw = b"GET /hello.txt HTTP/1.1\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n";
+//! let mut request = Request::parse_from_bytes(raw).unwrap();
+//!
+//! assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::Ok);
+//! ```
 
-use std::{ fs, path::PathBuf };
+use std::{
+    fs,
+    path::{ Component, Path, PathBuf },
+    time::{ SystemTime, UNIX_EPOCH },
+};
 
+use super::{ gzip::accepts_encoding, http_date };
 use crate::{
+    request::{ conditional::strong_compare, urlencoded },
     response::content_type::ContentType,
     server::route_handler::HandlerResult,
     Middleware,
@@ -21,6 +228,10 @@ use crate::{
     StatusCode,
 };
 
+pub mod source;
+
+pub use source::{ FileSource, FileSystem, InMemory, StaticFile };
+
 #[derive(Debug)]
 /// Serve static files from a specified folder.
 ///
@@ -35,7 +246,10 @@ use crate::{
 /// server.use_handler(statics);
 /// ```
 pub struct ServeStatic {
-    folder_path: String,
+    source: Box<dyn FileSource>,
+    folder_path: Option<String>,
+    max_age: Option<u64>,
+    autoindex: bool,
 }
 
 impl ServeStatic {
@@ -50,11 +264,182 @@ impl ServeStatic {
     /// ```
     pub fn new(folder_path: &str) -> ServeStatic {
         ServeStatic {
-            folder_path: folder_path.to_string(),
+            source: Box::new(FileSystem::new(folder_path)),
+            folder_path: Some(folder_path.to_string()),
+            max_age: None,
+            autoindex: false,
+        }
+    }
+
+    /// Creates a new instance of ServeStatic that serves the files of a source
+    ///
+    /// Ranges, entity tags and caching work the same as for files in a folder. Directory
+    /// listings are only rendered for folders, so `autoindex` has no effect.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, middleware::{ ServeStatic, statics::InMemory } };
+    ///
+    /// let assets = InMemory::new().file("index.html", &b"<h1>Hello</h1>"[..]);
+    /// let mut server = Server::create();
+    ///
+    /// server.use_handler(ServeStatic::from_source(assets).cache_control(3600));
+    /// ```
+    pub fn from_source(source: impl FileSource + 'static) -> ServeStatic {
+        ServeStatic {
+            source: Box::new(source),
+            folder_path: None,
+            max_age: None,
+            autoindex: false,
+        }
+    }
+
+    /// Sets the `Cache-Control` header of the served files to `public, max-age=<max_age>`
+    ///
+    /// By default, no `Cache-Control` header is set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Request, middleware::ServeStatic };
+    /// use std::fs;
+    ///
+    /// let folder = std::env::temp_dir().join("krustie-statics-cache");
+    /// fs::create_dir_all(&folder).unwrap();
+    /// fs::write(folder.join("hello.txt"), "Hello, World!").unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// server.use_handler(ServeStatic::new(folder.to_str().unwrap()).cache_control(3600));
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /hello.txt HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Cache-Control").unwrap(), "public, max-age=3600");
+    /// assert!(response.get_header("Last-Modified").unwrap().ends_with(" GMT"));
+    /// ```
+    pub fn cache_control(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Renders an HTML listing when the requested path is a directory without an `index.html`
+    ///
+    /// The listing has the name, the size and the modification date of each entry, with links to
+    /// them. Paths that leave the folder, also through symbolic links, are not listed. Disabled by
+    /// default, since it exposes the names of all files in the folder.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Request, StatusCode, middleware::ServeStatic };
+    /// use std::fs;
+    ///
+    /// let folder = std::env::temp_dir().join("krustie-statics-autoindex");
+    /// fs::create_dir_all(folder.join("docs/guides")).unwrap();
+    /// fs::write(folder.join("docs/readme.txt"), "Hello, World!").unwrap();
+    /// fs::write(folder.join("docs/a <b>.txt"), "Escaped").unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// server.use_handler(ServeStatic::new(folder.to_str().unwrap()).autoindex(true));
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /docs/ HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    /// let listing = String::from_utf8(response.body_bytes().to_vec()).unwrap();
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Ok);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/html");
+    /// assert_eq!(response.get_header("Accept-Ranges").unwrap(), "none");
+    /// assert!(listing.contains(r#"<a href="/docs/guides/">guides/</a>"#));
+    /// assert!(listing.contains(r#"<a href="/docs/readme.txt">readme.txt</a>"#));
+    /// assert!(listing.contains(r#"<a href="/docs/a%20%3Cb%3E.txt">a &lt;b&gt;.txt</a>"#));
+    /// assert!(listing.contains("<td>13</td>"));
+    /// assert!(listing.contains(" GMT</td>"));
+    ///
+    /// // Traversal outside of the folder is not listed
+    /// let mut request = Request::parse_from_bytes(b"GET /docs/../../ HTTP/1.1\r\n\r\n").unwrap();
+    /// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::NotFound);
+    ///
+    /// // Listings are off by default
+    /// let mut server = Server::create();
+    /// server.use_handler(ServeStatic::new(folder.to_str().unwrap()));
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /docs/ HTTP/1.1\r\n\r\n").unwrap();
+    /// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::NotFound);
+    /// ```
+    pub fn autoindex(mut self, enabled: bool) -> Self {
+        self.autoindex = enabled;
+        self
+    }
+
+    /// Returns the directory of the request path, if it is inside of the folder
+    fn directory(&self, request: &Request) -> Option<PathBuf> {
+        let folder = fs::canonicalize(self.folder_path.as_ref()?).ok()?;
+        let mut path = folder.clone();
+
+        for segment in request.get_path_array().iter().filter(|segment| !segment.is_empty()) {
+            let segment = urlencoded::decode_path(segment.as_bytes());
+            let mut components = Path::new(&segment).components();
+
+            // Each segment has to be a single plain name, so `..` and absolute paths are rejected
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) => path.push(name),
+                _ => {
+                    return None;
+                }
+            }
         }
+
+        // Symbolic links are resolved, so they can't point outside of the folder
+        let path = fs::canonicalize(path).ok()?;
+
+        (path.starts_with(&folder) && path.is_dir()).then_some(path)
     }
 
-    fn get_extension(&self, path: &PathBuf) -> Result<String, String> {
+    fn render_listing(request_path: &str, directory: &Path) -> Option<String> {
+        let mut entries = fs::read_dir(directory)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                Some((name, metadata))
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let base = request_path.trim_end_matches('/');
+        let title = escape_html(&format!("{base}/"));
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><title>Index of {title}</title></head>\n<body>\n\
+            <h1>Index of {title}</h1>\n<table>\n<tr><th>Name</th><th>Size</th><th>Last Modified</th></tr>\n"
+        );
+
+        if !base.is_empty() {
+            html.push_str("<tr><td><a href=\"../\">../</a></td><td></td><td></td></tr>\n");
+        }
+
+        for (name, metadata) in entries {
+            let suffix = if metadata.is_dir() { "/" } else { "" };
+            let size = if metadata.is_dir() { String::from("-") } else { metadata.len().to_string() };
+            let modified = metadata.modified().ok().and_then(http_date::format).unwrap_or_default();
+
+            html.push_str(
+                &format!(
+                    "<tr><td><a href=\"{base}/{href}{suffix}\">{name}{suffix}</a></td><td>{size}</td><td>{modified}</td></tr>\n",
+                    href = urlencoded::encode_path(&name),
+                    name = escape_html(&name)
+                )
+            );
+        }
+
+        html.push_str("</table>\n</body>\n</html>\n");
+
+        Some(html)
+    }
+
+    fn get_extension(&self, path: &Path) -> Result<String, String> {
         match path.extension() {
             Some(ext) =>
                 match ext.to_str() {
@@ -68,17 +453,222 @@ impl ServeStatic {
             }
         }
     }
+
+    /// Returns a precompressed sibling of the file and its coding, if the `Accept-Encoding`
+    /// header allows it
+    fn precompressed(&self, request: &Request, path: &str) -> Option<(StaticFile, &'static str)> {
+        let accept_encoding = request.get_header("accept-encoding")?;
+
+        [("br", "br"), ("gz", "gzip")].into_iter().find_map(|(extension, coding)| {
+            if !accepts_encoding(accept_encoding, coding) {
+                return None;
+            }
+
+            self.source.read(&format!("{path}.{extension}")).map(|variant| (variant, coding))
+        })
+    }
+
+    /// Returns true if the file has a precompressed sibling
+    fn has_precompressed(&self, path: &str) -> bool {
+        ["br", "gz"].into_iter().any(|extension| self.source.exists(&format!("{path}.{extension}")))
+    }
+
+    /// Returns true if the file wasn't modified since the date of the `If-Modified-Since` header
+    ///
+    /// The header is ignored if the request has an `If-None-Match` header.
+    fn not_modified_since(request: &Request, modified: Option<SystemTime>) -> bool {
+        if request.get_header("if-none-match").is_some() {
+            return false;
+        }
+
+        let since = request.get_header("if-modified-since").and_then(|date| http_date::parse(date));
+        let modified = modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs());
+
+        match (since, modified) {
+            (Some(since), Some(modified)) => modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the `If-Range` header is missing or matches the current file
+    ///
+    /// Entity tags are compared with the strong comparison, so weak tags never match.
+    fn if_range_matches(request: &Request, etag: &str, last_modified: Option<&str>) -> bool {
+        match request.get_header("if-range") {
+            Some(if_range) if if_range.starts_with("W/") || if_range.starts_with('"') => {
+                strong_compare(if_range, etag)
+            }
+            Some(if_range) => Some(if_range.as_str()) == last_modified,
+            None => true,
+        }
+    }
+}
+
+/// Maximum number of ranges of a `Range` header that are served
+const MAX_RANGES: usize = 16;
+
+/// Byte ranges requested with the `Range` header
+enum ByteRange {
+    /// Inclusive starts and ends of the satisfiable ranges, sorted and merged
+    Satisfiable(Vec<(usize, usize)>),
+    NotSatisfiable,
+    /// The header is malformed or asks for too much, so the full file is served
+    Ignored,
+}
+
+impl ByteRange {
+    fn parse(header: &str, length: usize) -> Self {
+        let Some(specs) = header.trim().strip_prefix("bytes=") else {
+            return Self::Ignored;
+        };
+
+        if specs.split(',').count() > MAX_RANGES {
+            return Self::Ignored;
+        }
+
+        let mut ranges = Vec::new();
+
+        for spec in specs.split(',') {
+            match Self::parse_spec(spec.trim(), length) {
+                Some(Some(range)) => ranges.push(range),
+                // Unsatisfiable ranges are left out if another range is satisfiable
+                Some(None) => (),
+                None => {
+                    return Self::Ignored;
+                }
+            }
+        }
+
+        if ranges.is_empty() {
+            return Self::NotSatisfiable;
+        }
+
+        // Overlapping ranges could request the same bytes many times
+        let total = ranges.iter().map(|(start, end)| end - start + 1).sum::<usize>();
+
+        if total > length {
+            return Self::Ignored;
+        }
+
+        Self::Satisfiable(Self::merge(ranges))
+    }
+
+    /// Sorts the ranges and merges the ones that overlap or are adjacent
+    fn merge(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        merged
+    }
+
+    /// Parses a single range, returning `None` if it is malformed and `Some(None)` if it is not
+    /// satisfiable
+    fn parse_spec(spec: &str, length: usize) -> Option<Option<(usize, usize)>> {
+        let (start, end) = spec.split_once('-')?;
+
+        let (start, end) = match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end),
+            (Ok(start), Err(_)) if end.is_empty() => (start, usize::MAX),
+            (Err(_), Ok(suffix)) if start.is_empty() => {
+                if suffix == 0 {
+                    return Some(None);
+                }
+                (length.saturating_sub(suffix), usize::MAX)
+            }
+            _ => {
+                return None;
+            }
+        };
+
+        if start >= length {
+            return Some(None);
+        }
+
+        Some(Some((start, end.min(length - 1))))
+    }
+}
+
+/// Returns a `multipart/byteranges` body with a part for each range, and its boundary
+fn byteranges_body(
+    content: &[u8],
+    ranges: &[(usize, usize)],
+    content_type: &ContentType
+) -> (Vec<u8>, String) {
+    let length = content.len();
+    let mut boundary = String::from("KRUSTIE_BYTERANGES");
+
+    // The boundary can't appear in the parts
+    while content.windows(boundary.len()).any(|window| window == boundary.as_bytes()) {
+        boundary.push('_');
+    }
+
+    let mut body = Vec::new();
+
+    for (start, end) in ranges {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: bytes {start}-{end}/{length}\r\n\r\n"
+            ).as_bytes()
+        );
+        body.extend_from_slice(&content[*start..=*end]);
+        body.extend_from_slice(b"\r\n");
+    }
+
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    (body, boundary)
+}
+
+/// Escapes the characters that have a meaning in HTML
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
 }
 
 impl Middleware for ServeStatic {
-    fn middleware(&mut self, request: &Request, response: &mut Response) -> HandlerResult {
+    fn middleware(&self, request: &Request, response: &mut Response) -> HandlerResult {
+        // A route of a router before this handler has answered the request already
+        if request.matched_route().is_some() {
+            return HandlerResult::Next;
+        }
+
+        if let Some(directory) = self.autoindex.then(|| self.directory(request)).flatten() {
+            if directory.join("index.html").is_file() {
+                return HandlerResult::Next;
+            }
+
+            return match Self::render_listing(request.get_path(), &directory) {
+                Some(listing) => {
+                    response
+                        .status(StatusCode::Ok)
+                        .insert_header("Accept-Ranges", "none")
+                        .body(listing.into_bytes(), ContentType::Html);
+                    HandlerResult::End
+                }
+                None => HandlerResult::Next,
+            };
+        }
+
         let file_name = &request.get_path_array()[0];
 
-        let path = PathBuf::from(&self.folder_path).join(file_name);
-        let extension = match self.get_extension(&path) {
+        let extension = match self.get_extension(Path::new(file_name)) {
             Ok(ext) => ext,
             Err(err) => {
-                eprintln!("{}", err);
+                log::debug!("{}", err);
                 return HandlerResult::Next;
             }
         };
@@ -90,16 +680,80 @@ impl Middleware for ServeStatic {
             return HandlerResult::End;
         }
 
-        match fs::read(&path) {
-            Ok(content) => {
-                response.status(StatusCode::Ok).body(content, content_type.unwrap());
-                return HandlerResult::End;
+        let (file, content_encoding) = match self.precompressed(request, file_name) {
+            Some((variant, coding)) => (Some(variant), Some(coding)),
+            None => (self.source.read(file_name), None),
+        };
+
+        let Some(file) = file else {
+            log::debug!("Failed to read file: {:?}", file_name);
+            return HandlerResult::Next;
+        };
+
+        let etag = file.etag().to_string();
+        let modified = file.modified;
+        let last_modified = modified.and_then(http_date::format);
+        let content = file.content;
+        let length = content.len();
+
+        response.insert_header("Accept-Ranges", "bytes").insert_header("ETag", &etag);
+
+        if let Some(coding) = content_encoding {
+            response.insert_header("Content-Encoding", coding);
+        }
+
+        // The response depends on `Accept-Encoding` even when the original file is served
+        if content_encoding.is_some() || self.has_precompressed(file_name) {
+            response.add_vary("Accept-Encoding");
+        }
+
+        if let Some(last_modified) = &last_modified {
+            response.insert_header("Last-Modified", last_modified);
+        }
+
+        if let Some(max_age) = self.max_age {
+            response.insert_header("Cache-Control", &format!("public, max-age={max_age}"));
+        }
+
+        if request.if_none_match_contains(&etag) || Self::not_modified_since(request, modified) {
+            response.status(StatusCode::NotModified);
+            return HandlerResult::End;
+        }
+
+        let range = match request.get_header("range") {
+            Some(range) if Self::if_range_matches(request, &etag, last_modified.as_deref()) => {
+                ByteRange::parse(range, length)
             }
-            Err(_) => {
-                eprintln!("Failed to read file: {:?}", path);
-                return HandlerResult::Next;
+            _ => ByteRange::Ignored,
+        };
+
+        match range {
+            ByteRange::Satisfiable(ranges) if ranges.len() == 1 => {
+                let (start, end) = ranges[0];
+
+                response
+                    .status(StatusCode::PartialContent)
+                    .insert_header("Content-Range", &format!("bytes {start}-{end}/{length}"))
+                    .body(content[start..=end].to_vec(), content_type.unwrap());
+            }
+            ByteRange::Satisfiable(ranges) => {
+                let (body, boundary) = byteranges_body(&content, &ranges, &content_type.unwrap());
+
+                response
+                    .status(StatusCode::PartialContent)
+                    .body(body, ContentType::Other(format!("multipart/byteranges; boundary={boundary}")));
+            }
+            ByteRange::NotSatisfiable => {
+                response
+                    .status(StatusCode::RangeNotSatisfiable)
+                    .insert_header("Content-Range", &format!("bytes */{length}"));
+            }
+            ByteRange::Ignored => {
+                response.status(StatusCode::Ok).body_shared(content, content_type.unwrap());
             }
         }
+
+        return HandlerResult::End;
     }
 }
 
@@ -0,0 +1,193 @@
+//! This module contains the sources of the files served by `ServeStatic`.
+//!
+//! Files are read from the file system by default. Assets embedded in the binary, e.g. with
+//! `include_bytes!`, can be served from an `InMemory` source with the same features.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs,
+    hash::{ DefaultHasher, Hash, Hasher },
+    path::PathBuf,
+    sync::Arc,
+    time::{ SystemTime, UNIX_EPOCH },
+};
+
+/// A file of a `FileSource`
+#[derive(Debug, Clone)]
+pub struct StaticFile {
+    /// The content of the file
+    pub content: Arc<[u8]>,
+    /// The time the file was last modified, if it is known
+    ///
+    /// It is sent as `Last-Modified`.
+    pub modified: Option<SystemTime>,
+    etag: Arc<str>,
+}
+
+impl StaticFile {
+    /// Creates a file and computes its `ETag`
+    ///
+    /// The `ETag` is built from the length and the modification time. Without a modification
+    /// time, the content is hashed, so sources that keep their files should create them once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::middleware::statics::StaticFile;
+    /// use std::{ sync::Arc, time::{ Duration, UNIX_EPOCH } };
+    ///
+    /// let modified = UNIX_EPOCH + Duration::from_secs(0x65f1c2a0);
+    /// let file = StaticFile::new(Arc::from(&b"Hello, World!"[..]), Some(modified));
+    ///
+    /// assert_eq!(file.etag(), "\"d-65f1c2a0\"");
+    /// ```
+    pub fn new(content: Arc<[u8]>, modified: Option<SystemTime>) -> Self {
+        let modified_secs = modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs());
+
+        let version = modified_secs.unwrap_or_else(|| {
+            let mut hasher = DefaultHasher::new();
+            content.hash(&mut hasher);
+            hasher.finish()
+        });
+
+        let etag = Arc::from(format!("\"{:x}-{:x}\"", content.len(), version));
+
+        Self { content, modified, etag }
+    }
+
+    /// Returns the `ETag` of the file, e.g. `"d-65f1c2a0"`
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+}
+
+/// A source of the files served by `ServeStatic`
+///
+/// Paths are relative to the root of the source, e.g. `hello.txt`, and precompressed variants are
+/// requested with their extension, e.g. `app.js.gz`.
+pub trait FileSource: Debug + Send + Sync {
+    /// Returns the file at the path, or `None` if there is no such file
+    fn read(&self, path: &str) -> Option<StaticFile>;
+
+    /// Returns true if there is a file at the path
+    ///
+    /// Reads the file by default, sources that can check it without reading should override it.
+    fn exists(&self, path: &str) -> bool {
+        self.read(path).is_some()
+    }
+}
+
+/// Files in a folder of the file system
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    folder: PathBuf,
+}
+
+impl FileSystem {
+    /// Creates a source for the files in the folder
+    pub fn new(folder_path: &str) -> Self {
+        Self { folder: PathBuf::from(folder_path) }
+    }
+}
+
+impl FileSource for FileSystem {
+    fn read(&self, path: &str) -> Option<StaticFile> {
+        let path = self.folder.join(path);
+        let metadata = fs::metadata(&path).ok().filter(|metadata| metadata.is_file())?;
+        let content = fs::read(&path).ok()?;
+
+        Some(StaticFile::new(Arc::from(content), metadata.modified().ok()))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.folder.join(path).is_file()
+    }
+}
+
+/// Files kept in memory, e.g. assets embedded in the binary
+///
+/// The contents are shared with the responses, so they are not copied for each request.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Request, StatusCode, middleware::{ ServeStatic, statics::InMemory } };
+///
+/// let assets = InMemory::new()
+///   .file("index.html", &b"<h1>Krusty Burger</h1>"[..])
+///   .file("style.css", &b"h1 { color: red; }"[..]);
+///
+/// let mut server = Server::create();
+/// server.use_handler(ServeStatic::from_source(assets));
+///
+/// let mut request = Request::parse_from_bytes(b"GET /style.css HTTP/1.1\r\n\r\n").unwrap();
+/// let response = server.dispatch(&mut request);
+/// let etag = response.get_header("ETag").unwrap().clone();
+///
+/// assert_eq!(response.get_status(), StatusCode::Ok);
+/// assert_eq!(response.get_header("Content-Type").unwrap(), "text/css");
+/// assert_eq!(response.body_bytes(), b"h1 { color: red; }");
+///
+/// let raw = format!("GET /style.css HTTP/1.1\r\nRange: bytes=0-1\r\nIf-Range: {etag}\r\n\r\n");
+/// let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+/// let response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_status(), StatusCode::PartialContent);
+/// assert_eq!(response.body_bytes(), b"h1");
+///
+/// let raw = format!("GET /style.css HTTP/1.1\r\nIf-None-Match: {etag}\r\n\r\n");
+/// let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+///
+/// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::NotModified);
+///
+/// let mut request = Request::parse_from_bytes(b"GET /missing.css HTTP/1.1\r\n\r\n").unwrap();
+///
+/// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::NotFound);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InMemory {
+    files: HashMap<String, StaticFile>,
+}
+
+impl InMemory {
+    /// Creates an empty source
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file without a modification time
+    ///
+    /// Its `ETag` is computed from the content once, when it is added.
+    pub fn file(mut self, path: &str, content: impl Into<Arc<[u8]>>) -> Self {
+        let file = StaticFile::new(content.into(), None);
+
+        self.files.insert(path.trim_start_matches('/').to_string(), file);
+        self
+    }
+
+    /// Adds a file with a modification time, which is sent as `Last-Modified`
+    pub fn file_modified(
+        mut self,
+        path: &str,
+        content: impl Into<Arc<[u8]>>,
+        modified: SystemTime
+    ) -> Self {
+        let file = StaticFile::new(content.into(), Some(modified));
+
+        self.files.insert(path.trim_start_matches('/').to_string(), file);
+        self
+    }
+}
+
+impl FileSource for InMemory {
+    fn read(&self, path: &str) -> Option<StaticFile> {
+        self.files.get(path).cloned()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+}
@@ -10,22 +10,59 @@ use std::{
     collections::HashMap,
     fmt::{ Debug, Display, Formatter, Result as fResult },
     net::{ IpAddr, Ipv4Addr, SocketAddr },
+    sync::Mutex,
+    time::Instant,
 };
-use self::{ http_method::HttpMethod, request_line::RequestLine };
+use self::{
+    body::JsonOptions,
+    http_method::HttpMethod,
+    multipart::ConnectionBody,
+    request_line::RequestLine,
+};
+use crate::{ json::JsonValue, Response, StatusCode };
 
-pub use body::RequestBody;
+pub use body::{ BodyError, RequestBody };
+pub use builder::RequestBuilder;
+pub use json_body::JsonBodyError;
+pub use query::QueryError;
+pub use trace_context::TraceContext;
 
 pub mod body;
+pub mod builder;
 pub mod http_method;
+pub mod json_body;
+pub mod multipart;
+pub mod ndjson;
+pub mod query;
+pub mod trace_context;
+mod authorization;
+pub(crate) mod conditional;
+mod cookies;
+pub(crate) mod forwarded;
 pub(crate) mod parser;
 mod request_line;
+pub(crate) mod urlencoded;
 
 /// Represents the HTTP request
 pub struct Request {
     request: RequestLine,
     headers: HashMap<String, String>,
+    raw_headers: HashMap<String, Vec<u8>>,
     body: RequestBody,
+    /// The received body, `None` if the parsed body holds it
+    raw_body: Option<Vec<u8>>,
+    /// The length of a body that the parser left on the connection to be streamed
+    unread_body_length: Option<usize>,
+    /// The body that is read from the connection while the request is handled
+    body_reader: Option<Mutex<ConnectionBody>>,
     peer_addr: SocketAddr,
+    params: HashMap<String, String>,
+    route: Vec<String>,
+    matched_route: Option<String>,
+    deadline: Option<Instant>,
+    secure: bool,
+    forwarded_host: Option<String>,
+    json_options: JsonOptions,
 }
 
 impl Request {
@@ -60,9 +97,79 @@ impl Request {
         self.headers.get(key)
     }
 
+    /// Returns true if the request has the header
+    ///
+    /// Header names are case-insensitive.
+    pub fn has_header(&self, key: &str) -> bool {
+        self.headers.contains_key(&key.to_lowercase())
+    }
+
+    /// Returns the headers whose names start with the prefix, without cloning them
+    ///
+    /// The prefix is case-insensitive and the names are returned in lowercase, as they are
+    /// stored. The headers are not returned in a specific order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nX-Forwarded-For: 10.0.0.1\r\nX-Forwarded-Proto: https\r\nX-Request-Id: 42\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// let mut forwarded = request
+    ///   .headers_with_prefix("X-Forwarded-")
+    ///   .map(|(key, value)| format!("{key}={value}"))
+    ///   .collect::<Vec<_>>();
+    /// forwarded.sort();
+    ///
+    /// assert_eq!(forwarded, ["x-forwarded-for=10.0.0.1", "x-forwarded-proto=https"]);
+    /// assert!(request.has_header("X-Request-Id"));
+    /// assert!(!request.has_header("X-Forwarded-Host"));
+    /// ```
+    pub fn headers_with_prefix(
+        &self,
+        prefix: &str
+    ) -> impl Iterator<Item = (&String, &String)> + '_ {
+        let prefix = prefix.to_lowercase();
+
+        self.headers.iter().filter(move |(key, _)| key.starts_with(&prefix))
+    }
+
+    /// Returns the value of the header key as bytes
+    ///
+    /// Header values that aren't valid UTF-8 are decoded lossily by `get_header`, while this
+    /// method returns their original bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nX-City: Montr\xe9al\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.get_header("x-city").unwrap(), "Montr\u{FFFD}al");
+    /// assert_eq!(request.get_header_bytes("x-city").unwrap(), b"Montr\xe9al");
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nX-City: Montreal\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.get_header_bytes("x-city").unwrap(), b"Montreal");
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nX-City: Mont\x01real\r\n\r\n";
+    /// assert!(Request::parse_from_bytes(raw).is_err());
+    /// ```
+    pub fn get_header_bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.raw_headers.get(key) {
+            Some(value) => Some(value),
+            None => self.headers.get(key).map(|value| value.as_bytes()),
+        }
+    }
+
     /// Returns the body of the HTTP request
     ///
-    /// The body can be of type `Text`, `Json`, `Form` or `None`
+    /// The body can be of type `Text`, `Json`, `Form`, `Bytes` or `None`
     ///
     /// # Example
     ///
@@ -87,6 +194,232 @@ impl Request {
         &self.body
     }
 
+    /// Replaces the parsed body of the request
+    ///
+    /// It is meant for middlewares that transform the body, like decrypting or normalizing it,
+    /// before the controllers run. The raw body is kept as it was received, so `body_text` and
+    /// `to_raw_http` are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, request::RequestBody };
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello";
+    /// let mut request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// request.set_body(RequestBody::Text(b"bye".to_vec()));
+    ///
+    /// assert!(matches!(request.get_body(), RequestBody::Text(text) if text == b"bye"));
+    /// assert_eq!(request.body_text().unwrap(), "hello");
+    /// ```
+    pub fn set_body(&mut self, body: RequestBody) {
+        let previous = std::mem::replace(&mut self.body, body);
+
+        // The received bytes were held by the replaced body
+        if self.raw_body.is_none() {
+            if let RequestBody::Text(bytes) | RequestBody::Bytes(bytes) = previous {
+                self.raw_body = Some(bytes);
+            }
+        }
+    }
+
+    /// Returns the body length declared by the `Content-Length` header
+    ///
+    /// It is read from the header without looking at the body, e.g. for enforcing quotas in a
+    /// middleware. Returns `None` if there is no `Content-Length` header or it is not a number.
+    /// Received requests with a malformed `Content-Length` are rejected with `400 Bad Request`
+    /// before they are handled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, StatusCode };
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nHello";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.content_length(), Some(5));
+    /// assert!(!request.body_is_empty());
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(request.content_length(), None);
+    /// assert!(request.body_is_empty());
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Length: 5 bytes\r\n\r\nHello";
+    /// let err = Request::parse_from_bytes(raw).unwrap_err();
+    ///
+    /// assert_eq!(err.status_code(), StatusCode::BadRequest);
+    ///
+    /// let request = Request::builder().header("Content-Length", "-1").build();
+    ///
+    /// assert_eq!(request.content_length(), None);
+    /// ```
+    pub fn content_length(&self) -> Option<usize> {
+        self.get_header("content-length")?.parse().ok()
+    }
+
+    /// Returns true if the request was received without a body
+    pub fn body_is_empty(&self) -> bool {
+        self.raw_body().is_empty() && self.body_reader.is_none()
+    }
+
+    /// Returns the raw body of the HTTP request as a UTF-8 string
+    ///
+    /// The body is returned as it is sent, regardless of the content type it is parsed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BodyError::Empty` if the request has no body and `BodyError::InvalidUtf8` if the
+    /// body is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, request::BodyError };
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 12\r\n\r\nHello, Krust";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.body_text().unwrap(), "Hello, Krust");
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\r\n{\"a\":1}";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.body_text().unwrap(), "{\"a\":1}");
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n\xff\xfe";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.body_text(), Err(BodyError::InvalidUtf8));
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(request.body_text(), Err(BodyError::Empty));
+    /// ```
+    pub fn body_text(&self) -> Result<&str, BodyError> {
+        let body = self.raw_body();
+
+        if body.is_empty() {
+            return Err(BodyError::Empty);
+        }
+
+        std::str::from_utf8(body).map_err(|_| BodyError::InvalidUtf8)
+    }
+
+    /// Serializes the request back into raw HTTP
+    ///
+    /// Header names are lowercase and sorted, and the body is written as it was received, so a
+    /// parsed request can be parsed again into the same request.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"POST /users?active=true HTTP/1.1\r\ncontent-length: 5\r\ncontent-type: text/plain\r\n\r\nHello";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.to_raw_http(), raw);
+    /// assert_eq!(request.to_string().as_bytes(), raw);
+    ///
+    /// let raw = b"GET /users HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// let reparsed = Request::parse_from_bytes(&request.to_raw_http()).unwrap();
+    ///
+    /// assert_eq!(reparsed.get_path(), "/users");
+    /// assert_eq!(reparsed.get_headers(), request.get_headers());
+    /// ```
+    pub fn to_raw_http(&self) -> Vec<u8> {
+        let mut raw = format!("{}\r\n", self.request).into_bytes();
+        let mut keys = self.headers.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        for key in keys {
+            raw.extend_from_slice(key.as_bytes());
+            raw.extend_from_slice(b": ");
+            raw.extend_from_slice(self.get_header_bytes(key).unwrap_or_default());
+            raw.extend_from_slice(b"\r\n");
+        }
+
+        raw.extend_from_slice(b"\r\n");
+        raw.extend_from_slice(self.raw_body());
+        raw
+    }
+
+    /// Returns the request line, e.g. `GET /users?page=2 HTTP/1.1`
+    pub(crate) fn request_line(&self) -> String {
+        self.request.to_string()
+    }
+
+    /// Returns the body as it was received
+    pub(crate) fn raw_body(&self) -> &[u8] {
+        match (&self.raw_body, &self.body) {
+            (Some(bytes), _) => bytes,
+            (None, RequestBody::Text(bytes) | RequestBody::Bytes(bytes)) => bytes,
+            (None, _) => &[],
+        }
+    }
+
+    /// Returns the number of bytes of the body as it was received
+    pub(crate) fn raw_body_len(&self) -> usize {
+        match self.body_reader {
+            Some(_) => self.content_length().unwrap_or(0),
+            None => self.raw_body().len(),
+        }
+    }
+
+    /// Returns the value at the JSON Pointer (RFC 6901) of a JSON body
+    ///
+    /// Returns `None` if the body is not JSON or nothing is found at the pointer.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let body = r#"{"user":{"name":"Krusty","roles":["admin"]}}"#;
+    /// let raw = format!(
+    ///   "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+    ///   body.len()
+    /// );
+    /// let request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(request.json_pointer("/user/name").unwrap(), "Krusty");
+    /// assert_eq!(request.json_pointer("/user/roles/0").unwrap(), "admin");
+    /// assert!(request.json_pointer("/user/age").is_none());
+    /// ```
+    pub fn json_pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        match &self.body {
+            RequestBody::Json(json) => json.pointer(pointer),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a top-level field of a JSON body
+    ///
+    /// Returns `None` if the body is not a JSON object or the field doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, Response };
+    ///
+    /// fn post(request: &Request, response: &mut Response) {
+    ///   match request.json_get("name") {
+    ///     Some(name) => { response.body_text(&format!("Hello, {name}")); }
+    ///     None => { response.body_text("Hello, stranger"); }
+    ///   }
+    /// }
+    /// ```
+    pub fn json_get(&self, key: &str) -> Option<&JsonValue> {
+        match &self.body {
+            RequestBody::Json(json) => json.get(key),
+            _ => None,
+        }
+    }
+
     /// Returns the peer address of the HTTP request
     ///
     /// The peer address is the address of the client that made the request
@@ -112,14 +445,228 @@ impl Request {
     }
 
     /// Returns the path of the HTTP request as a String
+    ///
+    /// The query string is not included in the path.
     pub fn get_path(&self) -> &String {
         self.request.get_path()
     }
 
+    /// Returns the raw query string of the HTTP request, without the `?`
+    pub(crate) fn get_query(&self) -> Option<&String> {
+        self.request.get_query()
+    }
+
+    /// Returns the query parameters of the HTTP request
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET /search?q=krusty+burger&page=2 HTTP/1.1\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.get_path(), "/search");
+    /// assert_eq!(request.get_query_params().len(), 2);
+    /// assert_eq!(request.get_query_param("q").unwrap(), "krusty burger");
+    /// assert_eq!(request.get_query_param("page").unwrap(), "2");
+    /// ```
+    pub fn get_query_params(&self) -> &HashMap<String, String> {
+        self.request.get_query_params()
+    }
+
+    /// Returns the value of a query parameter
+    pub fn get_query_param(&self, key: &str) -> Option<&String> {
+        self.request.get_query_params().get(key)
+    }
+
+    /// Creates a builder for a request
+    ///
+    /// It is useful for testing controllers and middlewares with `Server::dispatch`.
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::default()
+    }
+
+    /// Returns the value of a route parameter
+    ///
+    /// Route parameters are defined with a `:` prefix when adding a router (e.g. `:id`). See
+    /// `Router::use_router` for details.
+    ///
+    /// The path is split into segments before it is percent-decoded, so an encoded slash (`%2F`)
+    /// stays in the segment. The returned value is decoded, and `+` is not treated as a space.
+    /// Constraints are checked against the decoded value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, Response };
+    ///
+    /// fn get(request: &Request, response: &mut Response) {
+    ///   let user_id = request.get_param("id");
+    /// }
+    /// ```
+    ///
+    /// Parameters with an encoded slash
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.route(krustie::HttpMethod::GET, "/files/:name", |req, res| {
+    ///   let name = req.get_param("name").unwrap().clone();
+    ///   res.status(StatusCode::Ok).body_text(&name);
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /files/a%2Fb+c HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Ok);
+    /// assert_eq!(response.get_body(), b"a/b+c");
+    ///
+    /// // Escapes without two hex digits are kept as they are
+    /// let mut request = Request::parse_from_bytes(b"GET /files/%+1%2 HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(server.dispatch(&mut request).body_bytes(), b"%+1%2");
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /files/a/b HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::NotFound);
+    /// ```
+    pub fn get_param(&self, key: &str) -> Option<&String> {
+        self.params.get(key)
+    }
+
+    /// Returns all route parameters captured while matching the request
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ routes, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    ///
+    /// server.use_handler(routes! {
+    ///   GET "/users/:user_id/posts/:post_id" => |req, res| {
+    ///     let mut params = req.params().iter().collect::<Vec<_>>();
+    ///     params.sort();
+    ///     res.status(StatusCode::Ok).body_text(&format!("{:?}", params));
+    ///   },
+    /// });
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /users/42/posts/7 HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(request.params().len(), 2);
+    /// assert_eq!(response.get_body(), br#"[("post_id", "7"), ("user_id", "42")]"#);
+    /// ```
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    pub(crate) fn add_param(&mut self, key: &str, value: &str) {
+        self.params.insert(key.to_string(), value.to_string());
+    }
+
+    /// Returns the route pattern that matched the request
+    ///
+    /// The pattern is built from the paths the routers were added with, so parameters appear as
+    /// they were defined (e.g. `/users/:id`) instead of their values. This is useful for labeling
+    /// metrics without creating a label for every concrete path.
+    ///
+    /// Returns `None` if no endpoint matched the request.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server };
+    ///
+    /// let mut server = Server::create();
+    /// let mut main_router = Router::new();
+    /// let mut users = Router::new();
+    /// let mut user = Router::new();
+    /// let mut posts = Router::new();
+    /// let mut post = Router::new();
+    ///
+    /// post.get(|req, res| {
+    ///   res.body_text(req.matched_route().unwrap());
+    /// });
+    ///
+    /// posts.use_router(":post_id", post);
+    /// user.use_router("posts", posts);
+    /// users.use_router(":id:int", user);
+    /// main_router.use_router("users", users);
+    /// server.use_handler(main_router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /users/42/posts/7 HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(request.matched_route(), Some("/users/:id:int/posts/:post_id"));
+    /// assert_eq!(response.get_body(), b"/users/:id:int/posts/:post_id");
+    /// ```
+    pub fn matched_route(&self) -> Option<&str> {
+        self.matched_route.as_deref()
+    }
+
+    /// Returns the deadline of the request if it is set by the `Deadline` handler
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns true if the deadline of the request has passed
+    ///
+    /// Returns false if the request has no deadline.
+    pub fn deadline_exceeded(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Sets the status of the response to `504 Gateway Timeout` if the deadline has passed
+    ///
+    /// Returns true if the deadline has passed, so the controller can return early.
+    pub fn check_deadline(&self, response: &mut Response) -> bool {
+        if self.deadline_exceeded() {
+            response.status(StatusCode::GatewayTimeout);
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn push_route_segment(&mut self, segment: &str) {
+        self.route.push(segment.to_string());
+    }
+
+    /// Returns the route segments and the parameters added by the routers so far
+    pub(crate) fn route_state(&self) -> (Vec<String>, HashMap<String, String>) {
+        (self.route.clone(), self.params.clone())
+    }
+
+    /// Restores the route segments and the parameters returned by `route_state`
+    pub(crate) fn restore_route_state(&mut self, (route, params): (Vec<String>, HashMap<String, String>)) {
+        self.route = route;
+        self.params = params;
+    }
+
+    pub(crate) fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    pub(crate) fn set_matched_route(&mut self) {
+        self.matched_route = Some(format!("/{}", self.route.join("/")));
+    }
+
     /// Returns the method of the HTTP request
-    pub(crate) fn get_method(&self) -> &HttpMethod {
+    ///
+    /// Methods are normalized to their uppercase form, so `get` is returned as `HttpMethod::GET`.
+    pub fn get_method(&self) -> &HttpMethod {
         self.request.get_method()
     }
+
+    /// Replaces the method of the request, e.g. for method overrides
+    pub(crate) fn set_method(&mut self, method: HttpMethod) {
+        self.request.set_method(method);
+    }
 }
 
 impl Default for Request {
@@ -129,12 +676,32 @@ impl Default for Request {
                 "Failed to create default RequestLine"
             ),
             headers: HashMap::new(),
+            raw_headers: HashMap::new(),
             body: RequestBody::None,
+            raw_body: None,
+            unread_body_length: None,
+            body_reader: None,
             peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0),
+            params: HashMap::new(),
+            route: Vec::new(),
+            matched_route: None,
+            deadline: None,
+            secure: false,
+            forwarded_host: None,
+            json_options: JsonOptions::default(),
         }
     }
 }
 
+impl Display for Request {
+    /// Formats the request as raw HTTP, see `Request::to_raw_http`
+    ///
+    /// Bodies that aren't valid UTF-8 are decoded lossily.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        write!(f, "{}", String::from_utf8_lossy(&self.to_raw_http()))
+    }
+}
+
 impl Debug for Request {
     fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
         let headers = self.headers
@@ -144,6 +711,8 @@ impl Debug for Request {
         let body = match &self.body {
             RequestBody::Text(body) => format!("{:?}", body),
             RequestBody::Json(json) => format!("{:?}", json),
+            RequestBody::Form(form) => format!("{:?}", form),
+            RequestBody::Bytes(body) => format!("{:?}", body),
             RequestBody::None => "None".to_string(),
         };
 
@@ -158,12 +727,274 @@ impl Debug for Request {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 /// Represents an error that occurs when parsing an HTTP request
-pub struct ParseHttpRequestError;
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Request, StatusCode, request::ParseHttpRequestError };
+///
+/// let err = Request::parse_from_bytes(b"GET /\r\n\r\n").unwrap_err();
+/// assert_eq!(err, ParseHttpRequestError::InvalidRequestLine);
+/// assert_eq!(err.status_code(), StatusCode::BadRequest);
+///
+/// let err = Request::parse_from_bytes(b"BREW /pot HTTP/1.1\r\n\r\n").unwrap_err();
+/// assert_eq!(err, ParseHttpRequestError::UnsupportedMethod);
+/// assert_eq!(err.status_code(), StatusCode::NotImplemented);
+///
+/// let err = Request::parse_from_bytes(
+///   b"POST / HTTP/1.1\r\nContent-Type: plain/text\r\nContent-Length: 10\r\n\r\nshort"
+/// ).unwrap_err();
+/// assert_eq!(err, ParseHttpRequestError::IncompleteBody);
+///
+/// let err = Request::parse_from_bytes(
+///   b"POST / HTTP/1.1\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\n\x89PNG"
+/// ).unwrap_err();
+/// assert_eq!(err.status_code(), StatusCode::UnsupportedMediaType);
+/// ```
+pub enum ParseHttpRequestError {
+    /// The connection was closed before any byte was sent
+    ///
+    /// Health checkers and load balancers often open a connection and close it right away. This
+    /// is not treated as an error by the server and no response is sent.
+    ///
+    /// ```rust
+    /// use krustie::{ Request, request::ParseHttpRequestError };
+    /// use std::net::{ TcpListener, TcpStream };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// drop(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+    ///
+    /// let (stream, _) = listener.accept().unwrap();
+    ///
+    /// match Request::parse_from_reader(&stream) {
+    ///   Err(ParseHttpRequestError::ConnectionClosed) => {},
+    ///   other => panic!("Unexpected result: {:?}", other),
+    /// }
+    /// ```
+    ConnectionClosed,
+    /// The request line is missing or malformed
+    InvalidRequestLine,
+    /// The request line is longer than the maximum length
+    ///
+    /// ```rust
+    /// use krustie::{ Request, StatusCode, request::ParseHttpRequestError };
+    ///
+    /// let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(9000));
+    /// let err = Request::parse_from_bytes(raw.as_bytes()).unwrap_err();
+    ///
+    /// assert_eq!(err, ParseHttpRequestError::UriTooLong);
+    /// assert_eq!(err.status_code(), StatusCode::UriTooLong);
+    /// ```
+    UriTooLong,
+    /// The request method is not supported by the server
+    UnsupportedMethod,
+    /// The HTTP version of the request is not supported
+    UnsupportedVersion,
+    /// A header line is malformed
+    InvalidHeader,
+    /// The request has more than 100 header lines
+    ///
+    /// ```rust
+    /// use krustie::{ Request, StatusCode, request::ParseHttpRequestError };
+    ///
+    /// let headers = "X-Header: value\r\n".repeat(101);
+    /// let raw = format!("GET / HTTP/1.1\r\n{headers}\r\n");
+    /// let err = Request::parse_from_bytes(raw.as_bytes()).unwrap_err();
+    ///
+    /// assert_eq!(err, ParseHttpRequestError::TooManyHeaders);
+    /// assert_eq!(err.status_code(), StatusCode::RequestHeaderFieldsTooLarge);
+    ///
+    /// let headers = "X-Header: value\r\n".repeat(100);
+    /// let raw = format!("GET / HTTP/1.1\r\n{headers}\r\n");
+    /// assert!(Request::parse_from_bytes(raw.as_bytes()).is_ok());
+    /// ```
+    TooManyHeaders,
+    /// The body is shorter than the declared `Content-Length`
+    IncompleteBody,
+    /// The body is larger than the server accepts
+    ///
+    /// Either the declared `Content-Length` is above the maximum body size, see
+    /// `ServerConfig::max_body_size`, or the decoded body of a request with a `Content-Encoding` is
+    /// above the decompression limit, see `body::BodyParsers::set_decompression_limit`. The declared
+    /// length is checked before the body is read and decoding stops at the limit, so nothing is
+    /// allocated beyond the limits.
+    ///
+    /// ```rust
+    /// use krustie::{ Request, StatusCode, request::ParseHttpRequestError };
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 1000000000000\r\n\r\n";
+    /// let err = Request::parse_from_bytes(raw).unwrap_err();
+    ///
+    /// assert_eq!(err, ParseHttpRequestError::BodyTooLarge);
+    /// assert_eq!(err.status_code(), StatusCode::PayloadTooLarge);
+    /// ```
+    BodyTooLarge,
+    /// The request has both `Content-Length` and `Transfer-Encoding` headers, or `Content-Length`
+    /// headers with different values
+    ///
+    /// Servers and proxies could read a different body length from such a request, which can be
+    /// used to smuggle a request, so it is rejected with `400 Bad Request` and the connection is
+    /// closed.
+    ///
+    /// ```rust
+    /// use krustie::{ Request, StatusCode, request::ParseHttpRequestError };
+    ///
+    /// let smuggling_vectors: [&[u8]; 3] = [
+    ///   b"POST / HTTP/1.1\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+    ///   b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n0\r\n\r\n",
+    ///   b"POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\nHello!",
+    /// ];
+    ///
+    /// for raw in smuggling_vectors {
+    ///   let err = Request::parse_from_bytes(raw).unwrap_err();
+    ///
+    ///   assert_eq!(err, ParseHttpRequestError::AmbiguousBodyLength);
+    ///   assert_eq!(err.status_code(), StatusCode::BadRequest);
+    /// }
+    ///
+    /// // Repeating the same length is not ambiguous
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nContent-Length: 5\r\n\r\nHello";
+    /// assert_eq!(Request::parse_from_bytes(raw).unwrap().body_text().unwrap(), "Hello");
+    /// ```
+    ///
+    /// The connection is closed even if keep-alive is enabled
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode, server::ServerConfig };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   keep_alive: true,
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|_, res| {
+    ///   res.status(StatusCode::Ok);
+    /// });
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let smuggling_vectors: [&[u8]; 2] = [
+    ///   b"POST / HTTP/1.1\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+    ///   b"POST / HTTP/1.1\r\nContent-Length: 0\r\nContent-Length: 22\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+    /// ];
+    ///
+    /// for raw in smuggling_vectors {
+    ///   let mut stream = TcpStream::connect(address).unwrap();
+    ///   let mut response = String::new();
+    ///
+    ///   stream.write_all(raw).unwrap();
+    ///   // Reading to the end only returns when the server closes the connection
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///
+    ///   assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    ///   assert!(response.contains("Connection: close\r\n"));
+    ///   assert_eq!(response.matches("HTTP/1.1").count(), 1);
+    /// }
+    /// ```
+    AmbiguousBodyLength,
+    /// The request has a `Transfer-Encoding` header
+    ///
+    /// Transfer codings like `chunked` are not decoded, so the length of the body is unknown. The
+    /// request is rejected with `501 Not Implemented` and the connection is closed, so the body is
+    /// never read as the next request.
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode, server::ServerConfig };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   keep_alive: true,
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|_, res| {
+    ///   res.status(StatusCode::Ok);
+    /// });
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream
+    ///   .write_all(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n13\r\nGET / HTTP/1.1\r\n\r\n\r\n0\r\n\r\n")
+    ///   .unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 501 Not Implemented"));
+    /// assert!(response.contains("Connection: close\r\n"));
+    /// assert_eq!(response.matches("HTTP/1.1").count(), 1);
+    /// ```
+    UnsupportedTransferEncoding,
+    /// The JSON body is nested deeper or has more elements than the limits allow, see
+    /// `body::JsonLimits`
+    JsonLimitExceeded,
+    /// The body could not be decoded with the codings of its `Content-Encoding` header
+    InvalidContentEncoding,
+    /// A coding of the `Content-Encoding` header is not supported
+    UnsupportedContentEncoding,
+    /// The request has a body but no `Content-Type` header
+    MissingContentType,
+    /// The `Content-Type` of the body is not supported
+    UnsupportedContentType,
+    /// The connection timed out while the request was being read, see `Server::set_read_timeout`
+    Timeout,
+    /// The request could not be read from the connection
+    Io,
+}
+
+impl ParseHttpRequestError {
+    /// Returns the status code that should be sent for the error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UriTooLong => StatusCode::UriTooLong,
+            Self::UnsupportedMethod => StatusCode::NotImplemented,
+            Self::UnsupportedTransferEncoding => StatusCode::NotImplemented,
+            Self::TooManyHeaders => StatusCode::RequestHeaderFieldsTooLarge,
+            Self::UnsupportedVersion => StatusCode::HttpVersionNotSupported,
+            Self::UnsupportedContentType => StatusCode::UnsupportedMediaType,
+            Self::UnsupportedContentEncoding => StatusCode::UnsupportedMediaType,
+            Self::BodyTooLarge => StatusCode::PayloadTooLarge,
+            Self::Timeout => StatusCode::RequestTimeout,
+            _ => StatusCode::BadRequest,
+        }
+    }
+}
 
 impl Display for ParseHttpRequestError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
-        write!(f, "Failed to parse HTTP request")
+        match self {
+            Self::ConnectionClosed => write!(f, "Connection closed before the request was sent"),
+            Self::InvalidRequestLine => write!(f, "Invalid request line"),
+            Self::UriTooLong => write!(f, "Request line is too long"),
+            Self::UnsupportedMethod => write!(f, "Unsupported HTTP method"),
+            Self::UnsupportedVersion => write!(f, "Unsupported HTTP version"),
+            Self::InvalidHeader => write!(f, "Invalid header line"),
+            Self::TooManyHeaders => write!(f, "Request has too many headers"),
+            Self::IncompleteBody => write!(f, "Body is shorter than Content-Length"),
+            Self::BodyTooLarge => write!(f, "Body exceeds the size limit"),
+            Self::AmbiguousBodyLength => write!(f, "Body length is ambiguous"),
+            Self::UnsupportedTransferEncoding => write!(f, "Transfer-Encoding is not supported"),
+            Self::JsonLimitExceeded => write!(f, "JSON body exceeds the limits"),
+            Self::InvalidContentEncoding => write!(f, "Body doesn't match its Content-Encoding"),
+            Self::UnsupportedContentEncoding => write!(f, "Content-Encoding is not supported"),
+            Self::MissingContentType => write!(f, "Content-Type is missing for the body"),
+            Self::UnsupportedContentType => write!(f, "Content-Type is not supported"),
+            Self::Timeout => write!(f, "Timed out while reading the request"),
+            Self::Io => write!(f, "Failed to read HTTP request"),
+        }
     }
 }
+
+impl std::error::Error for ParseHttpRequestError {}
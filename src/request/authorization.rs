@@ -0,0 +1,47 @@
+//! This module contains the parsing of the `Authorization` header of a request.
+
+use super::Request;
+
+impl Request {
+    /// Returns the scheme and the credentials of the `Authorization` header
+    ///
+    /// The header is split at the first space, so any scheme can be handled, like `Basic`,
+    /// `Bearer`, `Digest` or a custom one. Schemes are case-insensitive, so they should be compared
+    /// with `eq_ignore_ascii_case`. The credentials are empty if the header only has a scheme.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nAuthorization: HMAC keyId=\"krusty\",signature=\"c2lnbmVk\"\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// let (scheme, credentials) = request.authorization().unwrap();
+    ///
+    /// assert_eq!(scheme, "HMAC");
+    /// assert_eq!(credentials, "keyId=\"krusty\",signature=\"c2lnbmVk\"");
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nAuthorization: bearer  abc.def.ghi\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// let (scheme, token) = request.authorization().unwrap();
+    ///
+    /// assert!(scheme.eq_ignore_ascii_case("Bearer"));
+    /// assert_eq!(token, "abc.def.ghi");
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(request.authorization(), None);
+    /// ```
+    pub fn authorization(&self) -> Option<(&str, &str)> {
+        let header = self.get_header("authorization")?.trim();
+
+        if header.is_empty() {
+            return None;
+        }
+
+        match header.split_once(' ') {
+            Some((scheme, credentials)) => Some((scheme, credentials.trim())),
+            None => Some((header, "")),
+        }
+    }
+}
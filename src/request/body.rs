@@ -1,13 +1,40 @@
-//! This module contains the RequestBody enum and its implementation.
+//! This module contains the RequestBody enum and the body parser registry.
 //!
-//! RequestBody can be a `Text`, a `Json` or `None`.
+//! RequestBody can be a `Text`, a `Json`, a `Form`, `Bytes` or `None`.
 //!
 //! - Text returns a `Vec<u8>`
 //!
 //! - Json returns a `JsonValue` (it's json_verde::Value)
+//!
+//! - Form returns a `HashMap<String, String>`
+//!
+//! - Bytes returns a `Vec<u8>`
+//!
+//! Bodies are parsed by the parser registered for their content type in `BodyParsers`. Custom
+//! parsers can be registered with `Server::add_body_parser`.
+//!
+//! ```rust
+//! use krustie::{ Request, request::RequestBody };
+//!
+//! let body = "name=Krusty+the+Clown&show=%22Itchy%22";
+//! let raw = format!(
+//!   "POST / HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+//!   body.len()
+//! );
+//! let request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+//!
+//! match request.get_body() {
+//!   RequestBody::Form(form) => {
+//!     assert_eq!(form["name"], "Krusty the Clown");
+//!     assert_eq!(form["show"], "\"Itchy\"");
+//!   }
+//!   _ => panic!("Body should be parsed as a form"),
+//! }
+//! ```
 
-use std::io::{ Error, ErrorKind };
+use std::{ collections::HashMap, fmt::{ Display, Formatter, Result as fResult } };
 
+use super::{ urlencoded, ParseHttpRequestError };
 use crate::json::JsonValue;
 
 #[derive(Debug)]
@@ -17,28 +44,376 @@ pub enum RequestBody {
     Text(Vec<u8>),
     /// Represents a json body. Holds a JsonValue.
     Json(JsonValue),
-    // Represents a form body. Holds a HashMap of strings. **Not implemented yet.**
-    // Form(HashMap<String, String>),
+    /// Represents a form body. Holds a HashMap of strings.
+    Form(HashMap<String, String>),
+    /// Represents a binary body. Holds a vector of bytes.
+    Bytes(Vec<u8>),
     /// Represents that there is no body or a body that is not supported.
     None,
 }
 
-impl RequestBody {
-    pub(crate) fn parse(body: Vec<u8>, content_type: &str) -> Result<RequestBody, Error> {
-        let body = match content_type {
-            "application/json" => {
-                match serde_json::from_slice(&body[..]) {
-                    Ok(json) => RequestBody::Json(json),
-                    Err(_) => RequestBody::None,
+/// A function that parses the raw bytes of a body
+pub type BodyParser = fn(Vec<u8>) -> RequestBody;
+
+/// A registry of body parsers by content type
+///
+/// Content types are matched by their essence, so parameters like `charset` are ignored. The
+/// default registry contains the parsers for `application/json`, `application/x-www-form-urlencoded`,
+/// `text/plain`, `application/x-ndjson`, `application/octet-stream` and `multipart/form-data`.
+/// Multipart bodies are read with `Request::multipart`. They are streamed from the connection while
+/// the request is handled, and kept as `Bytes` for requests that are parsed from bytes or readers.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Request, request::{ RequestBody, body::BodyParsers } };
+///
+/// let mut parsers = BodyParsers::default();
+///
+/// parsers.register("application/x-reversed", |mut body| {
+///   body.reverse();
+///   RequestBody::Bytes(body)
+/// });
+///
+/// let raw = b"POST / HTTP/1.1\r\nContent-Type: application/x-reversed\r\nContent-Length: 3\r\n\r\nabc";
+/// let request = Request::parse_from_bytes_with(raw, &parsers).unwrap();
+///
+/// match request.get_body() {
+///   RequestBody::Bytes(body) => assert_eq!(body, b"cba"),
+///   _ => panic!("Body should be parsed by the custom parser"),
+/// }
+///
+/// assert_eq!(request.body_text().unwrap(), "abc");
+/// ```
+#[derive(Debug, Clone)]
+pub struct BodyParsers {
+    parsers: HashMap<String, Parser>,
+    json_limits: JsonLimits,
+    json_options: JsonOptions,
+    decompression_limit: Option<usize>,
+}
+
+/// A parser of the registry
+///
+/// The built-in parsers read the bytes in place, so the received body is stored only once.
+#[derive(Debug, Clone, Copy)]
+enum Parser {
+    /// Keeps the bytes as `RequestBody::Text`
+    Text,
+    /// Keeps the bytes as `RequestBody::Bytes`
+    Bytes,
+    /// Parses the bytes as `RequestBody::Json`
+    Json,
+    /// Parses the bytes as `RequestBody::Form`
+    Form,
+    /// Keeps the bytes as `RequestBody::Bytes`, or leaves them on the connection for
+    /// `Request::multipart`
+    Multipart,
+    /// A parser registered with `BodyParsers::register`
+    Custom(BodyParser),
+}
+
+/// Limits of JSON bodies, which are checked before the body is parsed
+///
+/// They apply to `application/json` and `+json` bodies. Requests with a body exceeding them are
+/// rejected with `400 Bad Request`. By default, the depth is limited to 128, the same as the
+/// limit of the JSON parser, and the number of elements is not limited.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Request, StatusCode, request::{ ParseHttpRequestError, body::{ BodyParsers, JsonLimits } } };
+///
+/// let mut parsers = BodyParsers::default();
+///
+/// parsers.set_json_limits(JsonLimits { max_depth: 8, max_elements: 100 });
+///
+/// let parse = |body: &str| {
+///   let raw = format!(
+///     "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+///     body.len()
+///   );
+///   Request::parse_from_bytes_with(raw.as_bytes(), &parsers)
+/// };
+///
+/// let deep = format!("{}{}", "[".repeat(9), "]".repeat(9));
+/// let err = parse(&deep).unwrap_err();
+///
+/// assert_eq!(err, ParseHttpRequestError::JsonLimitExceeded);
+/// assert_eq!(err.status_code(), StatusCode::BadRequest);
+///
+/// let members = (0..101).map(|i| format!("\"k{i}\": {i}")).collect::<Vec<_>>().join(", ");
+/// let large = format!("{{{members}}}");
+///
+/// assert_eq!(parse(&large).unwrap_err(), ParseHttpRequestError::JsonLimitExceeded);
+///
+/// // Brackets and commas in strings are not counted
+/// assert!(parse(r#"{"a": "[[[[[[[[[[,,,,", "b": [1, 2, [3]]}"#).is_ok());
+/// assert!(parse(&format!("{}{}", "[".repeat(8), "]".repeat(8))).is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// The maximum nesting depth of arrays and objects
+    pub max_depth: usize,
+    /// The maximum total number of array items and object members
+    pub max_elements: usize,
+}
+
+impl JsonLimits {
+    /// Returns true if the JSON text exceeds the limits
+    ///
+    /// The text is only scanned for brackets and commas, so it is checked without parsing it.
+    fn is_exceeded_by(&self, json: &[u8]) -> bool {
+        let mut depth = 0;
+        let mut elements = 0;
+        let mut in_string = false;
+        let mut is_escaped = false;
+        let mut expects_first_element = false;
+
+        for byte in json {
+            if in_string {
+                match byte {
+                    _ if is_escaped => is_escaped = false,
+                    b'\\' => is_escaped = true,
+                    b'"' => in_string = false,
+                    _ => (),
+                }
+                continue;
+            }
+
+            // An empty container has no elements
+            if expects_first_element && !byte.is_ascii_whitespace() {
+                expects_first_element = false;
+
+                if *byte != b']' && *byte != b'}' {
+                    elements += 1;
                 }
             }
-            "plain/text" => { RequestBody::Text(body.to_vec()) }
-            _ => {
-                let error =
-                    format!("Error while parsing body. Content-type not supported: {}", content_type);
-                return Err(Error::new(ErrorKind::InvalidInput, error));
+
+            match byte {
+                b'"' => in_string = true,
+                b'[' | b'{' => {
+                    depth += 1;
+                    expects_first_element = true;
+                }
+                b']' | b'}' => depth -= usize::from(depth > 0),
+                b',' if depth > 0 => elements += 1,
+                _ => (),
+            }
+
+            if depth > self.max_depth || elements > self.max_elements {
+                return true;
             }
+        }
+
+        false
+    }
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_elements: usize::MAX,
+        }
+    }
+}
+
+/// Options of the typed deserialization of JSON bodies with `Request::body_json`
+///
+/// By default, values have to match the types they are deserialized into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Deserializes strings like `"42"` into number fields and numbers into string fields
+    ///
+    /// Strings are only converted if they are a valid JSON number, so `"42 "` or `"0x2a"` are
+    /// still rejected for a number field.
+    pub coerce_numbers: bool,
+}
+
+impl BodyParsers {
+    /// Creates a registry without any parsers
+    pub fn new() -> Self {
+        Self {
+            parsers: HashMap::new(),
+            json_limits: JsonLimits::default(),
+            json_options: JsonOptions::default(),
+            decompression_limit: None,
+        }
+    }
+
+    /// Registers a parser for the content type, replacing the previous one
+    pub fn register(&mut self, content_type: &str, parser: BodyParser) -> &mut Self {
+        self.insert(content_type, Parser::Custom(parser))
+    }
+
+    fn insert(&mut self, content_type: &str, parser: Parser) -> &mut Self {
+        self.parsers.insert(content_type.trim().to_lowercase(), parser);
+        self
+    }
+
+    /// Sets the limits of JSON bodies, see `JsonLimits`
+    pub fn set_json_limits(&mut self, limits: JsonLimits) -> &mut Self {
+        self.json_limits = limits;
+        self
+    }
+
+    /// Sets the options of `Request::body_json` for the parsed requests, see `JsonOptions`
+    pub fn set_json_options(&mut self, options: JsonOptions) -> &mut Self {
+        self.json_options = options;
+        self
+    }
+
+    /// Returns the options of `Request::body_json` for the parsed requests
+    pub(crate) fn json_options(&self) -> JsonOptions {
+        self.json_options
+    }
+
+    /// Decodes bodies with a `Content-Encoding` before they are parsed, up to the maximum size
+    ///
+    /// Bodies are not decoded by default. Once a limit is set, `gzip`, `x-gzip` and `deflate`
+    /// bodies are decoded, and the `Content-Encoding` header of the request is removed while its
+    /// `Content-Length` is set to the decoded length. Decoding stops as soon as the decoded body
+    /// exceeds the limit and the request is rejected with `413 Payload Too Large`, so a small
+    /// compressed body can't allocate unbounded memory. The decoded body is also limited by the
+    /// maximum body size of the server, see `ServerConfig::max_body_size`.
+    ///
+    /// Requests with a coding that is not supported are rejected with
+    /// `415 Unsupported Media Type` and the ones with a body that doesn't match its coding with
+    /// `400 Bad Request`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{
+    ///   Request,
+    ///   StatusCode,
+    ///   middleware::gzip::encode_body,
+    ///   request::{ ParseHttpRequestError, body::BodyParsers },
+    /// };
+    ///
+    /// let mut parsers = BodyParsers::default();
+    ///
+    /// parsers.set_decompression_limit(1024 * 1024);
+    ///
+    /// let parse = |body: &[u8]| {
+    ///   let mut raw = format!(
+    ///     "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+    ///     body.len()
+    ///   ).into_bytes();
+    ///   raw.extend_from_slice(body);
+    ///   Request::parse_from_bytes_with(&raw, &parsers)
+    /// };
+    ///
+    /// let request = parse(&encode_body(b"Hello, World!", "gzip").unwrap()).unwrap();
+    ///
+    /// assert_eq!(request.body_text().unwrap(), "Hello, World!");
+    /// assert_eq!(request.get_header("content-encoding"), None);
+    /// assert_eq!(request.content_length(), Some(13));
+    ///
+    /// // 10 MiB of zeros compress to a few kilobytes
+    /// let bomb = encode_body(&vec![0; 10 * 1024 * 1024], "gzip").unwrap();
+    /// let err = parse(&bomb).unwrap_err();
+    ///
+    /// assert_eq!(err, ParseHttpRequestError::BodyTooLarge);
+    /// assert_eq!(err.status_code(), StatusCode::PayloadTooLarge);
+    ///
+    /// assert_eq!(parse(b"not gzip").unwrap_err(), ParseHttpRequestError::InvalidContentEncoding);
+    /// ```
+    pub fn set_decompression_limit(&mut self, max_size: usize) -> &mut Self {
+        self.decompression_limit = Some(max_size);
+        self
+    }
+
+    /// Returns true if bodies of the content type are read from the connection while the request
+    /// is handled instead of being received in full first
+    pub(crate) fn is_streamed(&self, content_type: &str) -> bool {
+        matches!(self.parsers.get(&essence(content_type)), Some(Parser::Multipart))
+    }
+
+    /// Returns the decompression limit, if bodies with a `Content-Encoding` are decoded
+    pub(crate) fn decompression_limit(&self) -> Option<usize> {
+        self.decompression_limit
+    }
+
+    /// Parses the body with the parser of the content type
+    ///
+    /// Returns the parsed body and the received bytes, which are `None` if the parsed body holds
+    /// them as `Text` or `Bytes`.
+    pub(crate) fn parse(
+        &self,
+        body: Vec<u8>,
+        content_type: &str
+    ) -> Result<(RequestBody, Option<Vec<u8>>), ParseHttpRequestError> {
+        let essence = essence(content_type);
+        let parser = self.parsers
+            .get(&essence)
+            .ok_or(ParseHttpRequestError::UnsupportedContentType)?;
+
+        let is_json = essence == "application/json" || essence.ends_with("+json");
+
+        if is_json && self.json_limits.is_exceeded_by(&body) {
+            return Err(ParseHttpRequestError::JsonLimitExceeded);
+        }
+
+        let parsed = match *parser {
+            Parser::Text => (RequestBody::Text(body), None),
+            Parser::Bytes | Parser::Multipart => (RequestBody::Bytes(body), None),
+            Parser::Json => (Self::parse_json(&body), Some(body)),
+            Parser::Form => (RequestBody::Form(urlencoded::parse(&body)), Some(body)),
+            // Custom parsers own their input, so the received bytes are kept separately
+            Parser::Custom(parser) => (parser(body.clone()), Some(body)),
         };
-        Ok(body)
+
+        Ok(parsed)
+    }
+
+    fn parse_json(body: &[u8]) -> RequestBody {
+        match serde_json::from_slice(body) {
+            Ok(json) => RequestBody::Json(json),
+            Err(_) => RequestBody::None,
+        }
+    }
+}
+
+impl Default for BodyParsers {
+    fn default() -> Self {
+        let mut parsers = Self::new();
+
+        parsers
+            .insert("application/json", Parser::Json)
+            .insert("application/x-www-form-urlencoded", Parser::Form)
+            .insert("text/plain", Parser::Text)
+            .insert("plain/text", Parser::Text)
+            .insert("application/x-ndjson", Parser::Text)
+            .insert("application/octet-stream", Parser::Bytes)
+            .insert("multipart/form-data", Parser::Multipart);
+
+        parsers
     }
 }
+
+/// Returns the content type without its parameters, e.g. `text/plain` for `text/plain; charset=utf-8`
+fn essence(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_lowercase()
+}
+
+/// Error returned while reading the body of a request as text
+#[derive(Debug, PartialEq, Eq)]
+pub enum BodyError {
+    /// The request has no body
+    Empty,
+    /// The body is not valid UTF-8
+    InvalidUtf8,
+}
+
+impl Display for BodyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::Empty => write!(f, "Request has no body"),
+            Self::InvalidUtf8 => write!(f, "Request body is not valid UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for BodyError {}
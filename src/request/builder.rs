@@ -0,0 +1,179 @@
+//! This module contains the `RequestBuilder` for creating requests without parsing them.
+//!
+//! Built requests can be passed to `Server::dispatch` to test controllers and middlewares.
+
+use std::{ collections::HashMap, net::SocketAddr };
+
+use super::{
+    body::BodyParsers,
+    request_line::RequestLine,
+    urlencoded,
+    HttpMethod,
+    Request,
+    RequestBody,
+};
+use crate::json::JsonValue;
+
+/// A builder for creating requests
+///
+/// Created by `Request::builder`. The request is `GET /` from `0.0.0.0:0` unless it is changed.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Request, Router, Server, HttpMethod, StatusCode, json::json, request::RequestBody };
+///
+/// let mut server = Server::create();
+/// let mut router = Router::new();
+/// let mut users = Router::new();
+///
+/// users.post(|req, res| {
+///   match req.get_body() {
+///     RequestBody::Json(json) => {
+///       let greeting = req.get_query_param("greeting").unwrap();
+///       res.status(StatusCode::Created).body_text(&format!("{greeting}, {}", json["name"]));
+///     }
+///     _ => {
+///       res.status(StatusCode::BadRequest);
+///     }
+///   }
+/// });
+///
+/// router.use_router("users", users);
+/// server.use_handler(router);
+///
+/// let mut request = Request::builder()
+///   .method(HttpMethod::POST)
+///   .path("/users")
+///   .query("greeting", "Hey there")
+///   .header("X-Request-Id", "42")
+///   .json(json!({ "name": "Krusty" }))
+///   .build();
+///
+/// assert_eq!(request.get_header("x-request-id").unwrap(), "42");
+/// assert_eq!(request.get_header("content-length").unwrap(), "17");
+///
+/// let mut response = server.dispatch(&mut request);
+///
+/// assert_eq!(response.get_status(), StatusCode::Created);
+/// assert_eq!(response.get_body(), b"Hey there, \"Krusty\"");
+/// ```
+#[derive(Debug)]
+pub struct RequestBuilder {
+    method: HttpMethod,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+    peer_addr: SocketAddr,
+}
+
+impl RequestBuilder {
+    /// Sets the method of the request
+    pub fn method(mut self, method: HttpMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Sets the path of the request. A leading `/` is added if it is missing.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = if path.starts_with('/') { path.to_string() } else { format!("/{path}") };
+        self
+    }
+
+    /// Adds a query parameter to the request
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds a header to the request. Header names are case-insensitive.
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.insert(key.to_lowercase(), value.trim().to_string());
+        self
+    }
+
+    /// Sets the body and the `Content-Type` of the request
+    ///
+    /// The body is parsed with the default body parsers. Bodies with a content type that has no
+    /// parser are stored as `RequestBody::Bytes`.
+    pub fn body(self, body: Vec<u8>, content_type: &str) -> Self {
+        let mut builder = self.header("content-type", content_type);
+        builder.body = body;
+        builder
+    }
+
+    /// Sets a `text/plain` body
+    pub fn text(self, text: &str) -> Self {
+        self.body(text.as_bytes().to_vec(), "text/plain")
+    }
+
+    /// Sets an `application/json` body
+    pub fn json(self, json: JsonValue) -> Self {
+        self.body(json.to_string().into_bytes(), "application/json")
+    }
+
+    /// Sets the peer address of the request
+    pub fn peer_addr(mut self, peer_addr: SocketAddr) -> Self {
+        self.peer_addr = peer_addr;
+        self
+    }
+
+    /// Creates the request
+    pub fn build(mut self) -> Request {
+        let mut target = self.path;
+
+        if !self.query.is_empty() {
+            let query = self.query
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}={}", urlencoded::encode(key), urlencoded::encode(value))
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            target = format!("{target}?{query}");
+        }
+
+        if !self.body.is_empty() {
+            self.headers.insert("content-length".to_string(), self.body.len().to_string());
+        }
+
+        let (body, raw_body) = match self.headers.get("content-type") {
+            _ if self.body.is_empty() => (RequestBody::None, None),
+            Some(content_type) => {
+                // Bodies the parser rejects are kept as bytes
+                match BodyParsers::default().parse(self.body.clone(), content_type) {
+                    Ok(parsed) => parsed,
+                    Err(_) => (RequestBody::Bytes(self.body), None),
+                }
+            }
+            None => (RequestBody::Bytes(self.body), None),
+        };
+
+        Request {
+            request: RequestLine::new(&self.method.to_string(), &target, "HTTP/1.1").expect(
+                "Failed to create RequestLine"
+            ),
+            headers: self.headers,
+            body,
+            raw_body,
+            peer_addr: self.peer_addr,
+            ..Request::default()
+        }
+    }
+}
+
+impl Default for RequestBuilder {
+    fn default() -> Self {
+        let request = Request::default();
+
+        Self {
+            method: HttpMethod::GET,
+            path: "/".to_string(),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: Vec::new(),
+            peer_addr: *request.get_peer_addr(),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! This module contains the helpers for conditional request headers.
+//!
+//! `If-Match` and `If-None-Match` headers are parsed into lists of entity tags, so controllers can
+//! compare them with the current entity tag of a resource and return `412 Precondition Failed`.
+//!
+//! Weak tags (`W/"v1"`) only match with the weak comparison, which is used for `If-None-Match`.
+//! `If-Match` and `If-Range` use the strong comparison, where weak tags never match.
+
+use super::Request;
+
+impl Request {
+    /// Returns the entity tags of the `If-Match` header
+    ///
+    /// Tags are returned with their quotes, and weak tags keep their `W/` prefix. The `*` value is
+    /// returned as a single `*` element. Returns `None` if the header is missing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"PUT /doc HTTP/1.1\r\nIf-Match: \"v1\", W/\"v2\", \"v,3\"\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.if_match().unwrap(), vec!["\"v1\"", "W/\"v2\"", "\"v,3\""]);
+    ///
+    /// let raw = b"PUT /doc HTTP/1.1\r\nIf-Match: *\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.if_match().unwrap(), vec!["*"]);
+    ///
+    /// let request = Request::parse_from_bytes(b"PUT /doc HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(request.if_match(), None);
+    /// ```
+    ///
+    /// Rejecting an update of a resource that has changed
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.put(|req, res| {
+    ///   let current = "\"v2\"";
+    ///
+    ///   if let Some(tags) = req.if_match() {
+    ///     if !tags.iter().any(|tag| tag == "*" || tag == current) {
+    ///       res.status(StatusCode::PreconditionFailed);
+    ///       return;
+    ///     }
+    ///   }
+    ///
+    ///   res.no_content();
+    /// });
+    ///
+    /// server.use_handler(router);
+    ///
+    /// let raw = b"PUT / HTTP/1.1\r\nIf-Match: \"v1\"\r\n\r\n";
+    /// let mut request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::PreconditionFailed);
+    /// ```
+    pub fn if_match(&self) -> Option<Vec<String>> {
+        self.get_header("if-match").map(|value| parse_entity_tags(value))
+    }
+
+    /// Returns the entity tags of the `If-None-Match` header
+    ///
+    /// Parsed the same way as `if_match`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET /doc HTTP/1.1\r\nIf-None-Match: W/\"v1\",\"v2\"\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.if_none_match().unwrap(), vec!["W/\"v1\"", "\"v2\""]);
+    /// ```
+    pub fn if_none_match(&self) -> Option<Vec<String>> {
+        self.get_header("if-none-match").map(|value| parse_entity_tags(value))
+    }
+
+    /// Returns true if the `If-Match` header contains the entity tag
+    ///
+    /// Tags are compared with the strong comparison, so weak tags never match. Returns false if the
+    /// header is missing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"PUT /doc HTTP/1.1\r\nIf-Match: W/\"x\", \"y\"\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert!(!request.if_match_contains("\"x\""));
+    /// assert!(request.if_match_contains("\"y\""));
+    /// ```
+    pub fn if_match_contains(&self, etag: &str) -> bool {
+        self.if_match().is_some_and(|tags| {
+            tags.iter().any(|tag| tag == "*" || strong_compare(tag, etag))
+        })
+    }
+
+    /// Returns true if the `If-None-Match` header contains the entity tag
+    ///
+    /// Tags are compared with the weak comparison, so `W/"x"` matches `"x"`. Returns false if the
+    /// header is missing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET /doc HTTP/1.1\r\nIf-None-Match: W/\"x\"\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert!(request.if_none_match_contains("\"x\""));
+    /// assert!(request.if_none_match_contains("W/\"x\""));
+    /// assert!(!request.if_none_match_contains("\"y\""));
+    /// ```
+    pub fn if_none_match_contains(&self, etag: &str) -> bool {
+        self.if_none_match().is_some_and(|tags| {
+            tags.iter().any(|tag| tag == "*" || weak_compare(tag, etag))
+        })
+    }
+}
+
+/// Returns true if both tags are strong and their values are equal
+pub(crate) fn strong_compare(left: &str, right: &str) -> bool {
+    !left.starts_with("W/") && !right.starts_with("W/") && left == right
+}
+
+/// Returns true if the values of the tags are equal, ignoring the weak indicator
+pub(crate) fn weak_compare(left: &str, right: &str) -> bool {
+    left.trim_start_matches("W/") == right.trim_start_matches("W/")
+}
+
+/// Parses a comma separated list of entity tags
+///
+/// Malformed elements are skipped.
+fn parse_entity_tags(value: &str) -> Vec<String> {
+    if value.trim() == "*" {
+        return vec!["*".to_string()];
+    }
+
+    let mut tags = Vec::new();
+    let mut rest = value;
+
+    loop {
+        rest = rest.trim_start_matches(|char: char| char == ',' || char.is_whitespace());
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let (prefix, tag) = match rest.strip_prefix("W/") {
+            Some(tag) => ("W/", tag),
+            None => ("", rest),
+        };
+
+        let end = match tag.strip_prefix('"').and_then(|tag| tag.find('"')) {
+            Some(end) => end + 2,
+            None => {
+                // Skips the malformed element
+                rest = rest.find(',').map_or("", |comma| &rest[comma..]);
+                continue;
+            }
+        };
+
+        tags.push(format!("{prefix}{}", &tag[..end]));
+        rest = &tag[end..];
+    }
+
+    tags
+}
@@ -0,0 +1,64 @@
+//! This module contains the parsing of the `Cookie` header of a request.
+
+use std::collections::HashMap;
+
+use super::{ urlencoded, Request };
+
+impl Request {
+    /// Returns the cookies of the request as a map of names to values
+    ///
+    /// The `Cookie` header is parsed in a single pass. Quotes around a value are removed and
+    /// percent-encoded values are decoded. Pairs without a `=` or a name are skipped. If a name
+    /// is sent more than once, the first value is kept, as clients send the most specific cookie
+    /// first.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nCookie: session=abc123; theme=\"dark\"; name=Krusty%20the%20Clown; broken; =orphan\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// let cookies = request.cookies();
+    ///
+    /// assert_eq!(cookies.len(), 3);
+    /// assert_eq!(cookies.get("session").unwrap(), "abc123");
+    /// assert_eq!(cookies.get("theme").unwrap(), "dark");
+    /// assert_eq!(cookies.get("name").unwrap(), "Krusty the Clown");
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert!(request.cookies().is_empty());
+    /// ```
+    pub fn cookies(&self) -> HashMap<String, String> {
+        let mut cookies = HashMap::new();
+
+        let Some(header) = self.get_header("cookie") else {
+            return cookies;
+        };
+
+        for pair in header.split(';') {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+
+            let name = name.trim();
+
+            if name.is_empty() {
+                continue;
+            }
+
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+
+            cookies
+                .entry(name.to_string())
+                .or_insert_with(|| urlencoded::decode_path(value.as_bytes()));
+        }
+
+        cookies
+    }
+}
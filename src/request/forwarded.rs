@@ -0,0 +1,125 @@
+//! This module contains the handling of the `Forwarded` header (RFC 7239).
+//!
+//! When a request comes from one of the trusted proxies in `ServerConfig::trusted_proxies`, the
+//! server reads the client address, the protocol and the host from the `Forwarded` header before
+//! the handlers run. Headers of untrusted peers are ignored, since any client can send them.
+//!
+//! Each proxy appends an element describing the request it received, so the elements are read
+//! from the last one. The first element whose `for` address is not a trusted proxy describes the
+//! client.
+
+use std::net::{ IpAddr, SocketAddr };
+
+use super::Request;
+
+/// The parameters of a `Forwarded` element
+#[derive(Debug, Default)]
+struct ForwardedElement {
+    for_addr: Option<SocketAddr>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+impl ForwardedElement {
+    fn parse(element: &str) -> Self {
+        let mut parsed = Self::default();
+
+        for pair in element.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"');
+
+            match key.trim().to_lowercase().as_str() {
+                "for" => {
+                    parsed.for_addr = parse_node(value);
+                }
+                "proto" => {
+                    parsed.proto = Some(value.to_lowercase());
+                }
+                "host" => {
+                    parsed.host = Some(value.to_string());
+                }
+                _ => (),
+            }
+        }
+
+        parsed
+    }
+}
+
+impl Request {
+    /// Returns true if the request was received over HTTPS
+    ///
+    /// The server itself doesn't terminate TLS, so it is only true if a trusted proxy forwarded
+    /// the request with `proto=https`.
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Returns the host the request was sent to
+    ///
+    /// It is the `host` of the `Forwarded` header if a trusted proxy sent one, otherwise the `Host`
+    /// header of the request.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(request.host(), Some("example.com"));
+    /// ```
+    pub fn host(&self) -> Option<&str> {
+        self.forwarded_host
+            .as_deref()
+            .or_else(|| self.get_header("host").map(|host| host.as_str()))
+    }
+
+    /// Applies the `Forwarded` header if the peer is one of the trusted proxies
+    pub(crate) fn apply_forwarded(&mut self, trusted_proxies: &[IpAddr]) {
+        if !trusted_proxies.contains(&self.peer_addr.ip()) {
+            return;
+        }
+
+        let Some(header) = self.get_header("forwarded") else {
+            return;
+        };
+
+        let elements = header.split(',').map(ForwardedElement::parse).collect::<Vec<_>>();
+
+        // All elements may be added by trusted proxies, then the first one is the closest to the client
+        let client = elements
+            .iter()
+            .rev()
+            .find(|element| {
+                !element.for_addr.is_some_and(|addr| trusted_proxies.contains(&addr.ip()))
+            })
+            .or(elements.first());
+
+        let Some(client) = client else {
+            return;
+        };
+
+        if let Some(for_addr) = client.for_addr {
+            self.peer_addr = for_addr;
+        }
+
+        self.secure = client.proto.as_deref() == Some("https");
+        self.forwarded_host = client.host.clone();
+    }
+}
+
+/// Parses a node of the `for` parameter, e.g. `192.0.2.60`, `192.0.2.60:8080` or `[2001:db8::1]:80`
+///
+/// Obfuscated identifiers and `unknown` have no address, so `None` is returned for them.
+fn parse_node(node: &str) -> Option<SocketAddr> {
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+
+    let ip = node.strip_prefix('[').and_then(|node| node.strip_suffix(']')).unwrap_or(node);
+
+    ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 0))
+}
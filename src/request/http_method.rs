@@ -4,7 +4,7 @@
 
 use std::fmt::{ Display, Error, Formatter, Result as fResult };
 
-#[derive(Eq, Hash, PartialEq, Debug)]
+#[derive(Eq, Hash, PartialEq, Debug, Default)]
 /// An enum that represents an HTTP method
 ///
 /// The HTTP method is used to indicate the desired action to be performed for a given resource.
@@ -12,14 +12,19 @@ use std::fmt::{ Display, Error, Formatter, Result as fResult };
 /// # Supported methods
 ///
 /// - GET
+/// - HEAD
 /// - POST
 /// - PUT
 /// - PATCH
 /// - DELETE
+/// - OPTIONS
 ///
 pub enum HttpMethod {
     /// GET method is used to request data from a specified resource
+    #[default]
     GET,
+    /// HEAD method is the same as GET, but the response doesn't contain a body
+    HEAD,
     /// POST method is used to submit data to be processed to a specified resource
     POST,
     /// PUT method is used to update data to a specified resource
@@ -28,21 +33,30 @@ pub enum HttpMethod {
     PATCH,
     /// DELETE method is used to delete a specified resource
     DELETE,
+    /// OPTIONS method is used to describe the communication options for a resource
+    OPTIONS,
     // CONNTECT,
-    // HEAD,
-    // OPTIONS,
     // TRACE,
 }
 
 impl HttpMethod {
-    pub(crate) fn is_valid(method: &str) -> bool {
-        matches!(method, "GET" | "POST" | "PUT" | "PATCH" | "DELETE")
-    }
-}
+    /// All supported methods
+    pub(crate) const ALL: [HttpMethod; 7] = [
+        Self::GET,
+        Self::HEAD,
+        Self::POST,
+        Self::PUT,
+        Self::PATCH,
+        Self::DELETE,
+        Self::OPTIONS,
+    ];
 
-impl Default for HttpMethod {
-    fn default() -> Self {
-        Self::GET
+    /// Returns true if the method only contains token characters (RFC 9110)
+    pub(crate) fn is_token(method: &str) -> bool {
+        !method.is_empty() &&
+            method
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
     }
 }
 
@@ -50,10 +64,12 @@ impl Display for HttpMethod {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
             Self::GET => write!(f, "GET"),
+            Self::HEAD => write!(f, "HEAD"),
             Self::POST => write!(f, "POST"),
             Self::PUT => write!(f, "PUT"),
             Self::PATCH => write!(f, "PATCH"),
             Self::DELETE => write!(f, "DELETE"),
+            Self::OPTIONS => write!(f, "OPTIONS"),
         }
     }
 }
@@ -66,6 +82,8 @@ impl TryFrom<&str> for HttpMethod {
     ///
     /// Returns an error if the string is not a valid HTTP method
     ///
+    /// Methods are matched case-insensitively and normalized to their uppercase form.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -75,15 +93,19 @@ impl TryFrom<&str> for HttpMethod {
     ///   Ok(method) => assert_eq!(method, HttpMethod::GET),
     ///   Err(_) => panic!("Failed to parse HTTP method"),
     /// }
+    ///
+    /// assert_eq!(HttpMethod::try_from("get").unwrap().to_string(), "GET");
     /// ```
     fn try_from(method: &str) -> Result<Self, Self::Error> {
         let method = method.to_uppercase();
         match method.as_str() {
             "GET" => Ok(Self::GET),
+            "HEAD" => Ok(Self::HEAD),
             "POST" => Ok(Self::POST),
             "PUT" => Ok(Self::PUT),
             "PATCH" => Ok(Self::PATCH),
             "DELETE" => Ok(Self::DELETE),
+            "OPTIONS" => Ok(Self::OPTIONS),
             _ => Err(ParseHttpMethodError),
         }
     }
@@ -0,0 +1,302 @@
+//! This module contains the typed deserialization of JSON request bodies.
+//!
+//! JSON bodies are parsed into a `JsonValue` when the request is read, and can be deserialized
+//! into any type implementing `serde::Deserialize` with `Request::body_json`. The options of the
+//! deserialization are set with `Server::set_json_options`, see `body::JsonOptions`.
+
+use std::fmt::{ Display, Formatter, Result as fResult };
+
+use serde::{
+    de::{
+        self,
+        DeserializeOwned,
+        DeserializeSeed,
+        Deserializer,
+        IntoDeserializer,
+        MapAccess,
+        SeqAccess,
+        Visitor,
+    },
+    forward_to_deserialize_any,
+};
+use serde_json::{ Map, Number };
+
+use super::{ body::JsonOptions, Request, RequestBody };
+use crate::json::JsonValue;
+
+impl Request {
+    /// Deserializes the JSON body into the given type
+    ///
+    /// The body is deserialized with the `JsonOptions` of the server. By default, values have to
+    /// match the types of the fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `JsonBodyError::NotJson` if the body was not parsed as JSON, and
+    /// `JsonBodyError::Invalid` if it doesn't match the type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use krustie::{ Request, request::{ JsonBodyError, body::{ BodyParsers, JsonOptions } } };
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 10\r\n\r\n{\"n\":\"42\"}";
+    ///
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert!(matches!(request.body_json::<HashMap<String, u32>>(), Err(JsonBodyError::Invalid(_))));
+    /// assert_eq!(request.body_json::<HashMap<String, String>>().unwrap()["n"], "42");
+    ///
+    /// let mut parsers = BodyParsers::default();
+    /// parsers.set_json_options(JsonOptions { coerce_numbers: true });
+    ///
+    /// let request = Request::parse_from_bytes_with(raw, &parsers).unwrap();
+    ///
+    /// assert_eq!(request.body_json::<HashMap<String, u32>>().unwrap()["n"], 42);
+    /// assert_eq!(request.body_json::<HashMap<String, String>>().unwrap()["n"], "42");
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(request.body_json::<HashMap<String, u32>>().unwrap_err(), JsonBodyError::NotJson);
+    /// ```
+    ///
+    /// Deserializing into a struct
+    ///
+    /// ```rust
+    /// use krustie::{ Request, request::body::{ BodyParsers, JsonOptions } };
+    /// use serde::Deserialize;
+    ///
+    /// struct Item {
+    ///   n: i64,
+    ///   price: Option<f64>,
+    /// }
+    /// # // Written out by hand since the doctests don't enable `serde_derive`
+    /// # impl<'de> Deserialize<'de> for Item {
+    /// #   fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    /// #     struct ItemVisitor;
+    /// #     impl<'de> serde::de::Visitor<'de> for ItemVisitor {
+    /// #       type Value = Item;
+    /// #       fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    /// #         write!(f, "item")
+    /// #       }
+    /// #       fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Item, A::Error> {
+    /// #         let (mut n, mut price) = (None, None);
+    /// #         while let Some(key) = map.next_key::<String>()? {
+    /// #           match key.as_str() {
+    /// #             "n" => n = Some(map.next_value()?),
+    /// #             "price" => price = map.next_value()?,
+    /// #             _ => { map.next_value::<serde::de::IgnoredAny>()?; }
+    /// #           }
+    /// #         }
+    /// #         let n = n.ok_or_else(|| serde::de::Error::missing_field("n"))?;
+    /// #         Ok(Item { n, price })
+    /// #       }
+    /// #     }
+    /// #     deserializer.deserialize_struct("Item", &["n", "price"], ItemVisitor)
+    /// #   }
+    /// # }
+    ///
+    /// let mut parsers = BodyParsers::default();
+    /// parsers.set_json_options(JsonOptions { coerce_numbers: true });
+    ///
+    /// let parse = |body: &str| {
+    ///   let raw = format!(
+    ///     "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+    ///     body.len()
+    ///   );
+    ///   Request::parse_from_bytes_with(raw.as_bytes(), &parsers).unwrap().body_json::<Item>()
+    /// };
+    ///
+    /// let item = parse(r#"{"n":"42","price":"9.5"}"#).unwrap();
+    ///
+    /// assert_eq!(item.n, 42);
+    /// assert_eq!(item.price, Some(9.5));
+    /// assert_eq!(parse(r#"{"n":-7,"price":null}"#).unwrap().price, None);
+    /// assert!(parse(r#"{"n":"forty-two"}"#).is_err());
+    /// assert!(parse(r#"{"n":"4.2"}"#).is_err());
+    /// ```
+    pub fn body_json<T: DeserializeOwned>(&self) -> Result<T, JsonBodyError> {
+        let RequestBody::Json(json) = &self.body else {
+            return Err(JsonBodyError::NotJson);
+        };
+
+        let result = match self.json_options {
+            JsonOptions { coerce_numbers: true } => T::deserialize(CoercingDeserializer(json)),
+            JsonOptions { coerce_numbers: false } => T::deserialize(json),
+        };
+
+        result.map_err(|err| JsonBodyError::Invalid(err.to_string()))
+    }
+}
+
+/// Error returned while deserializing a JSON request body
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonBodyError {
+    /// The request has no body parsed as JSON
+    NotJson,
+    /// The body doesn't match the type
+    Invalid(String),
+}
+
+impl Display for JsonBodyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::NotJson => write!(f, "Request has no JSON body"),
+            Self::Invalid(msg) => write!(f, "Invalid JSON body: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for JsonBodyError {}
+
+/// Deserializes a JSON value, converting strings into numbers and numbers into strings when the
+/// type asks for them
+struct CoercingDeserializer<'a>(&'a JsonValue);
+
+impl<'a> CoercingDeserializer<'a> {
+    /// Returns the number of a number value or a string containing a JSON number
+    fn number(&self) -> Option<Number> {
+        match self.0 {
+            JsonValue::Number(number) => Some(number.clone()),
+            JsonValue::String(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Deserializes numbers from strings, and leaves the other values to `deserialize_any`
+macro_rules! deserialize_number {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.number() {
+                    Some(number) => number.$method(visitor),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for CoercingDeserializer<'de> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Array(items) => visitor.visit_seq(CoercingSeq(items.iter())),
+            JsonValue::Object(map) => visitor.visit_map(CoercingMap::new(map)),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Number(number) => visitor.visit_string(number.to_string()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, Self::Error> {
+        // Enums are deserialized without coercion
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool char bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+/// Deserializes the items of an array with `CoercingDeserializer`
+struct CoercingSeq<'a>(std::slice::Iter<'a, JsonValue>);
+
+impl<'de> SeqAccess<'de> for CoercingSeq<'de> {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.0
+            .next()
+            .map(|item| seed.deserialize(CoercingDeserializer(item)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+/// Deserializes the values of an object with `CoercingDeserializer`
+struct CoercingMap<'a> {
+    entries: serde_json::map::Iter<'a>,
+    value: Option<&'a JsonValue>,
+}
+
+impl<'a> CoercingMap<'a> {
+    fn new(map: &'a Map<String, JsonValue>) -> Self {
+        Self { entries: map.iter(), value: None }
+    }
+}
+
+impl<'de> MapAccess<'de> for CoercingMap<'de> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        self.value = Some(value);
+        seed.deserialize(key.as_str().into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V
+    ) -> Result<V::Value, Self::Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(CoercingDeserializer(value)),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
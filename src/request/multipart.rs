@@ -0,0 +1,532 @@
+//! This module contains the iterator for reading `multipart/form-data` request bodies.
+//!
+//! Multipart bodies of requests from a connection aren't received in full before the handler runs.
+//! `Request::multipart` reads them from the connection as the iterator advances, and the data of
+//! a part is read with `Read` as it arrives, so files can be copied to disk without buffering
+//! them. The declared length of the body is still limited by `ServerConfig::max_body_size`.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{ Debug, Display, Formatter, Result as fResult },
+    io::{ Chain, Cursor, Error as IoError, ErrorKind, Read, Result as IoResult, Take },
+    rc::Rc,
+    sync::{ Mutex, MutexGuard, PoisonError },
+};
+
+use super::Request;
+use crate::server::connection::Socket;
+
+/// Number of bytes that are read from the body at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Maximum size of the headers of a part
+const MAX_PART_HEADERS_SIZE: usize = 8 * 1024;
+
+/// An iterator over the parts of a `multipart/form-data` body
+///
+/// Created by `Request::multipart`. Advancing the iterator skips the unread data of the previous
+/// part. The iterator stops after the first error.
+pub struct Multipart<'a> {
+    body: Rc<RefCell<MultipartBody<'a>>>,
+    is_done: bool,
+}
+
+impl<'a> Multipart<'a> {
+    fn new(reader: Box<dyn Read + 'a>, boundary: &str) -> Self {
+        let body = MultipartBody {
+            reader,
+            // The first delimiter has no line break before it, so one is added to find it like
+            // the others. The bytes before it are the preamble, which is skipped like a part.
+            buffer: b"\r\n".to_vec(),
+            start: 0,
+            is_eof: false,
+            delimiter: format!("\r\n--{boundary}").into_bytes(),
+            part: 0,
+        };
+
+        Self { body: Rc::new(RefCell::new(body)), is_done: false }
+    }
+
+    fn next_part(&mut self) -> Result<Option<Part<'a>>, MultipartError> {
+        let mut body = self.body.borrow_mut();
+        let mut skipped = [0; CHUNK_SIZE];
+
+        while body.read_data(&mut skipped).map_err(read_error)? > 0 {}
+
+        body.start += body.delimiter.len();
+
+        // The delimiter is followed by `--` after the last part, and by a line break and the
+        // headers of the part otherwise
+        let headers_end = loop {
+            let available = &body.buffer[body.start..];
+
+            if available.starts_with(b"--") {
+                return Ok(None);
+            }
+
+            if let Some(end) = find(available, b"\r\n\r\n") {
+                break end;
+            }
+
+            if available.len() > MAX_PART_HEADERS_SIZE || !body.fill().map_err(read_error)? {
+                return Err(MultipartError::Malformed);
+            }
+        };
+
+        let raw_headers = &body.buffer[body.start..body.start + headers_end + 2];
+
+        if !raw_headers.starts_with(b"\r\n") {
+            return Err(MultipartError::Malformed);
+        }
+
+        let headers = Self::parse_headers(raw_headers)?;
+
+        body.start += headers_end + 4;
+        body.part += 1;
+
+        Ok(Some(Part { headers, index: body.part, body: Rc::clone(&self.body) }))
+    }
+
+    fn parse_headers(raw: &[u8]) -> Result<HashMap<String, String>, MultipartError> {
+        let raw = std::str::from_utf8(raw).map_err(|_| MultipartError::Malformed)?;
+
+        raw.split("\r\n")
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                match line.split_once(':') {
+                    Some((key, value)) => Ok((key.trim().to_lowercase(), value.trim().to_string())),
+                    None => Err(MultipartError::Malformed),
+                }
+            })
+            .collect()
+    }
+}
+
+impl<'a> Iterator for Multipart<'a> {
+    type Item = Result<Part<'a>, MultipartError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let part = self.next_part();
+
+        if !matches!(part, Ok(Some(_))) {
+            self.is_done = true;
+        }
+
+        part.transpose()
+    }
+}
+
+impl Debug for Multipart<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        f.debug_struct("Multipart").field("is_done", &self.is_done).finish_non_exhaustive()
+    }
+}
+
+/// The state of a multipart body that is shared by the iterator and its parts
+struct MultipartBody<'a> {
+    reader: Box<dyn Read + 'a>,
+    /// The bytes that are read from the body but not consumed yet, starting at `start`
+    buffer: Vec<u8>,
+    start: usize,
+    is_eof: bool,
+    /// The delimiter before each part, `\r\n--` followed by the boundary
+    delimiter: Vec<u8>,
+    /// The index of the current part, `0` for the preamble
+    part: usize,
+}
+
+impl MultipartBody<'_> {
+    /// Reads more of the body into the buffer, returns false at the end of the body
+    fn fill(&mut self) -> IoResult<bool> {
+        if self.is_eof {
+            return Ok(false);
+        }
+
+        self.buffer.drain(..self.start);
+        self.start = 0;
+
+        let len = self.buffer.len();
+        self.buffer.resize(len + CHUNK_SIZE, 0);
+
+        let read = loop {
+            match self.reader.read(&mut self.buffer[len..]) {
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                read => break read,
+            }
+        };
+
+        self.buffer.truncate(len + read.as_ref().map_or(0, |read| *read));
+        self.is_eof = read? == 0;
+
+        Ok(!self.is_eof)
+    }
+
+    /// Reads the data of the current part, returns `0` once the next delimiter is reached
+    fn read_data(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        loop {
+            let available = &self.buffer[self.start..];
+
+            // The bytes that could be the start of a delimiter are held back until the rest of
+            // it is read
+            let (end, is_delimiter) = match find(available, &self.delimiter) {
+                Some(end) => (end, true),
+                None => (available.len().saturating_sub(self.delimiter.len() - 1), false),
+            };
+
+            if end > 0 || is_delimiter {
+                let len = end.min(buf.len());
+
+                buf[..len].copy_from_slice(&available[..len]);
+                self.start += len;
+
+                return Ok(len);
+            }
+
+            if !self.fill()? {
+                return Err(
+                    IoError::new(ErrorKind::UnexpectedEof, "Multipart body ended before its last part")
+                );
+            }
+        }
+    }
+}
+
+/// A part of a `multipart/form-data` body
+///
+/// The data of the part is read with `Read`. It can only be read until the iterator advances to
+/// the next part, after that the part reads nothing.
+pub struct Part<'a> {
+    headers: HashMap<String, String>,
+    index: usize,
+    body: Rc<RefCell<MultipartBody<'a>>>,
+}
+
+impl Part<'_> {
+    /// Returns the value of a header of the part, e.g. `content-type`
+    pub fn get_header(&self, key: &str) -> Option<&String> {
+        self.headers.get(&key.to_lowercase())
+    }
+
+    /// Returns the `name` parameter of the `Content-Disposition` header
+    pub fn name(&self) -> Option<&str> {
+        self.disposition_param("name")
+    }
+
+    /// Returns the `filename` parameter of the `Content-Disposition` header
+    pub fn filename(&self) -> Option<&str> {
+        self.disposition_param("filename")
+    }
+
+    /// Returns the `Content-Type` header of the part
+    pub fn content_type(&self) -> Option<&String> {
+        self.get_header("content-type")
+    }
+
+    fn disposition_param(&self, name: &str) -> Option<&str> {
+        self.get_header("content-disposition")?
+            .split(';')
+            .skip(1)
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().trim_matches('"'))
+    }
+}
+
+impl Read for Part<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let mut body = self.body.borrow_mut();
+
+        if body.part != self.index {
+            return Ok(0);
+        }
+
+        body.read_data(buf)
+    }
+}
+
+impl Debug for Part<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        f.debug_struct("Part").field("headers", &self.headers).finish_non_exhaustive()
+    }
+}
+
+/// Error returned while reading a multipart body
+#[derive(Debug, PartialEq, Eq)]
+pub enum MultipartError {
+    /// The request is not `multipart/form-data` or has no boundary
+    NotMultipart,
+    /// The body doesn't follow the multipart format
+    Malformed,
+    /// The body is streamed from the connection and was already read by another iterator
+    AlreadyRead,
+    /// The body could not be read from the connection
+    Io,
+}
+
+impl Display for MultipartError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::NotMultipart => write!(f, "Request is not multipart/form-data with a boundary"),
+            Self::Malformed => write!(f, "Malformed multipart body"),
+            Self::AlreadyRead => write!(f, "Multipart body was already read"),
+            Self::Io => write!(f, "Failed to read the multipart body"),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+/// A multipart body that is read from the connection while the request is handled
+pub(crate) struct ConnectionBody {
+    reader: Chain<Cursor<Vec<u8>>, Take<Socket>>,
+    is_read: bool,
+}
+
+impl ConnectionBody {
+    /// Creates the body from the bytes the server already buffered and the socket for the rest
+    pub(crate) fn new(buffered: Vec<u8>, socket: Socket, remaining: usize) -> Self {
+        let reader = Cursor::new(buffered).chain(socket.take(remaining as u64));
+
+        Self { reader, is_read: false }
+    }
+
+    /// Reads the rest of the body, so the next request of the connection starts after it
+    ///
+    /// Returns false if the connection ended before the body.
+    fn skip_rest(&mut self) -> IoResult<bool> {
+        std::io::copy(&mut self.reader, &mut std::io::sink())?;
+        Ok(self.reader.get_ref().1.limit() == 0)
+    }
+}
+
+impl Read for ConnectionBody {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.reader.read(buf)
+    }
+}
+
+/// A connection body that is locked by the iterator reading it
+struct LockedBody<'a>(MutexGuard<'a, ConnectionBody>);
+
+impl Read for LockedBody<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Request {
+    /// Returns an iterator over the parts of a `multipart/form-data` body
+    ///
+    /// For requests from a connection, the body isn't received before the handler runs. The
+    /// iterator reads it from the connection as it advances, and the data of each part is read
+    /// from the connection with `Read`, so large files don't have to fit in memory. Such a body
+    /// can only be iterated once and isn't kept, so `body_text` and `get_body` don't return it.
+    /// The part of it that the handler doesn't read is skipped before the response is sent.
+    ///
+    /// For requests that are parsed from bytes or readers, the parts are read from the received
+    /// body.
+    ///
+    /// The filename of a part is sent by the client, use only its last component before joining it
+    /// to a path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MultipartError::NotMultipart` if the request is not `multipart/form-data` or its
+    /// `Content-Type` has no boundary, and `MultipartError::AlreadyRead` if the body is streamed
+    /// from the connection and was already iterated.
+    ///
+    /// # Example
+    ///
+    /// The upload is copied to a file while the client is still sending it
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Server, StatusCode };
+    /// use std::{
+    ///   fs::File,
+    ///   io::{ self, Read, Write },
+    ///   net::{ TcpListener, TcpStream },
+    ///   path::{ Path, PathBuf },
+    ///   sync::atomic::{ AtomicBool, Ordering },
+    ///   thread,
+    ///   time::{ Duration, Instant },
+    /// };
+    ///
+    /// // Set by the handler once it reads the file part
+    /// static IS_READING: AtomicBool = AtomicBool::new(false);
+    ///
+    /// fn upload_dir() -> PathBuf {
+    ///   std::env::temp_dir().join(format!("krustie-multipart-{}", std::process::id()))
+    /// }
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|req, res| {
+    ///   let parts = match req.multipart() {
+    ///     Ok(parts) => parts,
+    ///     Err(err) => {
+    ///       res.error(StatusCode::BadRequest, &err.to_string());
+    ///       return;
+    ///     }
+    ///   };
+    ///
+    ///   for part in parts {
+    ///     let mut part = part.unwrap();
+    ///
+    ///     // Drops directories such as `../` from the name sent by the client
+    ///     let filename = part.filename().map(Path::new).and_then(Path::file_name);
+    ///
+    ///     if let Some(filename) = filename {
+    ///       let mut file = File::create(upload_dir().join(filename)).unwrap();
+    ///
+    ///       IS_READING.store(true, Ordering::SeqCst);
+    ///       io::copy(&mut part, &mut file).unwrap();
+    ///     }
+    ///   }
+    ///
+    ///   res.status(StatusCode::Created);
+    /// });
+    /// server.use_handler(router);
+    /// std::fs::create_dir_all(upload_dir()).unwrap();
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let head = "--XyZ\r\n\
+    ///   Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+    ///   Numbers\r\n\
+    ///   --XyZ\r\n\
+    ///   Content-Disposition: form-data; name=\"file\"; filename=\"../numbers.txt\"\r\n\
+    ///   Content-Type: text/plain\r\n\r\n";
+    /// let content = "0123456789".repeat(100_000);
+    /// let tail = "\r\n--XyZ--\r\n";
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    ///
+    /// write!(
+    ///   stream,
+    ///   "POST / HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{head}",
+    ///   head.len() + content.len() + tail.len()
+    /// ).unwrap();
+    ///
+    /// // The file is only sent once the handler reads it, which it couldn't do if the body was
+    /// // received in full first
+    /// let started = Instant::now();
+    ///
+    /// while !IS_READING.load(Ordering::SeqCst) {
+    ///   assert!(started.elapsed() < Duration::from_secs(5), "The body was buffered");
+    ///   thread::sleep(Duration::from_millis(10));
+    /// }
+    ///
+    /// stream.write_all(content.as_bytes()).unwrap();
+    /// stream.write_all(tail.as_bytes()).unwrap();
+    ///
+    /// let mut response = String::new();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 201 Created"));
+    /// assert_eq!(std::fs::metadata(upload_dir().join("numbers.txt")).unwrap().len(), 1_000_000);
+    /// ```
+    ///
+    /// Reading the fields of a part
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    /// use std::io::Read;
+    ///
+    /// let body = "--b\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nHello\r\n--b--\r\n";
+    /// let raw = format!(
+    ///   "POST / HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=b\r\nContent-Length: {}\r\n\r\n{body}",
+    ///   body.len()
+    /// );
+    /// let request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    /// let mut parts = request.multipart().unwrap();
+    /// let mut part = parts.next().unwrap().unwrap();
+    /// let mut note = String::new();
+    ///
+    /// part.read_to_string(&mut note).unwrap();
+    ///
+    /// assert_eq!(part.name(), Some("note"));
+    /// assert_eq!(part.filename(), None);
+    /// assert_eq!(note, "Hello");
+    /// assert!(parts.next().is_none());
+    /// ```
+    pub fn multipart(&self) -> Result<Multipart<'_>, MultipartError> {
+        let content_type = self.get_header("content-type").ok_or(MultipartError::NotMultipart)?;
+        let mut params = content_type.split(';');
+
+        if !params.next().unwrap_or_default().trim().eq_ignore_ascii_case("multipart/form-data") {
+            return Err(MultipartError::NotMultipart);
+        }
+
+        let boundary = params
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| value.trim().trim_matches('"'))
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or(MultipartError::NotMultipart)?;
+
+        let reader: Box<dyn Read + '_> = match &self.body_reader {
+            Some(body_reader) => {
+                let mut body = body_reader.try_lock().map_err(|_| MultipartError::AlreadyRead)?;
+
+                if body.is_read {
+                    return Err(MultipartError::AlreadyRead);
+                }
+
+                body.is_read = true;
+                Box::new(LockedBody(body))
+            }
+            None => Box::new(self.raw_body()),
+        };
+
+        Ok(Multipart::new(reader, boundary))
+    }
+
+    /// Returns the length of a body that the parser left on the connection, so the server can
+    /// hand it over with `Request::set_body_reader`
+    pub(crate) fn take_unread_body_length(&mut self) -> Option<usize> {
+        self.unread_body_length.take()
+    }
+
+    /// Sets the body that is read from the connection while the request is handled
+    pub(crate) fn set_body_reader(&mut self, body: ConnectionBody) {
+        self.body_reader = Some(Mutex::new(body));
+    }
+
+    /// Skips the part of the body that wasn't read from the connection
+    ///
+    /// Returns false if it couldn't be skipped, so the connection can't be used for another
+    /// request.
+    pub(crate) fn skip_unread_body(&mut self) -> bool {
+        let Some(body) = self.body_reader.take() else {
+            return true;
+        };
+
+        let mut body = body.into_inner().unwrap_or_else(PoisonError::into_inner);
+
+        body.skip_rest().unwrap_or_else(|e| {
+            log::debug!("Error while skipping the request body: {}", e);
+            false
+        })
+    }
+}
+
+/// Returns the multipart error for an error while reading the body
+fn read_error(err: IoError) -> MultipartError {
+    match err.kind() {
+        ErrorKind::UnexpectedEof => MultipartError::Malformed,
+        _ => MultipartError::Io,
+    }
+}
+
+/// Returns the position of the first occurrence of the needle
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,135 @@
+//! This module contains the iterator for reading newline-delimited JSON (NDJSON) request bodies.
+//!
+//! NDJSON bodies (`application/x-ndjson`) contain one JSON value per line. They are stored as
+//! `RequestBody::Text` and can be read record by record with `Request::ndjson`.
+
+use std::{ fmt::{ Display, Formatter, Result as fResult }, marker::PhantomData };
+
+use serde::de::DeserializeOwned;
+
+use super::{ Request, RequestBody };
+
+/// An iterator over the records of an NDJSON request body
+///
+/// Created by `Request::ndjson`. Empty lines are skipped.
+#[derive(Debug)]
+pub struct NdjsonRecords<'a, T> {
+    remaining: &'a [u8],
+    line_number: usize,
+    _record: PhantomData<T>,
+}
+
+impl<'a, T: DeserializeOwned> Iterator for NdjsonRecords<'a, T> {
+    type Item = Result<T, NdjsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.remaining.is_empty() {
+                return None;
+            }
+
+            self.line_number += 1;
+
+            let (line, is_terminated) = match self.remaining.iter().position(|byte| *byte == b'\n') {
+                Some(end) => {
+                    let line = &self.remaining[..end];
+                    self.remaining = &self.remaining[end + 1..];
+                    (line, true)
+                }
+                None => {
+                    let line = self.remaining;
+                    self.remaining = &[];
+                    (line, false)
+                }
+            };
+
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            if line.iter().all(|byte| byte.is_ascii_whitespace()) {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_slice(line).map_err(|err| {
+                    if is_terminated {
+                        NdjsonError::InvalidRecord(self.line_number, err.to_string())
+                    } else {
+                        NdjsonError::PartialLine(self.line_number)
+                    }
+                })
+            );
+        }
+    }
+}
+
+/// Error returned while reading NDJSON records
+#[derive(Debug, PartialEq, Eq)]
+pub enum NdjsonError {
+    /// The line with the given number is not a valid record
+    InvalidRecord(usize, String),
+    /// The last line is not terminated with a newline and is not a complete record
+    PartialLine(usize),
+}
+
+impl Display for NdjsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::InvalidRecord(line, err) => write!(f, "Invalid NDJSON record on line {line}: {err}"),
+            Self::PartialLine(line) => write!(f, "Incomplete NDJSON record on line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for NdjsonError {}
+
+impl Request {
+    /// Returns an iterator over the records of an NDJSON (`application/x-ndjson`) body
+    ///
+    /// Each line of the body is deserialized into `T`. If the body is not a text body, the
+    /// iterator is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, json::JsonValue };
+    ///
+    /// let body = "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n";
+    /// let raw = format!(
+    ///   "POST /events HTTP/1.1\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\n\r\n{body}",
+    ///   body.len()
+    /// );
+    /// let request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    ///
+    /// let ids = request
+    ///   .ndjson::<JsonValue>()
+    ///   .map(|record| record.unwrap()["id"].as_u64().unwrap())
+    ///   .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(ids, vec![1, 2, 3]);
+    /// ```
+    ///
+    /// A partial trailing line returns an error
+    ///
+    /// ```rust
+    /// use krustie::{ Request, json::JsonValue, request::ndjson::NdjsonError };
+    ///
+    /// let body = "{\"id\":1}\n{\"id\":";
+    /// let raw = format!(
+    ///   "POST /events HTTP/1.1\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\n\r\n{body}",
+    ///   body.len()
+    /// );
+    /// let request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    /// let mut records = request.ndjson::<JsonValue>();
+    ///
+    /// assert!(records.next().unwrap().is_ok());
+    /// assert_eq!(records.next().unwrap(), Err(NdjsonError::PartialLine(2)));
+    /// ```
+    pub fn ndjson<T: DeserializeOwned>(&self) -> NdjsonRecords<'_, T> {
+        let remaining = match self.get_body() {
+            RequestBody::Text(body) => body.as_slice(),
+            _ => &[],
+        };
+
+        NdjsonRecords { remaining, line_number: 0, _record: PhantomData }
+    }
+}
@@ -1,119 +1,458 @@
 use std::{
     collections::HashMap,
-    io::{ BufRead, BufReader, Error, ErrorKind, Read },
-    net::TcpStream,
+    io::{ BufRead, BufReader, Error as IoError, ErrorKind, Read },
+    net::{ IpAddr, Ipv4Addr, SocketAddr },
 };
 
-use super::{ request_line::RequestLine, Request, ParseHttpRequestError, RequestBody };
+use super::{
+    body::BodyParsers,
+    http_method::HttpMethod,
+    request_line::RequestLine,
+    Request,
+    ParseHttpRequestError,
+    RequestBody,
+};
+use crate::middleware::gzip::{ decode_body_limited, DecodeError };
 
+/// Maximum number of header lines of a request
 const MAX_HEADER: usize = 100;
 
+/// Default maximum length of the request line in bytes
+pub(crate) const DEFAULT_MAX_REQUEST_LINE_LENGTH: usize = 8192;
+
+/// Default maximum size of the request body in bytes
+pub(crate) const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
 impl Request {
-    /// Parses a TcpStream into Request
-    pub(crate) fn parse(mut stream: &TcpStream) -> Result<Self, Error> {
-        let peer_addr = stream.peer_addr()?;
+    /// Parses a raw HTTP request from a byte slice
+    ///
+    /// Peer address of the returned request is set to `0.0.0.0:0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Request, HttpMethod, request::RequestBody };
+    ///
+    /// let raw = b"POST /echo HTTP/1.1\r\n\
+    ///   Host: localhost:8080\r\n\
+    ///   Content-Type: application/json\r\n\
+    ///   Content-Length: 17\r\n\
+    ///   \r\n\
+    ///   {\"name\":\"krusty\"}";
+    ///
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    ///
+    /// assert_eq!(request.get_path(), "/echo");
+    /// assert_eq!(request.get_header("host").unwrap(), "localhost:8080");
+    ///
+    /// match request.get_body() {
+    ///   RequestBody::Json(json) => assert_eq!(json["name"], "krusty"),
+    ///   _ => panic!("Body should be parsed as JSON"),
+    /// }
+    /// ```
+    ///
+    /// Methods are case-insensitive, but must only contain token characters
+    ///
+    /// ```rust
+    /// use krustie::{ Request, HttpMethod, StatusCode, request::ParseHttpRequestError };
+    ///
+    /// let request = Request::parse_from_bytes(b"get / HTTP/1.1\r\n\r\n").unwrap();
+    /// assert_eq!(request.get_method(), &HttpMethod::GET);
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// assert_eq!(request.get_method(), &HttpMethod::GET);
+    ///
+    /// for raw in [&b"G ET / HTTP/1.1\r\n\r\n"[..], b"G@T / HTTP/1.1\r\n\r\n"] {
+    ///   let err = Request::parse_from_bytes(raw).unwrap_err();
+    ///
+    ///   assert_eq!(err, ParseHttpRequestError::InvalidRequestLine);
+    ///   assert_eq!(err.status_code(), StatusCode::BadRequest);
+    /// }
+    /// ```
+    pub fn parse_from_bytes(bytes: &[u8]) -> Result<Self, ParseHttpRequestError> {
+        Self::parse_from_reader(bytes)
+    }
+
+    /// Parses a raw HTTP request from a byte slice using the given body parsers
+    ///
+    /// Peer address of the returned request is set to `0.0.0.0:0`.
+    pub fn parse_from_bytes_with(
+        bytes: &[u8],
+        parsers: &BodyParsers
+    ) -> Result<Self, ParseHttpRequestError> {
+        let peer_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0);
+
+        Self::read_from(
+            &mut BufReader::new(bytes),
+            peer_addr,
+            parsers,
+            DEFAULT_MAX_REQUEST_LINE_LENGTH,
+            DEFAULT_MAX_BODY_SIZE,
+            false
+        )
+    }
+
+    /// Parses a raw HTTP request from any type that implements `Read`
+    ///
+    /// Reading stops after the body declared by `Content-Length` is consumed. Requests without a
+    /// `Content-Length` or with `Content-Length: 0` have no body, so nothing is read after the
+    /// headers. Peer address of the returned request is set to `0.0.0.0:0`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParseHttpRequestError::InvalidHeader` if `Content-Length` is not a number,
+    /// `ParseHttpRequestError::BodyTooLarge` if it is above the default limit of 10 MiB and
+    /// `ParseHttpRequestError::IncompleteBody` if the body is shorter than declared.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    /// use std::io::Cursor;
+    ///
+    /// let reader = Cursor::new(b"GET /hello/world HTTP/1.1\r\nAccept: */*\r\n\r\n".to_vec());
+    /// let request = Request::parse_from_reader(reader).unwrap();
+    ///
+    /// assert_eq!(request.get_path_array(), &vec!["hello".to_string(), "world".to_string()]);
+    /// ```
+    ///
+    /// Only the declared number of bytes is read as the body
+    ///
+    /// ```rust
+    /// use krustie::{ Request, request::{ ParseHttpRequestError, RequestBody } };
+    ///
+    /// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// assert!(matches!(request.get_body(), RequestBody::None));
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 0\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// assert!(matches!(request.get_body(), RequestBody::None));
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nHelloWorld";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// assert_eq!(request.body_text().unwrap(), "Hello");
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: five\r\n\r\n";
+    /// let err = Request::parse_from_bytes(raw).unwrap_err();
+    /// assert_eq!(err, ParseHttpRequestError::InvalidHeader);
+    /// ```
+    pub fn parse_from_reader(reader: impl Read) -> Result<Self, ParseHttpRequestError> {
+        let peer_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0);
+
+        Self::read_from(
+            &mut BufReader::new(reader),
+            peer_addr,
+            &BodyParsers::default(),
+            DEFAULT_MAX_REQUEST_LINE_LENGTH,
+            DEFAULT_MAX_BODY_SIZE,
+            false
+        )
+    }
+
+    /// Parses the next request of a buffered connection stream into Request
+    ///
+    /// The reader has to be kept for the following requests of the connection, since it may have
+    /// buffered their bytes. Bodies that are streamed to the handler are left unread, so the
+    /// server has to hand them over to the request before it is handled.
+    pub(crate) fn parse(
+        reader: &mut impl BufRead,
+        peer_addr: SocketAddr,
+        parsers: &BodyParsers,
+        max_request_line_length: usize,
+        max_body_size: usize
+    ) -> Result<Self, ParseHttpRequestError> {
+        Self::read_from(reader, peer_addr, parsers, max_request_line_length, max_body_size, true)
+    }
+
+    /// Reads a request from the reader
+    ///
+    /// If `is_connection` is true, a body that is streamed to the handler is left unread, see
+    /// `Request::multipart`.
+    fn read_from<R: BufRead>(
+        reader: &mut R,
+        peer_addr: SocketAddr,
+        parsers: &BodyParsers,
+        max_request_line_length: usize,
+        max_body_size: usize,
+        is_connection: bool
+    ) -> Result<Self, ParseHttpRequestError> {
+        let mut header_lines = Vec::new();
+
+        match reader.fill_buf().map_err(read_error) {
+            Ok([]) => {
+                return Err(ParseHttpRequestError::ConnectionClosed);
+            }
+            Ok(_) => (),
+            // An idle connection that times out is closed like one closed by the client
+            Err(ParseHttpRequestError::Timeout) => {
+                return Err(ParseHttpRequestError::ConnectionClosed);
+            }
+            Err(err) => {
+                return Err(err);
+            }
+        }
 
-        let mut buf_reader = BufReader::new(&mut stream);
-        let mut http_request = Vec::new();
+        let request_line = Self::read_request_line(reader, max_request_line_length)?;
 
-        // Don't touch this. It's too sensitive :(((.
-        for line_result in buf_reader.by_ref().lines() {
-            let line = line_result.unwrap();
+        loop {
+            let line = Self::read_line(reader)?;
             if line.is_empty() {
                 break;
             }
-            http_request.push(line);
+            if header_lines.len() == MAX_HEADER {
+                return Err(ParseHttpRequestError::TooManyHeaders);
+            }
+            header_lines.push(line);
         }
 
-        let request_line = RequestLine::try_from(http_request[0].as_str());
+        let request_line = RequestLine::try_from(request_line.as_str())?;
 
-        if request_line.is_err() {
-            return Err(
-                Error::new(ErrorKind::InvalidInput, "Error while parsing request line".to_string())
-            );
+        if request_line.get_version() != "HTTP/1.1" {
+            return Err(ParseHttpRequestError::UnsupportedVersion);
         }
 
-        let request_line = request_line.unwrap();
+        let mut headers = HashMap::new();
+        let mut raw_headers = HashMap::new();
 
-        if request_line.get_version() != "HTTP/1.1" {
-            return Err(Error::new(ErrorKind::InvalidInput, "Invalid HTTP version".to_string()));
+        for line in &header_lines {
+            let (key, value) = Self::parse_header(line)?;
+
+            // A repeated `Content-Length` is only allowed with the same value
+            let is_conflicting_length = key == "content-length" &&
+                headers.get(&key).is_some_and(|length: &String| length.as_bytes() != value);
+
+            if is_conflicting_length {
+                return Err(ParseHttpRequestError::AmbiguousBodyLength);
+            }
+
+            // Values are opaque octets, so the ones that aren't valid UTF-8 are decoded lossily and
+            // kept as they are for `get_header_bytes`
+            match String::from_utf8(value.to_vec()) {
+                Ok(value) => {
+                    raw_headers.remove(&key);
+                    headers.insert(key, value);
+                }
+                Err(_) => {
+                    headers.insert(key.clone(), String::from_utf8_lossy(value).to_string());
+                    raw_headers.insert(key, value.to_vec());
+                }
+            }
         }
 
-        let headers: HashMap<String, String> = http_request
-            .iter()
-            .skip(1)
-            .take(MAX_HEADER)
-            .filter_map(Request::header_parser())
-            .collect();
+        // The body length can't be determined safely if both are sent, so the request is rejected
+        // instead of choosing one of them like a proxy in front of the server might not
+        if headers.contains_key("content-length") && headers.contains_key("transfer-encoding") {
+            return Err(ParseHttpRequestError::AmbiguousBodyLength);
+        }
 
-        let content_length = Self::parse_length(&http_request).unwrap_or(0);
+        // Transfer codings are not decoded, so the end of such a body is unknown and the bytes
+        // after the headers can't be read as the next request
+        if headers.contains_key("transfer-encoding") {
+            return Err(ParseHttpRequestError::UnsupportedTransferEncoding);
+        }
+
+        let content_length = Self::parse_length(&headers)?.unwrap_or(0);
 
         if content_length == 0 {
             return Ok(Request {
-                request: RequestLine::try_from(http_request[0].as_str()).unwrap(),
+                request: request_line,
                 headers,
+                raw_headers,
                 peer_addr,
                 body: RequestBody::None,
+                json_options: parsers.json_options(),
+                ..Request::default()
             });
         }
 
-        let mut body = Vec::with_capacity(content_length);
+        // The declared length is checked before reading, and the body is read as it arrives instead
+        // of allocating the declared length up front
+        if content_length > max_body_size {
+            return Err(ParseHttpRequestError::BodyTooLarge);
+        }
 
-        buf_reader.take(content_length as u64).read_to_end(&mut body)?;
+        // Encoded bodies are decoded in full, so only the others can be streamed
+        let is_streamed = is_connection &&
+            !headers.contains_key("content-encoding") &&
+            headers.get("content-type").is_some_and(|content_type| parsers.is_streamed(content_type));
 
-        let body: RequestBody = Self::parse_body(body, &headers)?;
+        if is_streamed {
+            return Ok(Request {
+                request: request_line,
+                headers,
+                raw_headers,
+                peer_addr,
+                body: RequestBody::None,
+                unread_body_length: Some(content_length),
+                json_options: parsers.json_options(),
+                ..Request::default()
+            });
+        }
+
+        let mut body = Vec::new();
+
+        reader
+            .take(content_length as u64)
+            .read_to_end(&mut body)
+            .map_err(read_error)?;
+
+        if body.len() < content_length {
+            return Err(ParseHttpRequestError::IncompleteBody);
+        }
+
+        if let Some(max_size) = parsers.decompression_limit() {
+            let max_size = max_size.min(max_body_size);
+            body = Self::decode_body(body, &mut headers, &mut raw_headers, max_size)?;
+        }
+
+        let (body, raw_body) = Self::parse_body(body, &headers, parsers)?;
 
         Ok(Request {
             request: request_line,
             headers,
+            raw_headers,
             peer_addr,
             body,
+            raw_body,
+            json_options: parsers.json_options(),
+            ..Request::default()
         })
     }
 
-    fn header_parser() -> impl Fn(&String) -> Option<(String, String)> {
-        |line: &String| {
-            let header_line: Vec<&str> = line.split(':').collect();
+    /// Reads the request line without reading more than the maximum length
+    fn read_request_line<R: BufRead>(
+        reader: &mut R,
+        max_length: usize
+    ) -> Result<String, ParseHttpRequestError> {
+        let mut line = Vec::new();
 
-            if header_line.len() == 2 {
-                let key = header_line[0].trim().to_lowercase().to_string();
-                let value = header_line[1].trim().to_string();
+        // The limit includes the trailing `\r\n`
+        reader
+            .take((max_length as u64) + 2)
+            .read_until(b'\n', &mut line)
+            .map_err(read_error)?;
 
-                Some((key, value))
-            } else {
-                None
+        if !line.ends_with(b"\n") {
+            if line.len() > max_length {
+                return Err(ParseHttpRequestError::UriTooLong);
             }
+            return Err(ParseHttpRequestError::InvalidRequestLine);
+        }
+
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
         }
+
+        if line.len() > max_length {
+            return Err(ParseHttpRequestError::UriTooLong);
+        }
+
+        String::from_utf8(line).map_err(|_| ParseHttpRequestError::InvalidRequestLine)
     }
 
-    fn parse_length(headers: &[String]) -> Option<usize> {
-        for line in headers.iter() {
-            if line.starts_with("Content-Length") {
-                match line.find(':') {
-                    Some(index) => {
-                        return Some(line[index + 1..].trim().parse::<usize>().unwrap_or(0));
-                    }
-                    None => {
-                        return None;
-                    }
-                }
-            }
+    /// Reads a single line without the trailing `\r\n`
+    fn read_line<R: BufRead>(reader: &mut R) -> Result<Vec<u8>, ParseHttpRequestError> {
+        let mut line = Vec::new();
+
+        reader.read_until(b'\n', &mut line).map_err(read_error)?;
+
+        if line.ends_with(b"\n") {
+            line.pop();
+        }
+        if line.ends_with(b"\r") {
+            line.pop();
         }
-        return None;
+
+        Ok(line)
     }
 
-    fn parse_body(body: Vec<u8>, headers: &HashMap<String, String>) -> Result<RequestBody, Error> {
-        if body.is_empty() {
-            return Err(Error::new(std::io::ErrorKind::NotFound, "Body is empty."));
+    /// Splits a header line into its lowercase name and its trimmed value
+    ///
+    /// The name must be a token. The value may contain any byte except control characters other
+    /// than horizontal tabs.
+    fn parse_header(line: &[u8]) -> Result<(String, &[u8]), ParseHttpRequestError> {
+        let colon = line
+            .iter()
+            .position(|byte| *byte == b':')
+            .ok_or(ParseHttpRequestError::InvalidHeader)?;
+        let key = std::str::from_utf8(&line[..colon]).map_err(|_| ParseHttpRequestError::InvalidHeader)?;
+        let value = line[colon + 1..].trim_ascii();
+
+        if !HttpMethod::is_token(key) {
+            return Err(ParseHttpRequestError::InvalidHeader);
+        }
+
+        if value.iter().any(|byte| byte.is_ascii_control() && *byte != b'\t') {
+            return Err(ParseHttpRequestError::InvalidHeader);
+        }
+
+        Ok((key.to_lowercase(), value))
+    }
+
+    /// Returns the declared body length, or `None` if there is no `Content-Length` header
+    fn parse_length(
+        headers: &HashMap<String, String>
+    ) -> Result<Option<usize>, ParseHttpRequestError> {
+        match headers.get("content-length") {
+            Some(length) => {
+                length
+                    .parse::<usize>()
+                    .map(Some)
+                    .map_err(|_| ParseHttpRequestError::InvalidHeader)
+            }
+            None => Ok(None),
         }
+    }
+
+    /// Decodes the body with the codings of the `Content-Encoding` header
+    ///
+    /// The headers are updated to describe the decoded body, so it isn't decoded again.
+    fn decode_body(
+        body: Vec<u8>,
+        headers: &mut HashMap<String, String>,
+        raw_headers: &mut HashMap<String, Vec<u8>>,
+        max_size: usize
+    ) -> Result<Vec<u8>, ParseHttpRequestError> {
+        let Some(content_encoding) = headers.remove("content-encoding") else {
+            return Ok(body);
+        };
 
+        raw_headers.remove("content-encoding");
+
+        let body = decode_body_limited(&body, &content_encoding, max_size).map_err(|err| {
+            match err {
+                DecodeError::TooLarge => ParseHttpRequestError::BodyTooLarge,
+                DecodeError::Invalid(_) => ParseHttpRequestError::InvalidContentEncoding,
+                DecodeError::Unsupported(_) => ParseHttpRequestError::UnsupportedContentEncoding,
+            }
+        })?;
+
+        headers.insert("content-length".to_string(), body.len().to_string());
+
+        Ok(body)
+    }
+
+    fn parse_body(
+        body: Vec<u8>,
+        headers: &HashMap<String, String>,
+        parsers: &BodyParsers
+    ) -> Result<(RequestBody, Option<Vec<u8>>), ParseHttpRequestError> {
         match headers.get("content-type") {
             Some(content_type) => {
-                return RequestBody::parse(body, content_type);
+                return parsers.parse(body, content_type);
             }
             None => {
-                return Err(Error::new(ErrorKind::NotFound, ParseHttpRequestError.to_string()));
+                return Err(ParseHttpRequestError::MissingContentType);
             }
         }
     }
 }
+
+/// Converts a read error, telling a timeout of the connection apart from the other errors
+fn read_error(err: IoError) -> ParseHttpRequestError {
+    match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => ParseHttpRequestError::Timeout,
+        _ => ParseHttpRequestError::Io,
+    }
+}
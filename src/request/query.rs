@@ -0,0 +1,331 @@
+//! This module contains the typed deserialization of query strings.
+//!
+//! Query strings can be deserialized into any type implementing `serde::Deserialize` with
+//! `Request::query_as`. Values are parsed into the type of the field, missing `Option` fields are
+//! `None` and repeated keys can be collected into a `Vec`.
+
+use std::fmt::{ Display, Formatter, Result as fResult };
+
+use serde::de::{
+    self,
+    value::StringDeserializer,
+    DeserializeOwned,
+    DeserializeSeed,
+    IntoDeserializer,
+    MapAccess,
+    SeqAccess,
+    Visitor,
+};
+
+use super::{ urlencoded, Request };
+
+impl Request {
+    /// Deserializes the query string into the given type
+    ///
+    /// Values are parsed into the type of the field. Missing `Option` fields are `None` and
+    /// repeated keys (`?tag=a&tag=b`) can be collected into a `Vec`. If a key is repeated for a
+    /// single value, the last one is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value can't be parsed or a required field is missing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use krustie::Request;
+    ///
+    /// let request = Request::parse_from_bytes(b"GET /users?page=2&size=10 HTTP/1.1\r\n\r\n").unwrap();
+    /// let query = request.query_as::<HashMap<String, u32>>().unwrap();
+    ///
+    /// assert_eq!(query["page"], 2);
+    /// assert_eq!(query["size"], 10);
+    ///
+    /// let request = Request::parse_from_bytes(b"GET /users?page=two HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert!(request.query_as::<HashMap<String, u32>>().is_err());
+    /// ```
+    ///
+    /// Deserializing into a struct
+    ///
+    /// ```rust
+    /// use krustie::Request;
+    /// use serde::Deserialize;
+    ///
+    /// struct Filters {
+    ///   page: u32,
+    ///   size: Option<u32>,
+    ///   tags: Vec<String>,
+    /// }
+    /// # // Written out by hand since the doctests don't enable `serde_derive`
+    /// # impl<'de> Deserialize<'de> for Filters {
+    /// #   fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    /// #     struct FiltersVisitor;
+    /// #     impl<'de> serde::de::Visitor<'de> for FiltersVisitor {
+    /// #       type Value = Filters;
+    /// #       fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    /// #         write!(f, "filters")
+    /// #       }
+    /// #       fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Filters, A::Error> {
+    /// #         let (mut page, mut size, mut tags) = (None, None, Vec::new());
+    /// #         while let Some(key) = map.next_key::<String>()? {
+    /// #           match key.as_str() {
+    /// #             "page" => page = Some(map.next_value()?),
+    /// #             "size" => size = map.next_value()?,
+    /// #             "tags" => tags = map.next_value()?,
+    /// #             _ => { map.next_value::<serde::de::IgnoredAny>()?; }
+    /// #           }
+    /// #         }
+    /// #         let page = page.ok_or_else(|| serde::de::Error::missing_field("page"))?;
+    /// #         Ok(Filters { page, size, tags })
+    /// #       }
+    /// #     }
+    /// #     deserializer.deserialize_struct("Filters", &["page", "size", "tags"], FiltersVisitor)
+    /// #   }
+    /// # }
+    ///
+    /// let raw = b"GET /posts?page=2&size=10&tags=rust&tags=http HTTP/1.1\r\n\r\n";
+    /// let request = Request::parse_from_bytes(raw).unwrap();
+    /// let filters = request.query_as::<Filters>().unwrap();
+    ///
+    /// assert_eq!(filters.page, 2);
+    /// assert_eq!(filters.size, Some(10));
+    /// assert_eq!(filters.tags, vec!["rust", "http"]);
+    ///
+    /// let request = Request::parse_from_bytes(b"GET /posts?page=1 HTTP/1.1\r\n\r\n").unwrap();
+    /// let filters = request.query_as::<Filters>().unwrap();
+    ///
+    /// assert_eq!(filters.size, None);
+    /// assert!(filters.tags.is_empty());
+    /// ```
+    pub fn query_as<T: DeserializeOwned>(&self) -> Result<T, QueryError> {
+        let query = self.request.get_query().map(String::as_bytes).unwrap_or_default();
+        let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+
+        for (key, value) in urlencoded::parse_pairs(query) {
+            match entries.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, values)) => values.push(value),
+                None => entries.push((key, vec![value])),
+            }
+        }
+
+        T::deserialize(QueryDeserializer { entries })
+    }
+}
+
+/// Error returned while deserializing a query string
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryError {
+    /// The value can't be parsed into the type of the field
+    InvalidValue(String),
+    /// The query doesn't match the type, e.g. a required field is missing
+    Custom(String),
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::InvalidValue(value) => write!(f, "Invalid query value: {value}"),
+            Self::Custom(msg) => write!(f, "Invalid query: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl de::Error for QueryError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes the whole query as a map of keys to their values
+struct QueryDeserializer {
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl<'de> de::Deserializer<'de> for QueryDeserializer {
+    type Error = QueryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_map(QueryMap { entries: self.entries.into_iter(), values: None })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct QueryMap {
+    entries: std::vec::IntoIter<(String, Vec<String>)>,
+    values: Option<Vec<String>>,
+}
+
+impl<'de> MapAccess<'de> for QueryMap {
+    type Error = QueryError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K
+    ) -> Result<Option<K::Value>, QueryError> {
+        match self.entries.next() {
+            Some((key, values)) => {
+                self.values = Some(values);
+                let key: StringDeserializer<QueryError> = key.into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, QueryError> {
+        let values = self.values.take().unwrap_or_default();
+        seed.deserialize(ValuesDeserializer(values))
+    }
+}
+
+/// Deserializes all values of a key, either as a sequence or as the last value
+struct ValuesDeserializer(Vec<String>);
+
+impl ValuesDeserializer {
+    fn last(mut self) -> ValueDeserializer {
+        ValueDeserializer(self.0.pop().unwrap_or_default())
+    }
+}
+
+/// Forwards the methods to the deserializer of the last value
+macro_rules! forward_to_last {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+                de::Deserializer::$method(self.last(), visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValuesDeserializer {
+    type Error = QueryError;
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_seq(QuerySeq(self.0.into_iter()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V
+    ) -> Result<V::Value, QueryError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, QueryError> {
+        self.last().deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_last! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_identifier
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit unit_struct tuple tuple_struct map struct ignored_any
+    }
+}
+
+struct QuerySeq(std::vec::IntoIter<String>);
+
+impl<'de> SeqAccess<'de> for QuerySeq {
+    type Error = QueryError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T
+    ) -> Result<Option<T::Value>, QueryError> {
+        match self.0.next() {
+            Some(value) => seed.deserialize(ValueDeserializer(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Deserializes a single value by parsing it into the requested type
+struct ValueDeserializer(String);
+
+/// Parses the value with `FromStr` and visits the result
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(QueryError::InvalidValue(self.0)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = QueryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, QueryError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V
+    ) -> Result<V::Value, QueryError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, QueryError> {
+        let value: StringDeserializer<QueryError> = self.0.into_deserializer();
+        visitor.visit_enum(value)
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
@@ -1,10 +1,12 @@
-use std::fmt::{ Display, Formatter, Result as fResult };
+use std::{ collections::HashMap, fmt::{ Display, Formatter, Result as fResult } };
 
-use super::http_method::HttpMethod;
+use super::{ http_method::HttpMethod, urlencoded, ParseHttpRequestError };
 
 pub(crate) struct RequestLine {
     method: HttpMethod,
     path: String,
+    query: Option<String>,
+    query_params: HashMap<String, String>,
     version: String,
     path_array: Vec<String>,
 }
@@ -12,9 +14,17 @@ pub(crate) struct RequestLine {
 impl RequestLine {
     pub(super) fn new(
         method: &str,
-        path: &str,
+        target: &str,
         version: &str
-    ) -> Result<Self, ParseRequestLineError> {
+    ) -> Result<Self, ParseHttpRequestError> {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (target, None),
+        };
+        let query_params = query
+            .as_ref()
+            .map(|query| urlencoded::parse(query.as_bytes()))
+            .unwrap_or_default();
         let path_array: Vec<String> = path[1..]
             .split('/')
             .map(|str| str.to_string())
@@ -24,11 +34,13 @@ impl RequestLine {
                 Ok(Self {
                     method,
                     path: path.to_string(),
+                    query,
+                    query_params,
                     version: version.to_string(),
                     path_array,
                 }),
             Err(_) => {
-                return Err(ParseRequestLineError);
+                return Err(ParseHttpRequestError::UnsupportedMethod);
             }
         }
     }
@@ -37,6 +49,10 @@ impl RequestLine {
         &self.method
     }
 
+    pub(super) fn set_method(&mut self, method: HttpMethod) {
+        self.method = method;
+    }
+
     pub(super) fn get_path_array(&self) -> &Vec<String> {
         &self.path_array
     }
@@ -48,43 +64,47 @@ impl RequestLine {
     pub(super) fn get_path(&self) -> &String {
         &self.path
     }
+
+    pub(super) fn get_query(&self) -> Option<&String> {
+        self.query.as_ref()
+    }
+
+    pub(super) fn get_query_params(&self) -> &HashMap<String, String> {
+        &self.query_params
+    }
 }
 
 impl Display for RequestLine {
     fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
-        write!(f, "{} {} {}", self.method, self.path, self.version)
+        match &self.query {
+            Some(query) => write!(f, "{} {}?{} {}", self.method, self.path, query, self.version),
+            None => write!(f, "{} {} {}", self.method, self.path, self.version),
+        }
     }
 }
 
 impl TryFrom<&str> for RequestLine {
-    type Error = ParseRequestLineError;
+    type Error = ParseHttpRequestError;
     fn try_from(request_line: &str) -> Result<Self, Self::Error> {
         let request_line: Vec<&str> = request_line.split(' ').collect();
 
-        if request_line.len() != 3 {
-            return Err(ParseRequestLineError);
-        }
+        // `*` is only allowed as the target of server-wide OPTIONS requests
+        let is_asterisk = request_line.len() == 3 &&
+            request_line[1] == "*" &&
+            request_line[0].eq_ignore_ascii_case("OPTIONS");
 
         if
-            !HttpMethod::is_valid(request_line[0]) ||
-            !request_line[1].starts_with('/') ||
+            request_line.len() != 3 ||
+            !(request_line[1].starts_with('/') || is_asterisk) ||
             !request_line[2].starts_with("HTTP/")
         {
-            return Err(ParseRequestLineError);
+            return Err(ParseHttpRequestError::InvalidRequestLine);
         }
 
-        match Self::new(request_line[0], request_line[1], request_line[2]) {
-            Ok(request_line) => Ok(request_line),
-            Err(_) => Err(ParseRequestLineError),
+        if !HttpMethod::is_token(request_line[0]) {
+            return Err(ParseHttpRequestError::InvalidRequestLine);
         }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub(crate) struct ParseRequestLineError;
 
-impl Display for ParseRequestLineError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
-        write!(f, "invalid request line")
+        Self::new(request_line[0], request_line[1], request_line[2])
     }
 }
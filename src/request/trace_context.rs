@@ -0,0 +1,173 @@
+//! This module contains the `TraceContext` of the W3C Trace Context headers.
+//!
+//! The `traceparent` and `tracestate` headers carry the trace a request belongs to. Requests that
+//! are sent while handling a request can carry the same context by adding the headers returned by
+//! `TraceContext::to_headers`.
+
+use std::fmt::{ Display, Formatter, Result as fResult };
+
+use super::Request;
+
+/// The trace context of a request, parsed from the `traceparent` and `tracestate` headers
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::Request;
+///
+/// let raw = b"GET / HTTP/1.1\r\n\
+///   traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01\r\n\
+///   tracestate: vendor=value\r\n\r\n";
+/// let request = Request::parse_from_bytes(raw).unwrap();
+/// let context = request.trace_context().unwrap();
+///
+/// assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+/// assert_eq!(context.parent_id(), "00f067aa0ba902b7");
+/// assert!(context.is_sampled());
+/// assert_eq!(context.trace_state(), Some("vendor=value"));
+/// assert_eq!(
+///   context.to_headers(),
+///   vec![
+///     ("traceparent".to_string(), "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string()),
+///     ("tracestate".to_string(), "vendor=value".to_string()),
+///   ]
+/// );
+/// ```
+///
+/// Missing and invalid headers have no trace context
+///
+/// ```rust
+/// use krustie::Request;
+///
+/// let request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// assert!(request.trace_context().is_none());
+///
+/// for traceparent in [
+///   "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+///   "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+///   "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+///   "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+///   "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+///   "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+/// ] {
+///   let raw = format!("GET / HTTP/1.1\r\ntraceparent: {traceparent}\r\n\r\n");
+///   let request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+///
+///   assert!(request.trace_context().is_none(), "{traceparent}");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    version: u8,
+    trace_id: String,
+    parent_id: String,
+    flags: u8,
+    trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Parses the values of the `traceparent` and `tracestate` headers
+    ///
+    /// Returns `None` if the `traceparent` is invalid. Future versions may add fields after the
+    /// flags, which are ignored.
+    pub fn parse(traceparent: &str, trace_state: Option<&str>) -> Option<Self> {
+        let fields = traceparent.trim().split('-').collect::<Vec<_>>();
+
+        let [version, trace_id, parent_id, flags, rest @ ..] = fields.as_slice() else {
+            return None;
+        };
+
+        let version = parse_hex_byte(version)?;
+
+        // Version 255 is invalid and version 0 has exactly four fields
+        if version == 0xff || (version == 0 && !rest.is_empty()) {
+            return None;
+        }
+
+        if !is_hex_id(trace_id, 32) || !is_hex_id(parent_id, 16) {
+            return None;
+        }
+
+        Some(Self {
+            version,
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            flags: parse_hex_byte(flags)?,
+            trace_state: trace_state
+                .map(|state| state.trim().to_string())
+                .filter(|state| !state.is_empty()),
+        })
+    }
+
+    /// Returns the ID of the trace as 32 lowercase hex digits
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// Returns the ID of the span of the caller as 16 lowercase hex digits
+    pub fn parent_id(&self) -> &str {
+        &self.parent_id
+    }
+
+    /// Returns the trace flags
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns true if the caller may have recorded the trace
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 == 0x01
+    }
+
+    /// Returns the vendor specific trace state
+    pub fn trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    /// Returns the headers for propagating the context, `traceparent` and `tracestate` if it is set
+    pub fn to_headers(&self) -> Vec<(String, String)> {
+        let mut headers = vec![(String::from("traceparent"), self.to_string())];
+
+        if let Some(trace_state) = &self.trace_state {
+            headers.push((String::from("tracestate"), trace_state.clone()));
+        }
+
+        headers
+    }
+}
+
+impl Display for TraceContext {
+    /// Formats the context as a `traceparent` header value
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        write!(f, "{:02x}-{}-{}-{:02x}", self.version, self.trace_id, self.parent_id, self.flags)
+    }
+}
+
+impl Request {
+    /// Returns the trace context of the `traceparent` and `tracestate` headers
+    ///
+    /// Returns `None` if the `traceparent` header is missing or invalid, see `TraceContext`.
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        let traceparent = self.get_header("traceparent")?;
+
+        TraceContext::parse(traceparent, self.get_header("tracestate").map(|state| state.as_str()))
+    }
+}
+
+/// Parses two lowercase hex digits
+fn parse_hex_byte(value: &str) -> Option<u8> {
+    if !is_lower_hex(value, 2) {
+        return None;
+    }
+
+    u8::from_str_radix(value, 16).ok()
+}
+
+/// Returns true if the value is an ID of lowercase hex digits that is not all zeros
+fn is_hex_id(value: &str, length: usize) -> bool {
+    is_lower_hex(value, length) && value.bytes().any(|byte| byte != b'0')
+}
+
+fn is_lower_hex(value: &str, length: usize) -> bool {
+    value.len() == length && value.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
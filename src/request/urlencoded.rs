@@ -0,0 +1,86 @@
+//! Encoding and decoding of `application/x-www-form-urlencoded` strings, which are used by form
+//! bodies and query strings.
+
+use std::collections::HashMap;
+
+/// Parses `key=value` pairs separated by `&`
+pub(crate) fn parse(input: &[u8]) -> HashMap<String, String> {
+    parse_pairs(input).collect()
+}
+
+/// Parses `key=value` pairs separated by `&`, keeping their order and repeated keys
+pub(crate) fn parse_pairs(input: &[u8]) -> impl Iterator<Item = (String, String)> + '_ {
+    input
+        .split(|byte| *byte == b'&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, |byte| *byte == b'=');
+            let key = decode(parts.next().unwrap_or_default());
+            let value = decode(parts.next().unwrap_or_default());
+            (key, value)
+        })
+}
+
+/// Decodes a key or a value
+pub(crate) fn decode(component: &[u8]) -> String {
+    decode_with(component, true)
+}
+
+/// Decodes a path segment, where `+` is not a space
+pub(crate) fn decode_path(component: &[u8]) -> String {
+    decode_with(component, false)
+}
+
+fn decode_with(component: &[u8], plus_as_space: bool) -> String {
+    let mut decoded = Vec::with_capacity(component.len());
+    let mut bytes = component.iter();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' if plus_as_space => decoded.push(b' '),
+            b'%' => {
+                // `from_str_radix` accepts a sign, so both characters are checked first
+                let hex = bytes.as_slice()
+                    .get(..2)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|hex| std::str::from_utf8(hex).ok());
+
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(value) => {
+                        decoded.push(value);
+                        bytes.nth(1);
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            _ => decoded.push(*byte),
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Encodes a key or a value
+pub(crate) fn encode(component: &str) -> String {
+    encode_with(component, true)
+}
+
+/// Encodes a path segment, where a space is encoded as `%20`
+pub(crate) fn encode_path(component: &str) -> String {
+    encode_with(component, false)
+}
+
+fn encode_with(component: &str, space_as_plus: bool) -> String {
+    component
+        .bytes()
+        .map(|byte| {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (byte as char).to_string()
+                }
+                b' ' if space_as_plus => "+".to_string(),
+                _ => format!("%{:02X}", byte),
+            }
+        })
+        .collect()
+}
@@ -23,15 +23,24 @@
 //!
 //! But there are other functions such as `insert_header` and `update_body` can be useful especially when creating a middleware.
 //!
-use std::{ collections::HashMap, fmt::{ Debug, Formatter, Result } };
-use self::status_code::StatusCode;
+use std::{ collections::HashMap, fmt::{ Debug, Formatter, Result }, io::Write, sync::Arc };
+use self::{ body::Body, status_code::StatusCode, stream::BodyStream, upgrade::UpgradeHandler };
+use crate::server::{ connection::Socket, ServerConfig };
 
 pub use self::content_type::ContentType;
+pub use self::into_response::{ IntoResponse, Json };
+pub use self::problem::Problem;
+pub use self::upgrade::Upgraded;
 
 pub mod status_code;
 pub mod body;
 pub mod utilities;
 pub mod content_type;
+pub mod stream;
+pub mod into_response;
+pub mod problem;
+pub mod upgrade;
+mod header_name;
 
 /// Represents the HTTP response
 ///
@@ -51,9 +60,23 @@ pub struct Response {
     status_code: StatusCode,
     headers: HashMap<String, String>,
     locals: HashMap<String, String>,
-    body: Vec<u8>,
+    body: Body,
+    stream: Option<BodyStream>,
+    trailers: HashMap<String, String>,
+    pretty_json: bool,
+    compression_threshold: usize,
+    preserve_header_case: bool,
+    omit_body: bool,
+    deferred: Vec<DeferredTask>,
+    early_hints: Vec<String>,
+    connection: Option<Arc<Socket>>,
+    upgrade: Option<UpgradeHandler>,
+    reason: Option<String>,
 }
 
+/// A task that runs after the response is sent
+type DeferredTask = Box<dyn FnOnce() + Send>;
+
 impl Response {
     /// Sets the status of the response
     ///
@@ -68,6 +91,7 @@ impl Response {
     /// ```
     pub fn status(&mut self, status_code: StatusCode) -> &mut Self {
         self.status_code = status_code;
+        self.reason = None;
         self
     }
 
@@ -91,60 +115,489 @@ impl Response {
     /// }
     /// ```
     pub fn headers(&mut self, headers: HashMap<String, String>) -> &mut Self {
-        self.headers.extend(headers);
+        self.insert_headers(headers)
+    }
+
+    /// Sets the status to `204 No Content` and removes the body
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    /// let mut output = Vec::new();
+    ///
+    /// response.body_text("Deleted").no_content();
+    /// response.write_to(&mut output).unwrap();
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NoContent);
+    /// assert!(response.get_body().is_empty());
+    /// assert_eq!(output, b"HTTP/1.1 204 No Content\r\n\r\n");
+    /// ```
+    pub fn no_content(&mut self) -> &mut Self {
+        self.status_code = StatusCode::NoContent;
+        self.body.clear();
+        self.stream = None;
+        self.headers.retain(|key, _| {
+            !key.eq_ignore_ascii_case("Content-Length") && !key.eq_ignore_ascii_case("Content-Type")
+        });
+        self
+    }
+
+    /// Sets the status to `201 Created` and the `Location` header to the created resource
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode, json::json };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.created("/users/42").body_json(json!({ "id": 42 }));
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Created);
+    /// assert_eq!(response.get_header("Location").unwrap(), "/users/42");
+    /// ```
+    pub fn created(&mut self, location: &str) -> &mut Self {
+        self.status_code = StatusCode::Created;
+        self.insert_header("Location", location);
         self
     }
 
+    /// Resets the status, headers, body and trailers of the response
+    ///
+    /// The status is reset to the default `404 Not Found`. Locals are kept, so the values set by
+    /// the middlewares are still available.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).insert_header("X-Partial", "true").body_text("Half of a");
+    /// response.clear();
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NotFound);
+    /// assert!(response.get_header("X-Partial").is_none());
+    /// assert!(response.get_body().is_empty());
+    ///
+    /// response.error(StatusCode::InternalServerError, "Something went wrong");
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::InternalServerError);
+    /// assert_eq!(response.get_body(), br#"{"error":"Something went wrong"}"#);
+    /// ```
+    pub fn clear(&mut self) -> &mut Self {
+        self.status_code = Self::default().status_code;
+        self.headers.clear();
+        self.trailers.clear();
+        self.clear_body()
+    }
+
+    /// Removes the body of the response but keeps the status and the headers
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).insert_header("X-Request-Id", "42").body_text("Hello");
+    /// response.clear_body();
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Ok);
+    /// assert_eq!(response.get_header("X-Request-Id").unwrap(), "42");
+    /// assert!(response.get_body().is_empty());
+    /// ```
+    pub fn clear_body(&mut self) -> &mut Self {
+        self.body.clear();
+        self.stream = None;
+        self
+    }
+
+    /// Adds a task that runs after the response is sent to the client
+    ///
+    /// Tasks run in the order they are added, on the connection thread after the response is
+    /// written, so the client doesn't wait for them. If the connection is closed after the
+    /// response, it is shut down for writing before the tasks run. Tasks also run if writing the
+    /// response failed. They don't run for responses that are not sent by the server, e.g. the
+    /// ones returned by `Server::dispatch`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{
+    ///   io::{ Read, Write },
+    ///   net::{ TcpListener, TcpStream },
+    ///   sync::{ mpsc::{ self, Sender }, Mutex, OnceLock },
+    ///   thread,
+    ///   time::Duration,
+    /// };
+    ///
+    /// static AUDIT_LOG: OnceLock<Mutex<Sender<&str>>> = OnceLock::new();
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    /// let (sender, receiver) = mpsc::channel();
+    /// AUDIT_LOG.set(Mutex::new(sender)).unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|_, res| {
+    ///   let sender = AUDIT_LOG.get().unwrap().lock().unwrap().clone();
+    ///
+    ///   res.status(StatusCode::Ok).body_text("Saved");
+    ///   res.defer(move || {
+    ///     // A slow audit log write
+    ///     thread::sleep(Duration::from_millis(300));
+    ///     sender.send("audit log written").unwrap();
+    ///   });
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream.write_all(b"POST / HTTP/1.1\r\n\r\n").unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// // The response is received before the task finished
+    /// assert!(response.ends_with("Saved"));
+    /// assert!(receiver.try_recv().is_err());
+    /// assert_eq!(receiver.recv().unwrap(), "audit log written");
+    /// ```
+    pub fn defer(&mut self, task: impl FnOnce() + Send + 'static) -> &mut Self {
+        self.deferred.push(Box::new(task));
+        self
+    }
+
+    /// Sends a `103 Early Hints` response before the final response
+    ///
+    /// Early hints usually have `Link` headers, so clients can start preloading resources while
+    /// the final response is prepared. For requests from a connection, the hint is written as soon
+    /// as this is called. Otherwise, e.g. with `Server::dispatch`, it is written before the final
+    /// response. It can be called more than once, every call sends another `103` response in the
+    /// same order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res
+    ///     .early_hints(&[("Link", "</style.css>; rel=preload; as=style")])
+    ///     .early_hints(&[("Link", "</app.js>; rel=preload; as=script")])
+    ///     .status(StatusCode::Ok)
+    ///     .body_text("Hello");
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let bytes: Vec<u8> = server.dispatch(&mut request).into();
+    /// let output = String::from_utf8(bytes).unwrap();
+    ///
+    /// assert!(output.starts_with(
+    ///   "HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n\
+    ///   HTTP/1.1 103 Early Hints\r\nLink: </app.js>; rel=preload; as=script\r\n\r\n\
+    ///   HTTP/1.1 200 OK\r\n"
+    /// ));
+    /// assert!(output.ends_with("\r\n\r\nHello"));
+    /// ```
+    ///
+    /// The client gets the hint while the handler is still running
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{
+    ///   io::{ Read, Write },
+    ///   net::{ TcpListener, TcpStream },
+    ///   sync::Barrier,
+    ///   thread,
+    ///   time::Duration,
+    /// };
+    ///
+    /// // The handler only returns after the client received the hint
+    /// static BARRIER: Barrier = Barrier::new(2);
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.early_hints(&[("Link", "</style.css>; rel=preload; as=style")]);
+    ///   BARRIER.wait();
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let hint = b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n";
+    /// let mut received = vec![0; hint.len()];
+    ///
+    /// // A queued hint would time out here instead of arriving
+    /// stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    /// stream.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+    /// stream.read_exact(&mut received).unwrap();
+    /// assert_eq!(received, hint);
+    ///
+    /// BARRIER.wait();
+    ///
+    /// let mut response = String::new();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    /// assert!(response.ends_with("\r\n\r\nHello"));
+    /// ```
+    pub fn early_hints(&mut self, headers: &[(&str, &str)]) -> &mut Self {
+        let mut hint = format!("{} 103 Early Hints\r\n", self.http_version);
+
+        for (key, value) in headers {
+            hint.push_str(&format!("{}: {value}\r\n", self.header_key(key)));
+        }
+
+        hint.push_str("\r\n");
+
+        match &self.connection {
+            Some(connection) => {
+                if let Err(e) = connection.as_ref().write_all(hint.as_bytes()) {
+                    log::warn!("Error while writing the early hints: {}", e);
+                }
+            }
+            None => self.early_hints.push(hint),
+        }
+
+        self
+    }
+
+    /// Sets the connection of the request, so early hints are written to it right away
+    pub(crate) fn set_connection(&mut self, connection: Arc<Socket>) -> &mut Self {
+        self.connection = Some(connection);
+        self
+    }
+
+    /// Removes the deferred tasks of the response, so they can be run after it is sent
+    pub(crate) fn take_deferred(&mut self) -> Vec<DeferredTask> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    /// Creates a response that uses the defaults of the server configuration
+    pub(crate) fn with_config(config: &ServerConfig) -> Self {
+        Self {
+            pretty_json: config.pretty_json,
+            compression_threshold: config.compression_threshold,
+            preserve_header_case: config.preserve_header_case,
+            ..Self::default()
+        }
+    }
+
+    /// Writes the headers of the response without its body, e.g. for `HEAD` requests
+    pub(crate) fn omit_body(&mut self) -> &mut Self {
+        self.omit_body = true;
+        self
+    }
+
+    /// Returns the header name as it is written, canonicalized unless the case is preserved
+    fn header_key(&self, key: &str) -> String {
+        match self.preserve_header_case {
+            true => key.to_string(),
+            false => header_name::canonicalize(key),
+        }
+    }
+
+    /// Returns the status line of the response, e.g. `HTTP/1.1 200 OK`
+    fn status_line(&self) -> String {
+        let reason = self.reason.as_deref().unwrap_or(self.status_code.get_message());
+
+        format!("{} {} {}", self.http_version, self.status_code, reason)
+    }
+
     /// Allows to set the debug mode for the response.
     ///
-    /// If `debug_mode` is set to `true`, all debug messages will be printed to the console.
+    /// If `debug_mode` is set to `true`, all debug messages will be logged at the debug level.
     pub(crate) fn debug_msg(&mut self, msg: &str) -> &mut Self {
         if self.debug_mode {
-            println!("{}", msg);
+            log::debug!("{}", msg);
         }
         self
     }
 }
 
-impl From<Response> for Vec<u8> {
-    /// Returns the response as a byte vector.
+impl Response {
+    /// Writes the response to the given writer.
+    ///
+    /// If the body is a stream, it is copied to the writer in fixed-size chunks. When the length
+    /// of the stream is unknown, the body is sent with `Transfer-Encoding: chunked`.
+    ///
+    /// Responses with a status that can't have a body, like `204 No Content` and
+    /// `304 Not Modified`, are written without a body and a `Content-Length`.
+    ///
+    /// Header names are written in their canonical form unless `ServerConfig::preserve_header_case`
+    /// is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to the writer or reading from the body stream fails, or if the
+    /// body stream ends before its length.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use krustie::{ Response, StatusCode, Request };
+    /// use krustie::{ Response, StatusCode };
     ///
-    /// fn get(request: &Request, response: Response) {
-    ///   let response_bytes: Vec<u8> = response.into();
+    /// let mut response = Response::default();
+    /// let mut output = Vec::new();
+    ///
+    /// response.status(StatusCode::Ok).body_text("Hello, World!");
+    /// response.write_to(&mut output).unwrap();
+    ///
+    /// assert!(output.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    /// assert!(output.ends_with(b"\r\n\r\nHello, World!"));
+    /// ```
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    /// let mut output = Vec::new();
+    ///
+    /// response.status(StatusCode::NotModified).body_text("Unchanged");
+    /// response.write_to(&mut output).unwrap();
+    ///
+    /// let output = String::from_utf8(output).unwrap();
+    ///
+    /// assert!(output.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+    /// assert!(!output.contains("Content-Length"));
+    /// assert!(output.ends_with("\r\n\r\n"));
+    /// ```
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    /// let mut output = Vec::new();
+    ///
+    /// response
+    ///   .status(StatusCode::Ok)
+    ///   .insert_header("content-type", "text/plain")
+    ///   .insert_header("etag", "\"v1\"")
+    ///   .insert_header("www-authenticate", "Basic")
+    ///   .insert_header("x-request-id", "42")
+    ///   .insert_header("X-XSS-PROTECTION", "0")
+    ///   .insert_header("content-md5", "Q2hlY2s=");
+    /// response.write_to(&mut output).unwrap();
+    ///
+    /// let output = String::from_utf8(output).unwrap();
+    ///
+    /// for line in [
+    ///   "Content-Type: text/plain",
+    ///   "ETag: \"v1\"",
+    ///   "WWW-Authenticate: Basic",
+    ///   "X-Request-Id: 42",
+    ///   "X-XSS-Protection: 0",
+    ///   "Content-MD5: Q2hlY2s=",
+    /// ] {
+    ///   assert!(output.contains(&format!("\r\n{line}\r\n")), "{line}");
     /// }
     /// ```
-    fn from(response: Response) -> Vec<u8> {
+    pub fn write_to(&mut self, writer: &mut impl Write) -> std::io::Result<()> {
+        if self.status_code.is_bodyless() {
+            self.body.clear();
+            self.stream = None;
+            self.headers.retain(|key, _| !key.eq_ignore_ascii_case("Content-Length"));
+        }
+
+        for hint in &self.early_hints {
+            writer.write_all(hint.as_bytes())?;
+        }
+
         let mut headers_string = String::new();
 
-        if !response.headers.is_empty() {
-            response.headers.iter().for_each(|(key, value)| {
-                headers_string.push_str(&format!("{key}: {value}\r\n"));
+        if !self.headers.is_empty() {
+            self.headers.iter().for_each(|(key, value)| {
+                headers_string.push_str(&format!("{}: {value}\r\n", self.header_key(key)));
             });
         }
 
-        if !response.body.is_empty() {
-            headers_string.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+        let has_body = self.has_body();
 
-            if !headers_string.contains("Content-Type") {
-                eprintln!("Content-Type not found even though body is present");
-                headers_string.push_str("Content-Type: text/plain\r\n");
+        match &self.stream {
+            Some(stream) =>
+                match stream.length {
+                    Some(length) => {
+                        headers_string.push_str(&format!("Content-Length: {}\r\n", length));
+                    }
+                    None => {
+                        headers_string.push_str("Transfer-Encoding: chunked\r\n");
+
+                        if !self.trailers.is_empty() {
+                            let names = self.trailers.keys().cloned().collect::<Vec<_>>();
+                            headers_string.push_str(&format!("Trailer: {}\r\n", names.join(", ")));
+                        }
+                    }
+                }
+            None if has_body => {
+                headers_string.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
             }
+            None => {}
+        }
+
+        let has_content_type = self.headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case("Content-Type"));
+
+        if has_body && !has_content_type {
+            log::warn!("Content-Type not found even though body is present");
+            headers_string.push_str("Content-Type: text/plain\r\n");
+        }
+
+        let head = format!(
+            "{status_line}\r\n{headers_string}\r\n",
+            status_line = self.status_line()
+        );
+
+        writer.write_all(head.as_bytes())?;
+
+        match self.stream.take() {
+            _ if self.omit_body => {}
+            Some(stream) => stream.write_to(writer, &self.trailers)?,
+            None => writer.write_all(self.body.as_slice())?,
         }
 
-        let mut response_bytes: Vec<u8> = format!(
-            "{http_version} {status_code} {status_msg}\r\n{headers_string}\r\n",
-            http_version = response.http_version,
-            status_code = response.status_code,
-            status_msg = response.status_code.get_message()
-        ).into_bytes();
+        writer.flush()
+    }
+}
+
+impl From<Response> for Vec<u8> {
+    /// Returns the response as a byte vector.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode, Request };
+    ///
+    /// fn get(request: &Request, response: Response) {
+    ///   let response_bytes: Vec<u8> = response.into();
+    /// }
+    /// ```
+    fn from(mut response: Response) -> Vec<u8> {
+        let mut response_bytes = Vec::new();
 
-        if !response.body.is_empty() {
-            response_bytes.extend_from_slice(&response.body);
+        if let Err(err) = response.write_to(&mut response_bytes) {
+            log::error!("Error while reading the body stream: {}", err);
         }
 
         response_bytes
@@ -167,8 +620,19 @@ impl Default for Response {
             http_version: "HTTP/1.1".to_string(),
             status_code: StatusCode::NotFound,
             headers: HashMap::new(),
-            body: Vec::new(),
+            body: Body::default(),
+            stream: None,
+            trailers: HashMap::new(),
+            pretty_json: false,
+            compression_threshold: 0,
+            preserve_header_case: false,
+            omit_body: false,
             locals: HashMap::new(),
+            deferred: Vec::new(),
+            early_hints: Vec::new(),
+            connection: None,
+            upgrade: None,
+            reason: None,
         }
     }
 }
@@ -177,10 +641,8 @@ impl Debug for Response {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
             f,
-            "{http_version} {status_code} {status_msg}\r\n{headers}\r\n",
-            http_version = self.http_version,
-            status_code = self.status_code,
-            status_msg = self.status_code.get_message(),
+            "{status_line}\r\n{headers}\r\n",
+            status_line = self.status_line(),
             headers = self.headers
                 .iter()
                 .fold(String::new(), |acc, (key, value)| format!("{acc}{key}: {value}\r\n"))
@@ -1,9 +1,58 @@
 //! Body module for the Response struct. Contains functions for setting the body of the
 //! response.
 
-use super::{ content_type::ContentType, Response };
+use std::sync::Arc;
+
+use super::{ content_type::ContentType, status_code::StatusCode, Response };
 use serde_json::Value as JsonValue;
 
+/// The bytes of a response body
+///
+/// A shared body is only copied if it is modified, e.g. by `get_body_mut`.
+pub(super) enum Body {
+    Owned(Vec<u8>),
+    Shared(Arc<[u8]>),
+}
+
+impl Body {
+    pub(super) fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            Self::Shared(bytes) => bytes,
+        }
+    }
+
+    /// Returns the owned bytes, copying a shared body first
+    pub(super) fn to_mut(&mut self) -> &mut Vec<u8> {
+        if let Self::Shared(bytes) = self {
+            *self = Self::Owned(bytes.to_vec());
+        }
+
+        match self {
+            Self::Owned(bytes) => bytes,
+            Self::Shared(_) => unreachable!("shared body is copied above"),
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
+
+    pub(super) fn clear(&mut self) {
+        *self = Self::Owned(Vec::new());
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Self::Owned(Vec::new())
+    }
+}
+
 impl Response {
     /// Sets the body of the response. Function sets `Content-Length` automatically but needs `Content-Type` to be set manually.
     ///
@@ -27,8 +76,56 @@ impl Response {
     /// }
     /// ```
     pub fn body(&mut self, body: Vec<u8>, content_type: ContentType) -> &mut Self {
-        self.headers.insert(String::from("Content-Type"), content_type.to_string());
-        self.body = body;
+        self.insert_header("Content-Type", &content_type.to_string());
+        self.body = Body::Owned(body);
+        self.stream = None;
+        self
+    }
+
+    /// Sets the body of the response to bytes that are shared with other responses
+    ///
+    /// The bytes are written from the shared buffer without copying them, e.g. for cached file
+    /// contents that are served many times. They are only copied if the body is modified later,
+    /// like by `get_body_mut` or `GzipEncoder`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, StatusCode, response::ContentType };
+    /// use std::sync::{ Arc, OnceLock };
+    ///
+    /// static STYLESHEET: OnceLock<Arc<[u8]>> = OnceLock::new();
+    ///
+    /// let stylesheet = STYLESHEET.get_or_init(|| Arc::from(&b"body { color: red; }"[..]));
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_shared(STYLESHEET.get().unwrap().clone(), ContentType::Css);
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// let mut first = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut second = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let first = server.dispatch(&mut first);
+    /// let second = server.dispatch(&mut second);
+    ///
+    /// // Both responses hold the same allocation
+    /// assert_eq!(Arc::strong_count(stylesheet), 3);
+    /// assert_eq!(first.body_bytes(), b"body { color: red; }");
+    /// assert_eq!(Arc::strong_count(stylesheet), 3);
+    ///
+    /// for response in [first, second] {
+    ///   let output = String::from_utf8(response.into()).unwrap();
+    ///   assert!(output.ends_with("\r\n\r\nbody { color: red; }"));
+    /// }
+    ///
+    /// assert_eq!(Arc::strong_count(stylesheet), 1);
+    /// ```
+    pub fn body_shared(&mut self, body: Arc<[u8]>, content_type: ContentType) -> &mut Self {
+        self.insert_header("Content-Type", &content_type.to_string());
+        self.body = Body::Shared(body);
+        self.stream = None;
         self
     }
 
@@ -59,8 +156,155 @@ impl Response {
     /// }
     /// ```
     pub fn body_json(&mut self, data: JsonValue) -> &mut Self {
-        let json = serde_json::to_string(&data).unwrap();
+        let json = if self.pretty_json {
+            serde_json::to_string_pretty(&data).unwrap()
+        } else {
+            serde_json::to_string(&data).unwrap()
+        };
         self.body(json.as_bytes().to_vec(), ContentType::Json);
         self
     }
+
+    /// Sets the body of the response to JSON that is already serialized
+    ///
+    /// The bytes are used as they are, so a cached JSON body isn't parsed and serialized again
+    /// like with `body_json`. `Content-Type` is set to `application/json`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let cached = br#"{"name":"Krusty","shows":1000}"#.to_vec();
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).raw_json(cached.clone());
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/json");
+    /// assert_eq!(response.get_body(), &cached);
+    ///
+    /// response.raw_html(b"<h1>Hey hey!</h1>".to_vec());
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/html");
+    /// assert_eq!(response.get_body(), b"<h1>Hey hey!</h1>");
+    /// ```
+    pub fn raw_json(&mut self, bytes: Vec<u8>) -> &mut Self {
+        self.body(bytes, ContentType::Json)
+    }
+
+    /// Sets the body of the response to HTML that is already rendered
+    ///
+    /// `Content-Type` is set to `text/html`. See `raw_json`.
+    pub fn raw_html(&mut self, bytes: Vec<u8>) -> &mut Self {
+        self.body(bytes, ContentType::Html)
+    }
+
+    /// Appends bytes to the body of the response
+    ///
+    /// `Content-Length` is set from the final body when the response is written, so it matches
+    /// the appended bytes. A streamed body is replaced by the appended bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).append_str("<ul>");
+    ///
+    /// for item in ["a", "b"] {
+    ///   response.append_body(format!("<li>{item}</li>").as_bytes());
+    /// }
+    /// response.append_str("</ul>");
+    ///
+    /// assert_eq!(response.get_body(), b"<ul><li>a</li><li>b</li></ul>");
+    /// assert_eq!(response.body_len(), 29);
+    ///
+    /// let mut output = Vec::new();
+    /// response.write_to(&mut output).unwrap();
+    /// let output = String::from_utf8(output).unwrap();
+    ///
+    /// assert!(output.contains("Content-Length: 29\r\n"));
+    /// assert!(output.ends_with("\r\n\r\n<ul><li>a</li><li>b</li></ul>"));
+    /// ```
+    pub fn append_body(&mut self, bytes: &[u8]) -> &mut Self {
+        self.body.to_mut().extend_from_slice(bytes);
+        self.stream = None;
+        self
+    }
+
+    /// Appends text to the body of the response
+    ///
+    /// Sets `Content-Type` to `text/plain` if it is not set yet. See `append_body`.
+    pub fn append_str(&mut self, text: &str) -> &mut Self {
+        let has_content_type = self.headers.keys().any(|key| key.eq_ignore_ascii_case("Content-Type"));
+
+        if !has_content_type {
+            self.insert_header("Content-Type", &ContentType::Text.to_string());
+        }
+
+        self.append_body(text.as_bytes())
+    }
+
+    /// Sets the status and a JSON error body in the form of `{"error": message}`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.error(StatusCode::NotFound, "User not found");
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NotFound);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/json");
+    /// assert_eq!(response.get_body(), br#"{"error":"User not found"}"#);
+    /// ```
+    pub fn error(&mut self, status_code: StatusCode, message: &str) -> &mut Self {
+        self.status(status_code).body_json(serde_json::json!({ "error": message }))
+    }
+}
+
+impl From<(StatusCode, &str)> for Response {
+    /// Creates a response with the status and a plain text body
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::from((StatusCode::NotFound, "missing"));
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NotFound);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/plain");
+    /// assert_eq!(response.get_body(), b"missing");
+    /// ```
+    fn from((status_code, message): (StatusCode, &str)) -> Self {
+        let mut response = Response::default();
+        response.status(status_code).body_text(message);
+        response
+    }
+}
+
+impl From<(StatusCode, JsonValue)> for Response {
+    /// Creates a response with the status and a JSON body
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode, json::json };
+    ///
+    /// let mut response = Response::from((StatusCode::BadRequest, json!({ "error": "Invalid id" })));
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::BadRequest);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/json");
+    /// assert_eq!(response.get_body(), br#"{"error":"Invalid id"}"#);
+    /// ```
+    fn from((status_code, data): (StatusCode, JsonValue)) -> Self {
+        let mut response = Response::default();
+        response.status(status_code).body_json(data);
+        response
+    }
 }
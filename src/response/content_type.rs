@@ -75,6 +75,39 @@ pub enum ContentType {
     Other(String),
 }
 
+impl ContentType {
+    /// Guesses the content type of a body by looking at its content.
+    ///
+    /// Returns `Json` if the body is valid JSON, `Html` if it looks like an HTML document and
+    /// `application/octet-stream` otherwise.
+    pub(crate) fn sniff(body: &[u8]) -> ContentType {
+        let trimmed = match body.iter().position(|byte| !byte.is_ascii_whitespace()) {
+            Some(start) => &body[start..],
+            None => {
+                return ContentType::Other("application/octet-stream".to_string());
+            }
+        };
+
+        if
+            matches!(trimmed[0], b'{' | b'[') &&
+            serde_json::from_slice::<serde_json::Value>(trimmed).is_ok()
+        {
+            return ContentType::Json;
+        }
+
+        if trimmed[0] == b'<' {
+            let start = String::from_utf8_lossy(&trimmed[..trimmed.len().min(512)]).to_lowercase();
+            let html_tags = ["<!doctype html", "<html", "<head", "<body", "<div", "<p>", "<h1"];
+
+            if html_tags.iter().any(|tag| start.contains(tag)) {
+                return ContentType::Html;
+            }
+        }
+
+        ContentType::Other("application/octet-stream".to_string())
+    }
+}
+
 impl Display for ContentType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
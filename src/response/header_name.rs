@@ -0,0 +1,30 @@
+//! Canonicalization of the header names written to responses.
+
+/// Words that are not written in title case
+const SPECIAL_WORDS: [&str; 8] = ["DNT", "ETag", "MD5", "TE", "WebSocket", "WWW", "XSS", "CSP"];
+
+/// Returns the canonical form of a header name, e.g. `Content-Type` for `content-type`
+///
+/// Each dash separated word is title-cased, except the words with a known special form like
+/// `ETag` and `WWW-Authenticate`.
+pub(super) fn canonicalize(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let special = SPECIAL_WORDS.iter().find(|special| special.eq_ignore_ascii_case(word));
+
+            if let Some(special) = special {
+                return special.to_string();
+            }
+
+            let mut chars = word.chars();
+
+            match chars.next() {
+                Some(first) => {
+                    first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                }
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,102 @@
+//! This module contains the `IntoResponse` trait for the values that controllers can return.
+//!
+//! Controllers registered with `Router::respond` return a value instead of modifying the
+//! response, and the value is written into the response by the router.
+//!
+//! ```rust
+//! use krustie::{ Router, Request, Server, StatusCode, HttpMethod, json::json, response::Json };
+//!
+//! let mut server = Server::create();
+//! let mut router = Router::new();
+//!
+//! router.respond(HttpMethod::GET, "/hello", |_| "Hello, World!");
+//! router.respond(HttpMethod::GET, "/user", |_| Json(json!({ "name": "Krusty" })));
+//! router.respond(HttpMethod::POST, "/user", |_| (StatusCode::Created, "Created"));
+//! router.respond(HttpMethod::DELETE, "/user", |_| StatusCode::NoContent);
+//! server.use_handler(router);
+//!
+//! let mut request = Request::parse_from_bytes(b"GET /user HTTP/1.1\r\n\r\n").unwrap();
+//! let mut response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::Ok);
+//! assert_eq!(response.get_header("Content-Type").unwrap(), "application/json");
+//! assert_eq!(response.get_body(), br#"{"name":"Krusty"}"#);
+//!
+//! let mut request = Request::parse_from_bytes(b"POST /user HTTP/1.1\r\n\r\n").unwrap();
+//! let mut response = server.dispatch(&mut request);
+//!
+//! assert_eq!(response.get_status(), StatusCode::Created);
+//! assert_eq!(response.get_body(), b"Created");
+//! ```
+
+use serde::Serialize;
+
+use super::{ Response, StatusCode };
+
+/// A value that can be written into a response
+///
+/// Implemented for text (`&str`, `String`), `StatusCode`, `Json`, `Problem`, `()` and tuples of a
+/// `StatusCode` with one of them.
+pub trait IntoResponse {
+    /// Writes the value into the response
+    fn into_response(self, response: &mut Response);
+}
+
+/// A JSON response body of any serializable value
+///
+/// If the value can't be serialized, the response is `500 Internal Server Error`.
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+impl IntoResponse for () {
+    /// Leaves the response as it is
+    fn into_response(self, _response: &mut Response) {}
+}
+
+impl IntoResponse for StatusCode {
+    /// Sets the status without a body
+    fn into_response(self, response: &mut Response) {
+        response.status(self);
+    }
+}
+
+impl IntoResponse for &str {
+    /// Sets a `200 OK` text body
+    fn into_response(self, response: &mut Response) {
+        response.status(StatusCode::Ok).body_text(self);
+    }
+}
+
+impl IntoResponse for String {
+    /// Sets a `200 OK` text body
+    fn into_response(self, response: &mut Response) {
+        self.as_str().into_response(response);
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    /// Sets a `200 OK` JSON body
+    fn into_response(self, response: &mut Response) {
+        match serde_json::to_value(self.0) {
+            Ok(value) => {
+                response.status(StatusCode::Ok).body_json(value);
+            }
+            Err(err) => {
+                response.status(StatusCode::InternalServerError).debug_msg(&err.to_string());
+            }
+        }
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    /// Writes the value and overrides its status, unless writing the value failed
+    fn into_response(self, response: &mut Response) {
+        let (status, value) = self;
+
+        value.into_response(response);
+
+        if response.get_status() != StatusCode::InternalServerError {
+            response.status(status);
+        }
+    }
+}
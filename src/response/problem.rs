@@ -0,0 +1,186 @@
+//! This module contains the `Problem` struct for `application/problem+json` error bodies.
+//!
+//! The body follows [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807), so API clients can read
+//! errors of every endpoint the same way.
+
+use serde::{ ser::SerializeStruct, Serialize, Serializer };
+
+use super::{ content_type::ContentType, into_response::IntoResponse, status_code::StatusCode, Response };
+use crate::Request;
+
+/// The details of an error, written as an `application/problem+json` body
+///
+/// The type defaults to `about:blank` and the title to the reason phrase of the status.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Response, StatusCode, response::Problem };
+///
+/// let mut response = Response::default();
+///
+/// response.problem(
+///   Problem::new(StatusCode::Forbidden)
+///     .problem_type("https://example.com/probs/out-of-credit")
+///     .title("You do not have enough credit")
+///     .detail("Your current balance is 30, but that costs 50")
+///     .instance("/account/12345/msgs/abc")
+/// );
+///
+/// assert_eq!(response.get_status(), StatusCode::Forbidden);
+/// assert_eq!(response.get_header("Content-Type").unwrap(), "application/problem+json");
+/// assert_eq!(
+///   response.get_body(),
+///   br#"{"type":"https://example.com/probs/out-of-credit","title":"You do not have enough credit","status":403,"detail":"Your current balance is 30, but that costs 50","instance":"/account/12345/msgs/abc"}"#
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    problem_type: String,
+    title: String,
+    status: StatusCode,
+    detail: Option<String>,
+    instance: Option<String>,
+}
+
+impl Problem {
+    /// Creates a new problem for the status
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            problem_type: String::from("about:blank"),
+            title: status.get_message().to_string(),
+            status,
+            detail: None,
+            instance: None,
+        }
+    }
+
+    /// Sets the URI that identifies the type of the problem
+    pub fn problem_type(mut self, problem_type: &str) -> Self {
+        self.problem_type = problem_type.to_string();
+        self
+    }
+
+    /// Sets the short summary of the type of the problem
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Sets the explanation of this occurrence of the problem
+    pub fn detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    /// Sets the URI of this occurrence of the problem
+    pub fn instance(mut self, instance: &str) -> Self {
+        self.instance = Some(instance.to_string());
+        self
+    }
+
+    /// Returns the status of the problem
+    pub fn get_status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// A status handler that renders the error responses as problems
+    ///
+    /// The path of the request is used as the instance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, StatusCode, response::{ status_code::StatusRange, Problem } };
+    ///
+    /// let mut server = Server::create();
+    /// let router = Router::new();
+    ///
+    /// server.use_handler(router);
+    /// server.set_status_handler(StatusRange::try_from("4xx").unwrap(), Problem::status_handler);
+    /// server.set_status_handler(StatusRange::try_from("5xx").unwrap(), Problem::status_handler);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /missing HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NotFound);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/problem+json");
+    /// assert_eq!(
+    ///   response.get_body(),
+    ///   br#"{"type":"about:blank","title":"Not Found","status":404,"instance":"/missing"}"#
+    /// );
+    /// ```
+    pub fn status_handler(request: &Request, response: &mut Response) {
+        let problem = Problem::new(response.get_status()).instance(request.get_path());
+        response.problem(problem);
+    }
+}
+
+impl Serialize for Problem {
+    /// Serializes the members in the order of the RFC, leaving out the unset optional ones
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let length = 3 + usize::from(self.detail.is_some()) + usize::from(self.instance.is_some());
+        let mut problem = serializer.serialize_struct("Problem", length)?;
+
+        problem.serialize_field("type", &self.problem_type)?;
+        problem.serialize_field("title", &self.title)?;
+        problem.serialize_field("status", &(self.status as u16))?;
+
+        if let Some(detail) = &self.detail {
+            problem.serialize_field("detail", detail)?;
+        }
+
+        if let Some(instance) = &self.instance {
+            problem.serialize_field("instance", instance)?;
+        }
+
+        problem.end()
+    }
+}
+
+impl Response {
+    /// Sets the status of the problem and writes it as an `application/problem+json` body
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode, response::Problem };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|_, res| {
+    ///   res.problem(Problem::new(StatusCode::BadRequest).detail("The name is missing"));
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"POST / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::BadRequest);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/problem+json");
+    /// assert_eq!(
+    ///   response.get_body(),
+    ///   br#"{"type":"about:blank","title":"Bad Request","status":400,"detail":"The name is missing"}"#
+    /// );
+    /// ```
+    pub fn problem(&mut self, problem: Problem) -> &mut Self {
+        let json = if self.pretty_json {
+            serde_json::to_string_pretty(&problem).unwrap()
+        } else {
+            serde_json::to_string(&problem).unwrap()
+        };
+
+        self.status(problem.status).body(
+            json.into_bytes(),
+            ContentType::Other(String::from("application/problem+json"))
+        )
+    }
+}
+
+impl IntoResponse for Problem {
+    /// Writes the problem as an `application/problem+json` body
+    fn into_response(self, response: &mut Response) {
+        response.problem(self);
+    }
+}
@@ -3,9 +3,64 @@
 use std::fmt::{ self, Display, Formatter };
 
 /// Represents the status code of an HTTP response
-#[derive(Eq, Hash, PartialEq, Debug, Clone, Copy)]
+///
+/// # Example
+///
+/// Every status code is written with its reason phrase in the status line
+///
+/// ```rust
+/// use krustie::{ Response, StatusCode };
+///
+/// let status_lines = [
+///   (StatusCode::SwitchingProtocols, "101 Switching Protocols"),
+///   (StatusCode::Ok, "200 OK"),
+///   (StatusCode::Created, "201 Created"),
+///   (StatusCode::Accepted, "202 Accepted"),
+///   (StatusCode::NoContent, "204 No Content"),
+///   (StatusCode::PartialContent, "206 Partial Content"),
+///   (StatusCode::MovedPermanently, "301 Moved Permanently"),
+///   (StatusCode::NotModified, "304 Not Modified"),
+///   (StatusCode::BadRequest, "400 Bad Request"),
+///   (StatusCode::Unauthorized, "401 Unauthorized"),
+///   (StatusCode::Forbidden, "403 Forbidden"),
+///   (StatusCode::NotFound, "404 Not Found"),
+///   (StatusCode::MethodNotAllowed, "405 Method Not Allowed"),
+///   (StatusCode::RequestTimeout, "408 Request Timeout"),
+///   (StatusCode::LengthRequired, "411 Length Required"),
+///   (StatusCode::PreconditionFailed, "412 Precondition Failed"),
+///   (StatusCode::PayloadTooLarge, "413 Payload Too Large"),
+///   (StatusCode::UriTooLong, "414 URI Too Long"),
+///   (StatusCode::UnsupportedMediaType, "415 Unsupported Media Type"),
+///   (StatusCode::RangeNotSatisfiable, "416 Range Not Satisfiable"),
+///   (StatusCode::IAmATeapot, "418 I'm A Teapot"),
+///   (StatusCode::TooManyRequests, "429 Too Many Requests"),
+///   (StatusCode::RequestHeaderFieldsTooLarge, "431 Request Header Fields Too Large"),
+///   (StatusCode::InternalServerError, "500 Internal Server Error"),
+///   (StatusCode::NotImplemented, "501 Not Implemented"),
+///   (StatusCode::ServiceUnavailable, "503 Service Unavailable"),
+///   (StatusCode::GatewayTimeout, "504 Gateway Timeout"),
+///   (StatusCode::HttpVersionNotSupported, "505 HTTP Version Not Supported"),
+/// ];
+///
+/// // Fails if a status code is added without a line above
+/// let known_codes = (100..600).filter(|code| StatusCode::try_from(*code).is_ok()).count();
+/// assert_eq!(known_codes, status_lines.len());
+///
+/// for (status_code, line) in status_lines {
+///   let mut response = Response::default();
+///   let mut output = Vec::new();
+///
+///   response.status(status_code).write_to(&mut output).unwrap();
+///
+///   assert!(output.starts_with(format!("HTTP/1.1 {line}\r\n").as_bytes()), "{line}");
+/// }
+/// ```
+#[derive(Eq, Hash, PartialEq, Debug, Clone, Copy, Default)]
 pub enum StatusCode {
+    /// 101 Switching Protocols
+    SwitchingProtocols = 101,
     /// 200 OK
+    #[default]
     Ok = 200,
     /// 201 Created
     Created = 201,
@@ -13,6 +68,12 @@ pub enum StatusCode {
     Accepted = 202,
     /// 204 No Content
     NoContent = 204,
+    /// 206 Partial Content
+    PartialContent = 206,
+    /// 301 Moved Permanently
+    MovedPermanently = 301,
+    /// 304 Not Modified
+    NotModified = 304,
     /// 400 Bad Request
     BadRequest = 400,
     /// 401 Unauthorized
@@ -27,12 +88,22 @@ pub enum StatusCode {
     RequestTimeout = 408,
     /// 411 Length Required
     LengthRequired = 411,
+    /// 412 Precondition Failed
+    PreconditionFailed = 412,
+    /// 413 Payload Too Large
+    PayloadTooLarge = 413,
+    /// 414 URI Too Long
+    UriTooLong = 414,
     /// 415 Unsupported Media Type
     UnsupportedMediaType = 415,
+    /// 416 Range Not Satisfiable
+    RangeNotSatisfiable = 416,
     /// 418 I'm A Teapot
     IAmATeapot = 418,
-    /// 249 Too Many Requests
+    /// 429 Too Many Requests
     TooManyRequests = 429,
+    /// 431 Request Header Fields Too Large
+    RequestHeaderFieldsTooLarge = 431,
     /// 500 Internal Server Error
     InternalServerError = 500,
     /// 501 Not Implemented
@@ -46,12 +117,21 @@ pub enum StatusCode {
 }
 
 impl StatusCode {
+    /// Returns true if responses with the status code can't have a body
+    pub(crate) fn is_bodyless(&self) -> bool {
+        matches!(self, Self::SwitchingProtocols | Self::NoContent | Self::NotModified)
+    }
+
     pub(super) fn get_message(&self) -> &str {
         match self {
+            Self::SwitchingProtocols => "Switching Protocols",
             Self::Ok => "OK",
             Self::Created => "Created",
             Self::Accepted => "Accepted",
             Self::NoContent => "No Content",
+            Self::PartialContent => "Partial Content",
+            Self::MovedPermanently => "Moved Permanently",
+            Self::NotModified => "Not Modified",
             Self::BadRequest => "Bad Request",
             Self::Unauthorized => "Unauthorized",
             Self::Forbidden => "Forbidden",
@@ -59,9 +139,14 @@ impl StatusCode {
             Self::MethodNotAllowed => "Method Not Allowed",
             Self::RequestTimeout => "Request Timeout",
             Self::LengthRequired => "Length Required",
+            Self::PreconditionFailed => "Precondition Failed",
+            Self::PayloadTooLarge => "Payload Too Large",
+            Self::UriTooLong => "URI Too Long",
             Self::UnsupportedMediaType => "Unsupported Media Type",
+            Self::RangeNotSatisfiable => "Range Not Satisfiable",
             Self::IAmATeapot => "I'm A Teapot",
             Self::TooManyRequests => "Too Many Requests",
+            Self::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
             Self::InternalServerError => "Internal Server Error",
             Self::NotImplemented => "Not Implemented",
             Self::ServiceUnavailable => "Service Unavailable",
@@ -71,12 +156,6 @@ impl StatusCode {
     }
 }
 
-impl Default for StatusCode {
-    fn default() -> Self {
-        Self::Ok
-    }
-}
-
 impl TryFrom<&u16> for StatusCode {
     type Error = ParseStatusCodeError;
     /// Converts a `u16` to a `StatusCode`
@@ -101,10 +180,14 @@ impl TryFrom<&u16> for StatusCode {
     /// ```
     fn try_from(status_code: &u16) -> Result<Self, Self::Error> {
         match status_code {
+            101 => Ok(Self::SwitchingProtocols),
             200 => Ok(Self::Ok),
             201 => Ok(Self::Created),
             202 => Ok(Self::Accepted),
             204 => Ok(Self::NoContent),
+            206 => Ok(Self::PartialContent),
+            301 => Ok(Self::MovedPermanently),
+            304 => Ok(Self::NotModified),
             400 => Ok(Self::BadRequest),
             401 => Ok(Self::Unauthorized),
             403 => Ok(Self::Forbidden),
@@ -112,9 +195,14 @@ impl TryFrom<&u16> for StatusCode {
             405 => Ok(Self::MethodNotAllowed),
             408 => Ok(Self::RequestTimeout),
             411 => Ok(Self::LengthRequired),
+            412 => Ok(Self::PreconditionFailed),
+            413 => Ok(Self::PayloadTooLarge),
+            414 => Ok(Self::UriTooLong),
             415 => Ok(Self::UnsupportedMediaType),
+            416 => Ok(Self::RangeNotSatisfiable),
             418 => Ok(Self::IAmATeapot),
             429 => Ok(Self::TooManyRequests),
+            431 => Ok(Self::RequestHeaderFieldsTooLarge),
             500 => Ok(Self::InternalServerError),
             501 => Ok(Self::NotImplemented),
             503 => Ok(Self::ServiceUnavailable),
@@ -230,3 +318,72 @@ impl Display for ParseStatusCodeError {
         write!(f, "Invalid status code for HTTP response")
     }
 }
+
+/// Represents a single status code or a class of status codes
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ StatusCode, response::status_code::StatusRange };
+///
+/// assert_eq!(StatusRange::try_from("4xx").unwrap(), StatusRange::ClientError);
+/// assert_eq!(StatusRange::try_from("404").unwrap(), StatusRange::Code(StatusCode::NotFound));
+/// assert!(StatusRange::ClientError.contains(StatusCode::Forbidden));
+/// assert!(!StatusRange::ClientError.contains(StatusCode::InternalServerError));
+/// ```
+#[derive(Eq, Hash, PartialEq, Debug, Clone, Copy)]
+pub enum StatusRange {
+    /// A single status code
+    Code(StatusCode),
+    /// `1xx` status codes
+    Informational,
+    /// `2xx` status codes
+    Success,
+    /// `3xx` status codes
+    Redirection,
+    /// `4xx` status codes
+    ClientError,
+    /// `5xx` status codes
+    ServerError,
+}
+
+impl StatusRange {
+    /// Returns true if the status code is in the range
+    pub fn contains(&self, status_code: StatusCode) -> bool {
+        let class = u16::from(&status_code) / 100;
+
+        match self {
+            Self::Code(code) => *code == status_code,
+            Self::Informational => class == 1,
+            Self::Success => class == 2,
+            Self::Redirection => class == 3,
+            Self::ClientError => class == 4,
+            Self::ServerError => class == 5,
+        }
+    }
+}
+
+impl From<StatusCode> for StatusRange {
+    fn from(status_code: StatusCode) -> Self {
+        Self::Code(status_code)
+    }
+}
+
+impl TryFrom<&str> for StatusRange {
+    type Error = ParseStatusCodeError;
+    /// Converts a status code (`404`) or a status class (`4xx`) to a `StatusRange`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is neither a valid status code nor a status class
+    fn try_from(range: &str) -> Result<Self, Self::Error> {
+        match range.to_lowercase().as_str() {
+            "1xx" => Ok(Self::Informational),
+            "2xx" => Ok(Self::Success),
+            "3xx" => Ok(Self::Redirection),
+            "4xx" => Ok(Self::ClientError),
+            "5xx" => Ok(Self::ServerError),
+            code => StatusCode::try_from(code).map(Self::Code),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Stream module for the Response struct. Contains functions for sending a body from a `Read`
+//! source without buffering it in memory.
+
+use std::{
+    collections::HashMap,
+    fmt::{ Debug, Formatter, Result as fmtResult },
+    io::{ Error, ErrorKind, Read, Result, Write },
+};
+
+use serde::Serialize;
+
+use super::Response;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+pub(crate) struct BodyStream {
+    reader: Box<dyn Read + Send>,
+    pub(super) length: Option<usize>,
+}
+
+impl BodyStream {
+    /// Copies the stream to the writer. If the length is unknown, chunked encoding is used.
+    ///
+    /// A stream that ends before its length returns an `UnexpectedEof` error, since the client
+    /// would wait for the missing bytes.
+    pub(super) fn write_to(
+        mut self,
+        writer: &mut impl Write,
+        trailers: &HashMap<String, String>
+    ) -> Result<()> {
+        let mut buffer = [0; CHUNK_SIZE];
+
+        match self.length {
+            Some(length) => {
+                let mut reader = self.reader.take(length as u64);
+                let mut written = 0;
+
+                loop {
+                    let read = reader.read(&mut buffer)?;
+                    if read == 0 {
+                        break;
+                    }
+                    writer.write_all(&buffer[..read])?;
+                    written += read;
+                }
+
+                if written < length {
+                    return Err(
+                        Error::new(
+                            ErrorKind::UnexpectedEof,
+                            format!("Body stream ended after {written} of {length} bytes")
+                        )
+                    );
+                }
+            }
+            None => {
+                loop {
+                    let read = self.reader.read(&mut buffer)?;
+                    if read == 0 {
+                        break;
+                    }
+                    writer.write_all(format!("{:X}\r\n", read).as_bytes())?;
+                    writer.write_all(&buffer[..read])?;
+                    writer.write_all(b"\r\n")?;
+                    writer.flush()?;
+                }
+                writer.write_all(b"0\r\n")?;
+
+                for (key, value) in trailers {
+                    writer.write_all(format!("{key}: {value}\r\n").as_bytes())?;
+                }
+
+                writer.write_all(b"\r\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes records into NDJSON lines while it is read
+struct NdjsonReader<I> {
+    records: I,
+    line: Vec<u8>,
+    position: usize,
+}
+
+impl<I> Read for NdjsonReader<I> where I: Iterator, I::Item: Serialize {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.position == self.line.len() {
+            match self.records.next() {
+                Some(record) => {
+                    self.line = serde_json::to_vec(&record).map_err(Error::other)?;
+                    self.line.push(b'\n');
+                    self.position = 0;
+                }
+                None => {
+                    return Ok(0);
+                }
+            }
+        }
+
+        let read = buf.len().min(self.line.len() - self.position);
+        buf[..read].copy_from_slice(&self.line[self.position..self.position + read]);
+        self.position += read;
+
+        Ok(read)
+    }
+}
+
+impl Debug for BodyStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmtResult {
+        write!(f, "BodyStream {{ length: {:?} }}", self.length)
+    }
+}
+
+impl Response {
+    /// Sets the body of the response to a stream. The stream is read while the response is
+    /// written, so the body is never fully loaded into memory.
+    ///
+    /// If `content_length` is `Some`, `Content-Length` is set and exactly that many bytes are
+    /// copied from the reader. If the reader ends earlier, writing the response fails with
+    /// `UnexpectedEof` and the server closes the connection. Otherwise the body is sent with
+    /// `Transfer-Encoding: chunked`.
+    ///
+    /// `Content-Type` needs to be set manually. If it is not set, it defaults to `text/plain`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    /// use std::io::{ repeat, Read };
+    ///
+    /// let size = 1024 * 1024;
+    /// let mut response = Response::default();
+    ///
+    /// response
+    ///   .status(StatusCode::Ok)
+    ///   .insert_header("Content-Type", "application/octet-stream")
+    ///   .body_stream(Box::new(repeat(7).take(size as u64)), Some(size));
+    ///
+    /// let bytes: Vec<u8> = response.into();
+    /// let body_start = bytes.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+    ///
+    /// assert_eq!(bytes[body_start..].len(), size);
+    /// assert!(bytes[body_start..].iter().all(|byte| *byte == 7));
+    /// ```
+    ///
+    /// A stream shorter than its length fails
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    /// use std::io::ErrorKind;
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).body_stream(Box::new(&b"Hello"[..]), Some(10));
+    ///
+    /// let err = response.write_to(&mut Vec::new()).unwrap_err();
+    ///
+    /// assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    /// ```
+    ///
+    /// Unknown length is sent using chunked encoding
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).body_stream(Box::new(&b"Hello"[..]), None);
+    ///
+    /// let bytes: Vec<u8> = response.into();
+    /// let output = String::from_utf8(bytes).unwrap();
+    ///
+    /// assert!(output.contains("Transfer-Encoding: chunked\r\n"));
+    /// assert!(output.ends_with("\r\n\r\n5\r\nHello\r\n0\r\n\r\n"));
+    /// ```
+    pub fn body_stream(
+        &mut self,
+        reader: Box<dyn Read + Send>,
+        content_length: Option<usize>
+    ) -> &mut Self {
+        self.body.clear();
+        self.stream = Some(BodyStream { reader, length: content_length });
+        self
+    }
+
+    /// Adds a trailer header to the response
+    ///
+    /// Trailers are sent after the last chunk of a body stream with unknown length and their
+    /// names are advertised in the `Trailer` header. They are ignored for other bodies.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response
+    ///   .status(StatusCode::Ok)
+    ///   .insert_header("Content-Type", "text/plain")
+    ///   .insert_trailer("X-Checksum", "abc123")
+    ///   .body_stream(Box::new(&b"Hello"[..]), None);
+    ///
+    /// let output = String::from_utf8(response.into()).unwrap();
+    ///
+    /// assert!(output.contains("Trailer: X-Checksum\r\n"));
+    /// assert!(output.ends_with("5\r\nHello\r\n0\r\nX-Checksum: abc123\r\n\r\n"));
+    /// ```
+    pub fn insert_trailer(&mut self, key: &str, value: &str) -> &mut Self {
+        self.trailers.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Returns true if the body of the response is a stream
+    pub(crate) fn has_stream(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Returns the declared length of the body stream, if it has one
+    pub(crate) fn stream_length(&self) -> Option<usize> {
+        self.stream.as_ref().and_then(|stream| stream.length)
+    }
+
+    /// Replaces the reader of the body stream with a wrapper of it, e.g. an encoder. The length of
+    /// the wrapped stream is unknown, so it is sent with chunked encoding.
+    pub(crate) fn map_stream(
+        &mut self,
+        wrap: impl FnOnce(Box<dyn Read + Send>) -> Box<dyn Read + Send>
+    ) {
+        if let Some(stream) = self.stream.take() {
+            self.stream = Some(BodyStream { reader: wrap(stream.reader), length: None });
+        }
+    }
+
+    /// Sets the body of the response to a stream of NDJSON (`application/x-ndjson`) records
+    ///
+    /// Each record is serialized to a single line while the response is written and sent as a
+    /// separate chunk, so clients receive the records as they are produced.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode, json::json };
+    ///
+    /// let mut response = Response::default();
+    /// let records = (1..=3).map(|id| json!({ "id": id }));
+    ///
+    /// response.status(StatusCode::Ok).ndjson_stream(records);
+    ///
+    /// let output = String::from_utf8(response.into()).unwrap();
+    ///
+    /// assert!(output.contains("Content-Type: application/x-ndjson\r\n"));
+    /// assert!(output.contains("\r\n\r\n9\r\n{\"id\":1}\n\r\n9\r\n{\"id\":2}\n\r\n"));
+    /// ```
+    pub fn ndjson_stream<I>(&mut self, records: I) -> &mut Self
+        where I: IntoIterator, I::IntoIter: Send + 'static, I::Item: Serialize
+    {
+        let reader = NdjsonReader { records: records.into_iter(), line: Vec::new(), position: 0 };
+
+        self.insert_header("Content-Type", "application/x-ndjson");
+        self.body_stream(Box::new(reader), None)
+    }
+}
@@ -0,0 +1,174 @@
+//! Upgrade module for the Response struct. Contains the function for taking over the connection
+//! after a `101 Switching Protocols` response, e.g. for WebSockets.
+
+use std::{
+    io::{ Cursor, Read, Result as IoResult, Write },
+    net::Shutdown,
+    time::Duration,
+};
+
+use super::{ status_code::StatusCode, Response };
+use crate::server::connection::Socket;
+
+/// The connection of an upgraded request
+///
+/// Bytes the client sent after the request are read first, even if the server already buffered
+/// them. The connection is closed when it and all of its clones are dropped.
+#[derive(Debug)]
+pub struct Upgraded {
+    buffered: Cursor<Vec<u8>>,
+    socket: Socket,
+}
+
+impl Upgraded {
+    pub(crate) fn new(buffered: Vec<u8>, socket: Socket) -> Self {
+        Self { buffered: Cursor::new(buffered), socket }
+    }
+
+    /// Returns another handle to the connection, e.g. to read and write on different threads
+    ///
+    /// The clone reads from the socket directly, bytes the server already buffered are only read
+    /// by the original handle.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket can't be duplicated.
+    pub fn try_clone(&self) -> IoResult<Self> {
+        Ok(Self { buffered: Cursor::default(), socket: self.socket.try_clone()? })
+    }
+
+    /// Sets the time a read can block before it fails, `None` blocks without a limit
+    ///
+    /// The timeout of the server is kept until it is changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the timeout is zero.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        self.socket.set_read_timeout(timeout)
+    }
+
+    /// Sets the time a write can block before it fails, `None` blocks without a limit
+    ///
+    /// The timeout of the server is kept until it is changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the timeout is zero.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        self.socket.set_write_timeout(timeout)
+    }
+
+    /// Shuts down the reading half, the writing half or both halves of the connection
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket is not connected.
+    pub fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        self.socket.shutdown(how)
+    }
+}
+
+impl Read for Upgraded {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if (self.buffered.position() as usize) < self.buffered.get_ref().len() {
+            return self.buffered.read(buf);
+        }
+
+        self.socket.read(buf)
+    }
+}
+
+impl Write for Upgraded {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.socket.write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.socket.flush()
+    }
+}
+
+/// A function that takes over the connection after the response is written
+pub(crate) type UpgradeHandler = Box<dyn FnOnce(Upgraded)>;
+
+impl Response {
+    /// Switches the connection to another protocol after the response is written
+    ///
+    /// The status is set to `101 Switching Protocols` and `Connection: Upgrade` is added. The
+    /// `Upgrade` header and any handshake headers need to be set by the caller. After the
+    /// response is written, the callback receives the connection, which isn't used for other
+    /// requests. It can be cloned to read and write at the same time, and its timeouts can be
+    /// changed.
+    ///
+    /// The callback only runs when the server writes the response to a connection, not for
+    /// `Server::dispatch`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{
+    ///   io::{ BufRead, BufReader, Read, Write },
+    ///   net::{ TcpListener, TcpStream },
+    ///   thread,
+    ///   time::Duration,
+    /// };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.insert_header("Upgrade", "shout").upgrade(|mut stream| {
+    ///     stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    ///
+    ///     // Greets the client from another thread while the message is read
+    ///     let mut writer = stream.try_clone().unwrap();
+    ///     let greeting = thread::spawn(move || writer.write_all(b"hi "));
+    ///     let mut message = [0; 5];
+    ///
+    ///     stream.read_exact(&mut message).unwrap();
+    ///     greeting.join().unwrap().unwrap();
+    ///     stream.write_all(&message.to_ascii_uppercase()).unwrap();
+    ///   });
+    /// });
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    ///
+    /// // The first message is sent right after the request
+    /// stream.write_all(b"GET / HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: shout\r\n\r\nhello").unwrap();
+    ///
+    /// let mut reader = BufReader::new(stream);
+    /// let mut head = String::new();
+    ///
+    /// while !head.ends_with("\r\n\r\n") {
+    ///   reader.read_line(&mut head).unwrap();
+    /// }
+    ///
+    /// assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    /// assert!(head.contains("Connection: Upgrade\r\n"));
+    ///
+    /// let mut reply = String::new();
+    /// reader.read_to_string(&mut reply).unwrap();
+    ///
+    /// assert_eq!(reply, "hi HELLO");
+    /// ```
+    pub fn upgrade(
+        &mut self,
+        callback: impl FnOnce(Upgraded) + 'static
+    ) -> &mut Self {
+        self.status(StatusCode::SwitchingProtocols).insert_header("Connection", "Upgrade");
+        self.upgrade = Some(Box::new(callback));
+        self
+    }
+
+    /// Removes the upgrade callback of the response, so it can run after the response is written
+    pub(crate) fn take_upgrade(&mut self) -> Option<UpgradeHandler> {
+        self.upgrade.take()
+    }
+}
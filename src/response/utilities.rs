@@ -4,9 +4,26 @@
 
 use std::collections::HashMap;
 
-use crate::{ Response, StatusCode };
+use super::body::Body;
+use crate::{ response::ContentType, Response, StatusCode };
 
 impl Response {
+    /// Gets the status code of the response
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    /// response.status(StatusCode::Accepted);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Accepted);
+    /// ```
+    pub fn get_status(&self) -> StatusCode {
+        self.status_code
+    }
+
     /// Gets the headers of the response
     ///
     /// # Example
@@ -28,6 +45,8 @@ impl Response {
 
     /// Gets requested header from the response
     ///
+    /// Header names are compared case-insensitively.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -43,11 +62,18 @@ impl Response {
     /// }
     /// ```
     pub fn get_header(&self, key: &str) -> Option<&String> {
-        self.headers.get(key)
+        self.headers.get(key).or_else(|| {
+            self.headers
+                .iter()
+                .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value)
+        })
     }
 
     /// Adds a single header to the response
     ///
+    /// A header with the same name is replaced, even if its name has another case.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -57,13 +83,233 @@ impl Response {
     ///   response.insert_header("Server", "Krustie");
     /// }
     /// ```
+    ///
+    /// Replacing a header set with another case
+    ///
+    /// ```rust
+    /// use krustie::Response;
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.insert_header("server", "Krusty").insert_header("Server", "Krustie");
+    ///
+    /// assert_eq!(response.get_header("SERVER").unwrap(), "Krustie");
+    /// assert_eq!(response.get_headers().len(), 1);
+    /// ```
     pub fn insert_header(&mut self, key: &str, value: &str) -> &mut Self {
+        // Header names are case-insensitive, so a header set with another case is replaced
+        self.headers.retain(|existing, _| existing == key || !existing.eq_ignore_ascii_case(key));
         self.headers.insert(key.to_string(), value.to_string());
         self
     }
 
+    /// Adds multiple headers to the response
+    ///
+    /// Accepts any iterator of key-value pairs that can be referenced as strings. Each pair is
+    /// inserted the same way as `insert_header`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Response;
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.insert_headers(vec![
+    ///   ("Server", "Krustie"),
+    ///   ("Cache-Control", "no-cache"),
+    ///   ("X-Request-Id", "42"),
+    /// ]);
+    ///
+    /// assert_eq!(response.get_header("Server").unwrap(), "Krustie");
+    /// assert_eq!(response.get_header("Cache-Control").unwrap(), "no-cache");
+    /// assert_eq!(response.get_header("X-Request-Id").unwrap(), "42");
+    /// ```
+    pub fn insert_headers<I, K, V>(&mut self, headers: I) -> &mut Self
+        where I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: AsRef<str>
+    {
+        for (key, value) in headers {
+            self.insert_header(key.as_ref(), value.as_ref());
+        }
+        self
+    }
+
+    /// Adds a header to the response only if it isn't set yet
+    ///
+    /// Header names are compared case-insensitively, so a `content-type` set by a controller is
+    /// kept when a middleware sets a default `Content-Type`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{
+    ///   Server,
+    ///   Router,
+    ///   Request,
+    ///   Response,
+    ///   StatusCode,
+    ///   Middleware,
+    ///   response::ContentType,
+    ///   server::route_handler::HandlerResult,
+    /// };
+    ///
+    /// struct Defaults;
+    ///
+    /// impl Middleware for Defaults {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///     res
+    ///       .set_header_if_absent("Content-Type", "application/octet-stream")
+    ///       .set_header_if_absent("Server", "Krustie");
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body(b"<h1>Hello</h1>".to_vec(), ContentType::Html);
+    /// });
+    /// router.post(|_, res| {
+    ///   res.status(StatusCode::Ok).insert_header("server", "Custom");
+    /// });
+    /// router.use_response_middleware(Defaults);
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/html");
+    /// assert_eq!(response.get_header("Server").unwrap(), "Krustie");
+    ///
+    /// let mut request = Request::parse_from_bytes(b"POST / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("server").unwrap(), "Custom");
+    /// assert_eq!(response.get_header("Server").unwrap(), "Custom");
+    /// assert_eq!(response.get_headers().len(), 2);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/octet-stream");
+    /// ```
+    pub fn set_header_if_absent(&mut self, key: &str, value: &str) -> &mut Self {
+        if !self.headers.keys().any(|existing| existing.eq_ignore_ascii_case(key)) {
+            self.insert_header(key, value);
+        }
+        self
+    }
+
+    /// Adds a request header to the `Vary` header of the response
+    ///
+    /// Middlewares that choose the response by a request header, like `Accept-Encoding` for
+    /// compression, should add it, so caches store the variants separately. Headers are merged
+    /// into a single `Vary` header and a header that is already listed is not added again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, Response, StatusCode, json::json, middleware::GzipEncoder };
+    ///
+    /// fn get(req: &Request, res: &mut Response) {
+    ///   let wants_json = req.get_header("accept").is_some_and(|accept| accept.contains("json"));
+    ///   let message = "Hello, World! ".repeat(10);
+    ///
+    ///   if wants_json {
+    ///     res.status(StatusCode::Ok).body_json(json!({ "message": message }));
+    ///   } else {
+    ///     res.status(StatusCode::Ok).body_text(&message);
+    ///   }
+    ///
+    ///   res.add_vary("Accept").add_vary("accept");
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(get);
+    /// router.use_response_middleware(GzipEncoder);
+    /// server.use_handler(router);
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nAccept: application/json\r\nAccept-Encoding: gzip\r\n\r\n";
+    /// let mut request = Request::parse_from_bytes(raw).unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Vary").unwrap(), "Accept, Accept-Encoding");
+    ///
+    /// // Caches need the header even if the response wasn't compressed
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert!(response.get_header("Content-Encoding").is_none());
+    /// assert_eq!(response.get_header("Vary").unwrap(), "Accept, Accept-Encoding");
+    /// ```
+    pub fn add_vary(&mut self, header: &str) -> &mut Self {
+        let vary = self.headers
+            .iter_mut()
+            .find(|(key, _)| key.eq_ignore_ascii_case("vary"))
+            .map(|(_, value)| value);
+
+        match vary {
+            Some(value) => {
+                let is_listed = value
+                    .split(',')
+                    .map(str::trim)
+                    .any(|listed| listed == "*" || listed.eq_ignore_ascii_case(header));
+
+                if !is_listed {
+                    value.push_str(&format!(", {header}"));
+                }
+            }
+            None => {
+                self.headers.insert(String::from("Vary"), header.to_string());
+            }
+        }
+
+        self
+    }
+
+    /// Returns true if the response has a body or a body stream
+    pub(crate) fn has_body(&self) -> bool {
+        !self.body.is_empty() || self.stream.is_some()
+    }
+
+    /// Sets the `Content-Type` header by sniffing the body if the body is present and no
+    /// `Content-Type` was set.
+    pub(crate) fn sniff_content_type(&mut self) {
+        let has_content_type = self.headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case("content-type"));
+
+        if self.body.is_empty() || has_content_type {
+            return;
+        }
+
+        let content_type = ContentType::sniff(self.body.as_slice());
+        self.insert_header("Content-Type", &content_type.to_string());
+    }
+
+    /// Appends the charset to the `Content-Type` header if it is a `text/*` type without one
+    pub(crate) fn add_default_charset(&mut self, charset: &str) {
+        let content_type = self.headers
+            .iter_mut()
+            .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value);
+
+        if let Some(value) = content_type {
+            if value.starts_with("text/") && !value.to_lowercase().contains("charset=") {
+                value.push_str(&format!("; charset={charset}"));
+            }
+        }
+    }
+
+    /// Returns the minimum body size for compression set in the server configuration
+    pub(crate) fn get_compression_threshold(&self) -> usize {
+        self.compression_threshold
+    }
+
     /// Gets the body of the response as a byte vector reference
     ///
+    /// A shared body is copied into an owned vector first, use `body_bytes` to read it without
+    /// copying.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -74,7 +320,36 @@ impl Response {
     /// }
     /// ```
     pub fn get_body(&mut self) -> &Vec<u8> {
-        &mut self.body
+        self.body.to_mut()
+    }
+
+    /// Returns the bytes of the body without copying a shared body
+    ///
+    /// Streamed bodies are not included.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode, response::ContentType };
+    /// use std::sync::Arc;
+    ///
+    /// let shared: Arc<[u8]> = Arc::from(&b"Hello"[..]);
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok).body_shared(shared.clone(), ContentType::Text);
+    ///
+    /// assert_eq!(response.body_bytes(), b"Hello");
+    /// assert_eq!(Arc::strong_count(&shared), 2);
+    /// ```
+    pub fn body_bytes(&self) -> &[u8] {
+        self.body.as_slice()
+    }
+
+    /// Returns the number of bytes of the body
+    ///
+    /// Streamed bodies are not counted.
+    pub fn body_len(&self) -> usize {
+        self.body.len()
     }
 
     /// Gets the body of the response as a **mutable** byte vector reference
@@ -89,7 +364,7 @@ impl Response {
     /// }
     /// ```
     pub fn get_body_mut(&mut self) -> &mut Vec<u8> {
-        &mut self.body
+        self.body.to_mut()
     }
 
     /// Updates the body of the response.
@@ -115,10 +390,46 @@ impl Response {
         if self.body.is_empty() {
             return Err("Request has no body.".to_string());
         }
-        self.body = body;
+        self.body = Body::Owned(body);
         return Ok(());
     }
 
+    /// Replaces the reason phrase of the status line, e.g. `200 All Good`
+    ///
+    /// The status code stays the same. Setting the status afterwards restores the standard
+    /// reason phrase, so it should be called after `status`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reason contains control characters other than tabs, since they
+    /// would break the status line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Response, StatusCode };
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// response.status(StatusCode::Ok);
+    /// response.set_reason("All Good").unwrap();
+    ///
+    /// let output = String::from_utf8(response.into()).unwrap();
+    /// assert!(output.starts_with("HTTP/1.1 200 All Good\r\n"));
+    ///
+    /// let mut response = Response::default();
+    ///
+    /// assert!(response.set_reason("Bad\r\nX-Injected: true").is_err());
+    /// ```
+    pub fn set_reason(&mut self, reason: &str) -> Result<(), String> {
+        if reason.chars().any(|char| char.is_control() && char != '\t') {
+            return Err(format!("Reason phrase contains control characters: {:?}", reason));
+        }
+
+        self.reason = Some(reason.to_string());
+        Ok(())
+    }
+
     /// Returns the value of the local variable
     ///
     /// `Local` variables can be used to store data that can be defined in a *middleware* and accessed in the *controller*
@@ -155,23 +466,25 @@ impl Response {
 
     /// Returns true if status code is 4xx or 5xx.
     pub fn is_err(&self) -> bool {
-        match self.status_code {
-            StatusCode::BadRequest => true,
-            StatusCode::Unauthorized => true,
-            StatusCode::Forbidden => true,
-            StatusCode::NotFound => true,
-            StatusCode::MethodNotAllowed => true,
-            StatusCode::RequestTimeout => true,
-            StatusCode::LengthRequired => true,
-            StatusCode::UnsupportedMediaType => true,
-            StatusCode::IAmATeapot => true,
-            StatusCode::TooManyRequests => true,
-            StatusCode::InternalServerError => true,
-            StatusCode::NotImplemented => true,
-            StatusCode::ServiceUnavailable => true,
-            StatusCode::GatewayTimeout => true,
-            StatusCode::HttpVersionNotSupported => true,
-            _ => false,
-        }
+        matches!(
+            self.status_code,
+            StatusCode::BadRequest |
+                StatusCode::Unauthorized |
+                StatusCode::Forbidden |
+                StatusCode::NotFound |
+                StatusCode::MethodNotAllowed |
+                StatusCode::RequestTimeout |
+                StatusCode::LengthRequired |
+                StatusCode::PayloadTooLarge |
+                StatusCode::UnsupportedMediaType |
+                StatusCode::IAmATeapot |
+                StatusCode::TooManyRequests |
+                StatusCode::RequestHeaderFieldsTooLarge |
+                StatusCode::InternalServerError |
+                StatusCode::NotImplemented |
+                StatusCode::ServiceUnavailable |
+                StatusCode::GatewayTimeout |
+                StatusCode::HttpVersionNotSupported
+        )
     }
 }
@@ -24,6 +24,7 @@
 //! ```
 
 use crate::{
+    request::urlencoded,
     server::route_handler::{ HandlerResult, RouteHandler },
     HttpMethod,
     Request,
@@ -31,12 +32,32 @@ use crate::{
     Middleware,
     StatusCode,
 };
-use std::{ collections::HashMap, fmt::{ Debug, Formatter, Result as fmtResult } };
+use std::{ collections::{ BTreeMap, HashMap }, fmt::{ Debug, Formatter, Result as fmtResult } };
 
 pub mod methods;
+mod param;
+
+use param::RouteParam;
 
 type Controller = fn(&Request, &mut Response);
 
+/// An endpoint of a route, built from a controller or a handler returning `IntoResponse`
+type Endpoint = Box<dyn Fn(&Request, &mut Response) + Send + Sync>;
+
+/// Automatic method handling and fall-through inherited by sub-routers
+#[derive(Clone, Copy)]
+struct AutoMethods {
+    head: bool,
+    options: bool,
+    fall_through: bool,
+}
+
+impl Default for AutoMethods {
+    fn default() -> Self {
+        Self { head: true, options: true, fall_through: false }
+    }
+}
+
 /// A router for handling requests
 ///
 /// # Example
@@ -59,11 +80,63 @@ type Controller = fn(&Request, &mut Response);
 /// sub_router.use_router("suber", sub_sub_router);
 /// main_router.use_router("sub", sub_router);
 /// ```
+///
+/// # Unmatched requests
+///
+/// Requests to paths without a route get `404 Not Found`. Requests to a route without an endpoint
+/// for their method get `405 Method Not Allowed` with an `Allow` header.
+///
+/// ```rust
+/// use krustie::{ Router, Request, Server, StatusCode };
+///
+/// let mut server = Server::create();
+/// let mut main_router = Router::new();
+/// let mut users = Router::new();
+/// let mut user = Router::new();
+///
+/// main_router.get(|_, res| {
+///   res.status(StatusCode::Ok).body_text("Home");
+/// });
+/// user.get(|_, res| {
+///   res.status(StatusCode::Ok);
+/// });
+/// users.use_router(":id", user);
+/// main_router.use_router("/users", users);
+/// server.use_handler(main_router);
+///
+/// let mut status = |raw: &[u8]| {
+///   let mut request = Request::parse_from_bytes(raw).unwrap();
+///   server.dispatch(&mut request).get_status()
+/// };
+///
+/// assert_eq!(status(b"GET / HTTP/1.1\r\n\r\n"), StatusCode::Ok);
+/// assert_eq!(status(b"GET /users/42 HTTP/1.1\r\n\r\n"), StatusCode::Ok);
+/// assert_eq!(status(b"GET /unknown HTTP/1.1\r\n\r\n"), StatusCode::NotFound);
+/// assert_eq!(status(b"GET /users HTTP/1.1\r\n\r\n"), StatusCode::NotFound);
+/// assert_eq!(status(b"DELETE /users/42 HTTP/1.1\r\n\r\n"), StatusCode::MethodNotAllowed);
+///
+/// let mut request = Request::parse_from_bytes(b"DELETE /users/42 HTTP/1.1\r\n\r\n").unwrap();
+/// assert_eq!(server.dispatch(&mut request).get_header("Allow").unwrap(), "GET, HEAD, OPTIONS");
+/// ```
+///
+/// # Automatic HEAD and OPTIONS
+///
+/// By default, `HEAD` requests are handled by the `GET` endpoint and the response is sent without
+/// its body, and `OPTIONS` requests are answered with `204 No Content` and an `Allow` header. Both
+/// only apply if no endpoint is registered for the method and can be disabled with `auto_head`
+/// and `auto_options`.
 pub struct Router {
-    endpoints: HashMap<HttpMethod, Controller>,
-    subroutes: HashMap<String, Router>,
-    request_middlewares: Vec<Box<dyn Middleware>>,
-    response_middlewares: Vec<Box<dyn Middleware>>,
+    endpoints: HashMap<HttpMethod, Endpoint>,
+    any_method_endpoint: Option<Endpoint>,
+    subroutes: BTreeMap<String, Router>,
+    hosts: Vec<(String, Router)>,
+    scopes: Vec<Router>,
+    request_middlewares: Vec<Box<dyn RouteHandler + Send + Sync>>,
+    response_middlewares: Vec<Box<dyn Middleware + Send + Sync>>,
+    auto_head: Option<bool>,
+    auto_options: Option<bool>,
+    fall_through: Option<bool>,
+    accept_post: Vec<String>,
 }
 
 impl Router {
@@ -86,9 +159,16 @@ impl Router {
     pub fn new() -> Self {
         Self {
             endpoints: HashMap::new(),
-            subroutes: HashMap::new(),
+            any_method_endpoint: None,
+            subroutes: BTreeMap::new(),
+            hosts: Vec::new(),
+            scopes: Vec::new(),
             request_middlewares: Vec::new(),
             response_middlewares: Vec::new(),
+            auto_head: None,
+            auto_options: None,
+            fall_through: None,
+            accept_post: Vec::new(),
         }
     }
 
@@ -112,14 +192,332 @@ impl Router {
     /// sub_router.use_router("suber", sub_sub_router);
     /// main_router.use_router("sub", sub_router);
     /// ```
+    ///
+    /// # Parameters
+    ///
+    /// A path starting with `:` is a parameter and matches any segment. The matched segment can be
+    /// read with `Request::get_param`. A parameter can be constrained by adding one of the
+    /// following after its name, in which case segments that don't satisfy the constraint don't
+    /// match:
+    ///
+    /// - `:int` - only ASCII digits (`:id:int`)
+    /// - `:alpha` - only ASCII letters (`:name:alpha`)
+    /// - `:alnum` - only ASCII letters and digits (`:code:alnum`)
+    ///
+    /// A path starting with `*` is a wildcard and matches the rest of the path, which can be read
+    /// with `Request::get_param` without its leading slash.
+    ///
+    /// When several subroutes match a segment, literal paths take precedence over constrained
+    /// parameters, constrained parameters over the other parameters and parameters over
+    /// wildcards, whatever order they are added in. Subroutes of the same kind are tried in the
+    /// order of their paths. A subroute without a route for the rest of the path is skipped, so
+    /// `/files/docs/a.txt` reaches `*path` even if `:name` matches `docs`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraint of a parameter is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut users = Router::new();
+    /// let mut user = Router::new();
+    ///
+    /// user.get(|req, res| {
+    ///   let id = req.get_param("id").unwrap();
+    ///   res.status(StatusCode::Ok).body_text(&format!("User {id}"));
+    /// });
+    ///
+    /// users.use_router(":id:int", user);
+    ///
+    /// let mut main_router = Router::new();
+    /// main_router.use_router("users", users);
+    /// server.use_handler(main_router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /users/42 HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Ok);
+    /// assert_eq!(response.get_body(), b"User 42");
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /users/abc HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NotFound);
+    /// ```
+    ///
+    /// Precedence of literal paths, parameters and wildcards
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server };
+    ///
+    /// let mut server = Server::create();
+    /// let mut files = Router::new();
+    /// let (mut wildcard, mut param, mut literal) = (Router::new(), Router::new(), Router::new());
+    ///
+    /// wildcard.get(|req, res| { res.body_text(&format!("wildcard {}", req.get_param("path").unwrap())); });
+    /// param.get(|req, res| { res.body_text(&format!("param {}", req.get_param("name").unwrap())); });
+    /// literal.get(|_, res| { res.body_text("literal"); });
+    ///
+    /// files.use_router("*path", wildcard);
+    /// files.use_router(":name", param);
+    /// files.use_router("readme", literal);
+    ///
+    /// let mut main_router = Router::new();
+    /// main_router.use_router("files", files);
+    /// server.use_handler(main_router);
+    ///
+    /// let mut handle = |raw: &str| {
+    ///   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    ///   let body = server.dispatch(&mut request).get_body().clone();
+    ///
+    ///   (String::from_utf8(body).unwrap(), request.matched_route().unwrap().to_string())
+    /// };
+    ///
+    /// assert_eq!(handle("GET /files/readme HTTP/1.1\r\n\r\n"), ("literal".into(), "/files/readme".into()));
+    /// assert_eq!(handle("GET /files/notes HTTP/1.1\r\n\r\n"), ("param notes".into(), "/files/:name".into()));
+    /// assert_eq!(
+    ///   handle("GET /files/docs/a%20b.txt HTTP/1.1\r\n\r\n"),
+    ///   ("wildcard docs/a b.txt".into(), "/files/*path".into())
+    /// );
+    ///
+    /// // Segments spelled like a parameter are values, not literal paths
+    /// assert_eq!(handle("GET /files/:name HTTP/1.1\r\n\r\n"), ("param :name".into(), "/files/:name".into()));
+    /// assert_eq!(handle("GET /files/*path/a HTTP/1.1\r\n\r\n"), ("wildcard *path/a".into(), "/files/*path".into()));
+    /// ```
     pub fn use_router(&mut self, path: &str, router: Router) {
-        let sub_path = if let Some(path) = path.strip_prefix('/') { &path[1..] } else { path };
+        let sub_path = path.strip_prefix('/').unwrap_or(path);
+
+        if let Some(param) = RouteParam::parse(sub_path) {
+            if let Err(err) = param.validate() {
+                panic!("{}", err);
+            }
+        }
 
         self.subroutes.entry(sub_path.to_string()).or_insert(router);
     }
 
+    /// Adds a router that only handles requests whose `Host` header matches the pattern
+    ///
+    /// The pattern is either an exact host name (`api.example.com`) or a wildcard subdomain
+    /// (`*.example.com`) which matches any subdomain but not `example.com` itself. The port part of
+    /// the `Host` header is ignored and the comparison is case-insensitive.
+    ///
+    /// Host routers are checked in the order they are added, before path routing. If none of them
+    /// matches, the request is handled by the router itself. The middlewares of the router run
+    /// for the requests of its host routers too, before and after their own middlewares.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Response, Server, Middleware, server::route_handler::HandlerResult };
+    ///
+    /// struct Tag(&'static str);
+    ///
+    /// impl Middleware for Tag {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///     res.insert_header(self.0, "true");
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut main_router = Router::new();
+    /// let mut a_router = Router::new();
+    /// let mut b_router = Router::new();
+    ///
+    /// a_router.get(|_, res| { res.body_text("a"); });
+    /// b_router.get(|_, res| { res.body_text("b"); });
+    ///
+    /// main_router.use_host("a.example.com", a_router);
+    /// main_router.use_host("*.example.com", b_router);
+    /// main_router.use_request_middleware(Tag("X-Request"));
+    /// main_router.use_response_middleware(Tag("X-Response"));
+    /// server.use_handler(main_router);
+    ///
+    /// let mut handle = |host: &str| {
+    ///   let raw = format!("GET / HTTP/1.1\r\nHost: {host}\r\n\r\n");
+    ///   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    ///
+    ///   server.dispatch(&mut request)
+    /// };
+    ///
+    /// let mut response = handle("a.example.com");
+    ///
+    /// assert_eq!(response.get_body(), b"a");
+    /// assert_eq!(response.get_header("X-Request").unwrap(), "true");
+    /// assert_eq!(response.get_header("X-Response").unwrap(), "true");
+    ///
+    /// assert_eq!(handle("B.example.com:8080").get_body(), b"b");
+    /// assert!(handle("example.org").get_body().is_empty());
+    /// ```
+    pub fn use_host(&mut self, host_pattern: &str, router: Router) {
+        self.hosts.push((host_pattern.to_lowercase(), router));
+    }
+
+    /// Groups routes at the same level of the router, so middlewares can be added to all of them
+    ///
+    /// The closure receives a new router whose routes are matched without a path prefix, unlike
+    /// `use_router` which mounts a router under a path. Middlewares added to the scope only run for
+    /// the routes of the scope, while the middlewares of the router run for all of them.
+    ///
+    /// Scopes are checked in the order they are added, before the routes of the router itself. A
+    /// scope is only used for a request if it has an endpoint for its method at the path, so a
+    /// scope and the router can have endpoints for different methods of the same path.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Server, Request, Response, StatusCode, Middleware, HttpMethod, server::route_handler::HandlerResult };
+    ///
+    /// struct Auth;
+    ///
+    /// impl Middleware for Auth {
+    ///   fn middleware(&self, req: &Request, res: &mut Response) -> HandlerResult {
+    ///     if req.get_header("authorization").is_some() {
+    ///       return HandlerResult::Next;
+    ///     }
+    ///     res.status(StatusCode::Unauthorized);
+    ///     HandlerResult::End
+    ///   }
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.route(HttpMethod::GET, "/login", |_, res| { res.body_text("login"); });
+    /// router.scope(|scope| {
+    ///   scope.route(HttpMethod::GET, "/profile", |_, res| { res.body_text("profile"); });
+    ///   scope.route(HttpMethod::GET, "/settings", |_, res| { res.body_text("settings"); });
+    ///   scope.use_request_middleware(Auth);
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// let mut handle = |raw: &str| server.dispatch(&mut Request::parse_from_bytes(raw.as_bytes()).unwrap());
+    ///
+    /// assert_eq!(handle("GET /profile HTTP/1.1\r\n\r\n").get_status(), StatusCode::Unauthorized);
+    /// assert_eq!(handle("GET /settings HTTP/1.1\r\n\r\n").get_status(), StatusCode::Unauthorized);
+    /// assert_eq!(handle("GET /settings HTTP/1.1\r\nAuthorization: token\r\n\r\n").get_body(), b"settings");
+    /// assert_eq!(handle("GET /login HTTP/1.1\r\n\r\n").get_body(), b"login");
+    /// assert_eq!(handle("GET /missing HTTP/1.1\r\n\r\n").get_status(), StatusCode::NotFound);
+    /// ```
+    ///
+    /// Other methods of a path in a scope are handled by the router
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Server, Request, StatusCode, HttpMethod };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.route(HttpMethod::POST, "/posts", |_, res| { res.status(StatusCode::Created); });
+    /// router.scope(|scope| {
+    ///   scope.route(HttpMethod::GET, "/posts", |_, res| { res.status(StatusCode::Ok); });
+    ///   scope.route(HttpMethod::GET, "/drafts", |_, res| { res.status(StatusCode::Ok); });
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// let mut handle = |raw: &str| server.dispatch(&mut Request::parse_from_bytes(raw.as_bytes()).unwrap());
+    ///
+    /// assert_eq!(handle("GET /posts HTTP/1.1\r\n\r\n").get_status(), StatusCode::Ok);
+    /// assert_eq!(handle("HEAD /posts HTTP/1.1\r\n\r\n").get_status(), StatusCode::Ok);
+    /// assert_eq!(handle("POST /posts HTTP/1.1\r\n\r\n").get_status(), StatusCode::Created);
+    /// assert_eq!(handle("DELETE /drafts HTTP/1.1\r\n\r\n").get_status(), StatusCode::MethodNotAllowed);
+    /// ```
+    pub fn scope(&mut self, build: impl FnOnce(&mut Router)) -> &mut Self {
+        let mut scope = Router::new();
+        build(&mut scope);
+        self.scopes.push(scope);
+        self
+    }
+
+    /// Moves the routes of another router into this router, without a path prefix
+    ///
+    /// Endpoints, sub-routers, host routers and scopes of `other` are added to this router, and
+    /// its middlewares are added after the middlewares of this router, so they run for all routes
+    /// of the merged router. Automatic method settings of `other` are only used if they are not
+    /// set on this router.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if both routers have an endpoint for the same method, both have an
+    /// endpoint for any method, or both have a sub-router under the same path. The router is left
+    /// unchanged in that case.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, HttpMethod };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let mut users = Router::new();
+    /// let mut posts = Router::new();
+    ///
+    /// users.route(HttpMethod::GET, "/users/:id", |req, res| {
+    ///   res.body_text(&format!("User {}", req.get_param("id").unwrap()));
+    /// });
+    /// posts.route(HttpMethod::GET, "/posts", |_, res| { res.body_text("Posts"); });
+    /// posts.get(|_, res| { res.body_text("Home"); });
+    ///
+    /// router.merge(users).unwrap();
+    /// router.merge(posts).unwrap();
+    ///
+    /// let mut conflicting = Router::new();
+    /// conflicting.route(HttpMethod::POST, "/users", |_, _| {});
+    ///
+    /// assert_eq!(router.merge(conflicting).unwrap_err(), "Duplicate sub-router: users");
+    ///
+    /// server.use_handler(router);
+    ///
+    /// let mut handle = |raw: &str| {
+    ///   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    ///   server.dispatch(&mut request).get_body().clone()
+    /// };
+    ///
+    /// assert_eq!(handle("GET /users/42 HTTP/1.1\r\n\r\n"), b"User 42");
+    /// assert_eq!(handle("GET /posts HTTP/1.1\r\n\r\n"), b"Posts");
+    /// assert_eq!(handle("GET / HTTP/1.1\r\n\r\n"), b"Home");
+    /// ```
+    pub fn merge(&mut self, other: Router) -> Result<(), String> {
+        if let Some(method) = other.endpoints.keys().find(|method| self.endpoints.contains_key(method)) {
+            return Err(format!("Duplicate endpoint: {}", method));
+        }
+
+        if self.any_method_endpoint.is_some() && other.any_method_endpoint.is_some() {
+            return Err("Duplicate endpoint for any method".to_string());
+        }
+
+        if let Some(path) = other.subroutes.keys().find(|path| self.subroutes.contains_key(*path)) {
+            return Err(format!("Duplicate sub-router: {}", path));
+        }
+
+        self.endpoints.extend(other.endpoints);
+        self.any_method_endpoint = self.any_method_endpoint.take().or(other.any_method_endpoint);
+        self.subroutes.extend(other.subroutes);
+        self.hosts.extend(other.hosts);
+        self.scopes.extend(other.scopes);
+        self.request_middlewares.extend(other.request_middlewares);
+        self.response_middlewares.extend(other.response_middlewares);
+        self.auto_head = self.auto_head.or(other.auto_head);
+        self.auto_options = self.auto_options.or(other.auto_options);
+        self.fall_through = self.fall_through.or(other.fall_through);
+        self.accept_post.extend(other.accept_post);
+
+        Ok(())
+    }
+
     /// Adds a middleware to the router that will be executed before the request is handled
     ///
+    /// Middlewares are stored as trait objects, so different middleware types can be added to the
+    /// same router. They are executed in the order they are added.
+    ///
+    /// Besides a `Middleware`, any `RouteHandler` can be added. A `RouteHandler` gets the request
+    /// mutably, so it can transform the request before the controller sees it.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -137,7 +535,7 @@ impl Router {
     /// }
     ///
     /// impl Middleware for AddHeader {
-    ///   fn middleware(&mut self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
     ///     res.insert_header(&self.key, &self.value);
     ///     HandlerResult::Next
     ///   }
@@ -148,11 +546,101 @@ impl Router {
     ///
     /// router.use_request_middleware(krustie_middleware);
     /// ```
-    pub fn use_request_middleware<T>(&mut self, middleware: T) where T: Middleware + 'static {
+    ///
+    /// Different middleware types on the same router
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Server, Middleware, Request, Response, server::route_handler::HandlerResult };
+    ///
+    /// struct AddServerHeader;
+    ///
+    /// impl Middleware for AddServerHeader {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///     res.insert_header("Server", "Krustie");
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// struct AddLocal {
+    ///   value: String,
+    /// }
+    ///
+    /// impl Middleware for AddLocal {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///     res.add_local("user_id", &self.value);
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   let user_id = res.get_local("user_id").unwrap().clone();
+    ///   res.body_text(&user_id);
+    /// });
+    /// router.use_request_middleware(AddServerHeader);
+    /// router.use_request_middleware(AddLocal { value: "42".to_string() });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Server").unwrap(), "Krustie");
+    /// assert_eq!(response.get_body(), b"42");
+    /// ```
+    ///
+    /// Rewriting the request body before the controller
+    ///
+    /// ```rust
+    /// use krustie::{
+    ///   Router,
+    ///   Server,
+    ///   Request,
+    ///   Response,
+    ///   request::RequestBody,
+    ///   server::route_handler::{ HandlerResult, RouteHandler },
+    /// };
+    ///
+    /// struct Uppercase;
+    ///
+    /// impl RouteHandler for Uppercase {
+    ///   fn handle(&self, req: &mut Request, _: &mut Response, _: &[String]) -> HandlerResult {
+    ///     if let RequestBody::Text(text) = req.get_body() {
+    ///       let text = text.to_ascii_uppercase();
+    ///       req.set_body(RequestBody::Text(text));
+    ///     }
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|req, res| {
+    ///   if let RequestBody::Text(text) = req.get_body() {
+    ///     res.body_text(&String::from_utf8_lossy(text));
+    ///   }
+    /// });
+    /// router.use_request_middleware(Uppercase);
+    /// server.use_handler(router);
+    ///
+    /// let raw = b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello";
+    /// let mut request = Request::parse_from_bytes(raw).unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_body(), b"HELLO");
+    /// ```
+    pub fn use_request_middleware<T>(&mut self, middleware: T)
+        where T: RouteHandler + Send + Sync + 'static
+    {
         self.request_middlewares.push(Box::new(middleware));
     }
 
-    /// Adds a middleware to the router that will be executed before the request is handled
+    /// Adds a middleware to the router that will be executed after the request is handled
+    ///
+    /// Response middlewares are executed even if no route matches the request, so they also see
+    /// `404 Not Found` and `405 Method Not Allowed` responses.
     ///
     /// # Example
     ///
@@ -171,7 +659,7 @@ impl Router {
     /// }
     ///
     /// impl Middleware for AddHeader {
-    ///   fn middleware(&mut self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
     ///     res.insert_header(&self.key, &self.value);
     ///     HandlerResult::Next
     ///   }
@@ -182,56 +670,412 @@ impl Router {
     ///
     /// router.use_response_middleware(krustie_middleware);
     /// ```
-    pub fn use_response_middleware<T>(&mut self, middleware: T) where T: Middleware + 'static {
+    ///
+    /// A logger sees the `404` of an unmatched route
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Server, StatusCode, Middleware, Request, Response, server::route_handler::HandlerResult };
+    /// use std::sync::{ Arc, Mutex };
+    ///
+    /// struct Logger {
+    ///   logs: Arc<Mutex<Vec<String>>>,
+    /// }
+    ///
+    /// impl Middleware for Logger {
+    ///   fn middleware(&self, req: &Request, res: &mut Response) -> HandlerResult {
+    ///     self.logs.lock().unwrap().push(format!("{} {}", req.get_path(), res.get_status()));
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// let logs = Arc::new(Mutex::new(Vec::new()));
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.use_router("home", Router::new());
+    /// router.use_response_middleware(Logger { logs: logs.clone() });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /missing HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NotFound);
+    /// assert_eq!(logs.lock().unwrap().as_slice(), ["/missing 404"]);
+    /// ```
+    pub fn use_response_middleware<T>(&mut self, middleware: T)
+        where T: Middleware + Send + Sync + 'static
+    {
         self.response_middlewares.push(Box::new(middleware));
     }
 
+    /// Sets whether `HEAD` requests are handled by the `GET` endpoint
+    ///
+    /// It is enabled by default. The response of the `GET` endpoint is sent without its body. The
+    /// setting applies to the sub-routers that don't set it themselves.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let mut users = Router::new();
+    ///
+    /// users.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("users");
+    /// });
+    /// router.use_router("users", users);
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"HEAD /users HTTP/1.1\r\n\r\n").unwrap();
+    /// let response: Vec<u8> = server.dispatch(&mut request).into();
+    /// let response = String::from_utf8(response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    /// assert!(response.contains("Content-Length: 5\r\n"));
+    /// assert!(response.ends_with("\r\n\r\n"));
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let mut users = Router::new();
+    ///
+    /// users.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("users");
+    /// });
+    /// router.auto_head(false).use_router("users", users);
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"HEAD /users HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::MethodNotAllowed);
+    /// assert_eq!(response.get_header("Allow").unwrap(), "GET, OPTIONS");
+    /// ```
+    pub fn auto_head(&mut self, enabled: bool) -> &mut Self {
+        self.auto_head = Some(enabled);
+        self
+    }
+
+    /// Sets whether `OPTIONS` requests are answered with the allowed methods
+    ///
+    /// It is enabled by default. Requests get `204 No Content` with an `Allow` header. The setting
+    /// applies to the sub-routers that don't set it themselves.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router
+    ///   .get(|_, res| {
+    ///     res.status(StatusCode::Ok);
+    ///   })
+    ///   .post(|_, res| {
+    ///     res.status(StatusCode::Created);
+    ///   });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"OPTIONS / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NoContent);
+    /// assert_eq!(response.get_header("Allow").unwrap(), "GET, HEAD, POST, OPTIONS");
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router
+    ///   .auto_options(false)
+    ///   .get(|_, res| {
+    ///     res.status(StatusCode::Ok);
+    ///   });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"OPTIONS / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// assert_eq!(server.dispatch(&mut request).get_status(), StatusCode::MethodNotAllowed);
+    /// ```
+    pub fn auto_options(&mut self, enabled: bool) -> &mut Self {
+        self.auto_options = Some(enabled);
+        self
+    }
+
+    /// Sets whether requests without a route are left to the next handler of the server
+    ///
+    /// It is disabled by default, so requests without a route end with `404 Not Found`. When it
+    /// is enabled, the status is still set to `404 Not Found` but the next handler added to the
+    /// server gets the request, e.g. to serve static files next to the routes. Requests to a route
+    /// without an endpoint for their method still end with `405 Method Not Allowed`. The setting
+    /// applies to the sub-routers that don't set it themselves.
+    ///
+    /// A request that fell through has no matched route, see `Request::matched_route`, and the
+    /// parameters of the router are removed. The response middlewares of the router don't run for
+    /// it, since the response is left to the next handler.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{
+    ///   Router,
+    ///   Request,
+    ///   Server,
+    ///   StatusCode,
+    ///   HttpMethod,
+    ///   middleware::statics::{ ServeStatic, InMemory },
+    /// };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.route(HttpMethod::GET, "/api/users", |_, res| {
+    ///   res.status(StatusCode::Ok).body_text("users");
+    /// });
+    /// router.fall_through(true);
+    /// server.use_handler(router);
+    /// server.use_handler(ServeStatic::from_source(InMemory::new().file("index.html", &b"<h1>App</h1>"[..])));
+    ///
+    /// let mut handle = |raw: &str| {
+    ///   let mut request = Request::parse_from_bytes(raw.as_bytes()).unwrap();
+    ///   let mut response = server.dispatch(&mut request);
+    ///
+    ///   (response.get_status(), response.get_body().clone(), request.matched_route().is_some())
+    /// };
+    ///
+    /// assert_eq!(handle("GET /api/users HTTP/1.1\r\n\r\n"), (StatusCode::Ok, b"users".to_vec(), true));
+    /// assert_eq!(handle("GET /index.html HTTP/1.1\r\n\r\n"), (StatusCode::Ok, b"<h1>App</h1>".to_vec(), false));
+    /// assert_eq!(handle("GET /missing.html HTTP/1.1\r\n\r\n").0, StatusCode::NotFound);
+    /// assert_eq!(handle("POST /api/users HTTP/1.1\r\n\r\n").0, StatusCode::MethodNotAllowed);
+    /// ```
+    ///
+    /// Routing a request with several routers
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Response, Server, StatusCode, HttpMethod, Middleware };
+    /// use krustie::server::route_handler::HandlerResult;
+    ///
+    /// struct Tag;
+    ///
+    /// impl Middleware for Tag {
+    ///   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
+    ///     res.insert_header("X-Users", "true");
+    ///     HandlerResult::Next
+    ///   }
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut users = Router::new();
+    /// let mut items = Router::new();
+    ///
+    /// users.route(HttpMethod::GET, "/api/users/:id", |_, res| {
+    ///   res.status(StatusCode::Ok);
+    /// });
+    /// users.fall_through(true).use_response_middleware(Tag);
+    /// items.route(HttpMethod::GET, "/api/items/:id", |req, res| {
+    ///   let route = req.matched_route().unwrap();
+    ///   res.status(StatusCode::Ok).body_text(&format!("{route} {:?}", req.params().keys()));
+    /// });
+    ///
+    /// server.use_handler(users);
+    /// server.use_handler(items);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /api/items/7 HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_body(), br#"/api/items/:id ["id"]"#);
+    /// assert_eq!(response.get_header("X-Users"), None);
+    /// ```
+    pub fn fall_through(&mut self, enabled: bool) -> &mut Self {
+        self.fall_through = Some(enabled);
+        self
+    }
+
+    /// Declares the content types accepted by the `POST` endpoint of the router
+    ///
+    /// They are listed in the `Accept-Post` header of automatic `OPTIONS` responses, so clients
+    /// can discover them. Requests with other content types are not rejected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let mut users = Router::new();
+    ///
+    /// users
+    ///   .post(|_, res| {
+    ///     res.status(StatusCode::Created);
+    ///   })
+    ///   .accept_post(&["application/json"]);
+    /// router.use_router("users", users);
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"OPTIONS /users HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Allow").unwrap(), "POST, OPTIONS");
+    /// assert_eq!(response.get_header("Accept-Post").unwrap(), "application/json");
+    /// ```
+    pub fn accept_post(&mut self, content_types: &[&str]) -> &mut Self {
+        self.accept_post = content_types
+            .iter()
+            .map(|content_type| content_type.to_string())
+            .collect();
+        self
+    }
+
+    /// Returns the number of request and response middlewares of the router
+    ///
+    /// Middlewares of the sub-routers are not counted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, middleware::{ GzipEncoder, Logger } };
+    ///
+    /// let mut router = Router::new();
+    ///
+    /// router.use_request_middleware(Logger::new());
+    /// router.use_response_middleware(GzipEncoder);
+    ///
+    /// assert_eq!(router.middleware_count(), 2);
+    /// assert_eq!(router.request_middleware_count(), 1);
+    ///
+    /// router.clear_request_middleware();
+    ///
+    /// assert_eq!(router.middleware_count(), 1);
+    /// assert_eq!(router.request_middleware_count(), 0);
+    ///
+    /// router.clear_response_middleware();
+    ///
+    /// assert_eq!(router.middleware_count(), 0);
+    /// ```
+    pub fn middleware_count(&self) -> usize {
+        self.request_middlewares.len() + self.response_middlewares.len()
+    }
+
+    /// Returns the number of request middlewares of the router
+    pub fn request_middleware_count(&self) -> usize {
+        self.request_middlewares.len()
+    }
+
+    /// Returns the number of response middlewares of the router
+    pub fn response_middleware_count(&self) -> usize {
+        self.response_middlewares.len()
+    }
+
+    /// Removes all request middlewares of the router
+    pub fn clear_request_middleware(&mut self) {
+        self.request_middlewares.clear();
+    }
+
+    /// Removes all response middlewares of the router
+    pub fn clear_response_middleware(&mut self) {
+        self.response_middlewares.clear();
+    }
+
     fn handle_router(
-        &mut self,
-        request: &Request,
+        &self,
+        request: &mut Request,
         response: &mut Response,
-        path: &[String]
+        path: &[String],
+        auto: AutoMethods
     ) -> HandlerResult {
         if path.is_empty() || path[0].is_empty() {
-            match self.endpoints.get(request.get_method()) {
+            let method = request.get_method();
+            let endpoint = self.endpoints
+                .get(method)
+                .or_else(|| {
+                    match method {
+                        HttpMethod::HEAD if auto.head => self.endpoints.get(&HttpMethod::GET),
+                        _ => None,
+                    }
+                })
+                .or(self.any_method_endpoint.as_ref());
+
+            match endpoint {
                 Some(endpoint) => {
+                    request.set_matched_route();
                     endpoint(request, response);
                 }
+                // The path only leads to sub-routers, so there is nothing to handle here
+                None if self.endpoints.is_empty() => {
+                    return Self::not_found(response, auto);
+                }
+                None if method == &HttpMethod::OPTIONS && auto.options => {
+                    request.set_matched_route();
+                    response.no_content().insert_header("Allow", &self.allowed_methods(auto));
+
+                    if !self.accept_post.is_empty() && self.endpoints.contains_key(&HttpMethod::POST) {
+                        response.insert_header("Accept-Post", &self.accept_post.join(", "));
+                    }
+                    return HandlerResult::End;
+                }
                 None => {
-                    response.status(StatusCode::MethodNotAllowed);
+                    response
+                        .status(StatusCode::MethodNotAllowed)
+                        .insert_header("Allow", &self.allowed_methods(auto));
                     return HandlerResult::End;
                 }
             }
         } else {
-            match self.subroutes.get_mut(&path[0]) {
-                Some(router) => {
-                    router.handle(request, response, &path[1..]);
+            match self.get_subroute(request, path) {
+                Some((router, rest)) => {
+                    return router.handle_with(request, response, rest, auto);
                 }
                 None => {
-                    response.status(StatusCode::NotFound);
-                    return HandlerResult::End;
+                    return Self::not_found(response, auto);
                 }
             }
         }
         return HandlerResult::Next;
     }
-}
 
-impl Debug for Router {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmtResult {
-        write!(f, "Router {{ endpoints: {:?}, subroutes: {:?} }}", self.endpoints, self.subroutes)
+    /// Answers a request without a route with `404 Not Found`
+    ///
+    /// If fall-through is enabled, the request is left to the next handler of the server instead.
+    fn not_found(response: &mut Response, auto: AutoMethods) -> HandlerResult {
+        response.status(StatusCode::NotFound);
+
+        if auto.fall_through {
+            return HandlerResult::Next;
+        }
+        return HandlerResult::End;
     }
-}
 
-impl RouteHandler for Router {
-    fn handle(
-        &mut self,
-        request: &Request,
+    /// Returns the value of the `Allow` header for the endpoints of this router
+    fn allowed_methods(&self, auto: AutoMethods) -> String {
+        HttpMethod::ALL.iter()
+            .filter(|method| {
+                self.endpoints.contains_key(method) ||
+                    match method {
+                        HttpMethod::HEAD => auto.head && self.endpoints.contains_key(&HttpMethod::GET),
+                        HttpMethod::OPTIONS => auto.options,
+                        _ => false,
+                    }
+            })
+            .map(|method| method.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Handles the request with the automatic method handling inherited from the parent router
+    fn handle_with(
+        &self,
+        request: &mut Request,
         response: &mut Response,
-        path: &[String]
+        path: &[String],
+        inherited: AutoMethods
     ) -> HandlerResult {
-        for middleware in &mut self.request_middlewares {
-            match middleware.middleware(request, response) {
+        let auto = self.auto_methods(inherited);
+
+        for middleware in &self.request_middlewares {
+            match middleware.handle(request, response, path) {
                 HandlerResult::End => {
                     return HandlerResult::End;
                 }
@@ -239,14 +1083,22 @@ impl RouteHandler for Router {
             }
         }
 
-        match self.handle_router(request, response, path) {
-            HandlerResult::End => {
-                return HandlerResult::End;
+        // Response middlewares run even if no route matched, so they can see the error response
+        let result = if let Some(router) = self.get_host_router(request) {
+            router.handle_with(request, response, path, auto)
+        } else {
+            match self.get_scope(request.get_method(), path, auto) {
+                Some(scope) => scope.handle_with(request, response, path, auto),
+                None => self.handle_router(request, response, path, auto),
             }
-            HandlerResult::Next => (),
+        };
+
+        // A request that fell through is answered by a later handler, not by this router
+        if result == HandlerResult::Next && request.matched_route().is_none() {
+            return result;
         }
 
-        for middleware in &mut self.response_middlewares {
+        for middleware in &self.response_middlewares {
             match middleware.middleware(request, response) {
                 HandlerResult::End => {
                     return HandlerResult::End;
@@ -255,7 +1107,200 @@ impl RouteHandler for Router {
             }
         }
 
-        return HandlerResult::Next;
+        return result;
+    }
+
+    /// Returns the automatic method settings of the router, with the inherited ones for the
+    /// settings it doesn't set
+    fn auto_methods(&self, inherited: AutoMethods) -> AutoMethods {
+        AutoMethods {
+            head: self.auto_head.unwrap_or(inherited.head),
+            options: self.auto_options.unwrap_or(inherited.options),
+            fall_through: self.fall_through.unwrap_or(inherited.fall_through),
+        }
+    }
+
+    /// Returns the scope that handles the request
+    ///
+    /// The first scope with an endpoint for the method at the path is used. Otherwise the routes
+    /// of the router itself are used, unless only a scope has the path, so that scope answers with
+    /// `405 Method Not Allowed`.
+    fn get_scope(&self, method: &HttpMethod, path: &[String], auto: AutoMethods) -> Option<&Router> {
+        self.scopes
+            .iter()
+            .find(|scope| scope.has_endpoint(method, path, auto))
+            .or_else(|| {
+                match self.has_own_route(path) {
+                    true => None,
+                    false => self.scopes.iter().find(|scope| scope.has_route(path)),
+                }
+            })
+    }
+
+    /// Returns the subroute matching the first segment of the path and the rest of the path
+    ///
+    /// The first subroute in the order of `subroute_candidates` that leads to a route is used. If
+    /// none of them does, the first one is used, so the request gets its error response. If a
+    /// parameter subroute is used, the parameter is added to the request.
+    fn get_subroute<'p>(
+        &self,
+        request: &mut Request,
+        path: &'p [String]
+    ) -> Option<(&Router, &'p [String])> {
+        let (key, rest) = {
+            let candidates = self.subroute_candidates(path);
+            let (key, rest) = candidates
+                .iter()
+                .find(|(key, rest)| {
+                    self.subroutes.get(*key).is_some_and(|router| router.has_route(rest))
+                })
+                .or(candidates.first())?;
+
+            (key.to_string(), *rest)
+        };
+
+        if let Some(param) = RouteParam::parse(&key) {
+            // A wildcard matches the rest of the path, which is decoded as a whole
+            let segments = if param.is_wildcard { path } else { &path[..1] };
+            let value = urlencoded::decode_path(segments.join("/").as_bytes());
+
+            request.add_param(param.name, &value);
+        }
+        request.push_route_segment(&key);
+
+        self.subroutes.get(&key).map(|router| (router, rest))
+    }
+
+    /// Returns the keys of the subroutes matching the first segment of the path, each with the
+    /// rest of the path it handles, in the order of their precedence
+    ///
+    /// Literal paths come first, then constrained parameters, the other parameters and wildcards,
+    /// which match the rest of the path. Subroutes of the same kind are ordered by their keys, so
+    /// the order doesn't depend on the order they are added.
+    fn subroute_candidates<'p>(&self, path: &'p [String]) -> Vec<(&String, &'p [String])> {
+        let Some((segment, rest)) = path.split_first() else {
+            return Vec::new();
+        };
+
+        // Segments are split before decoding, so an encoded slash stays in the value
+        let value = urlencoded::decode_path(segment.as_bytes());
+
+        let mut params = self.subroutes
+            .keys()
+            .filter_map(|key| RouteParam::parse(key).map(|param| (key, param)))
+            .filter(|(_, param)| param.matches(&value))
+            .collect::<Vec<_>>();
+
+        params.sort_by_key(|(_, param)| param.precedence());
+
+        let params = params
+            .into_iter()
+            .map(|(key, param)| (key, if param.is_wildcard { &path[path.len()..] } else { rest }));
+
+        // A segment like `:id` is a value for the parameters, not their literal path
+        let literal = self.subroutes
+            .get_key_value(segment)
+            .filter(|(key, _)| RouteParam::parse(key).is_none());
+
+        literal
+            .map(|(key, _)| (key, rest))
+            .into_iter()
+            .chain(params)
+            .collect()
+    }
+
+    /// Returns true if the path leads to an endpoint of the router or one of its scopes
+    fn has_route(&self, path: &[String]) -> bool {
+        self.scopes.iter().any(|scope| scope.has_route(path)) || self.has_own_route(path)
+    }
+
+    /// Returns true if the path leads to an endpoint of the router, without its scopes
+    fn has_own_route(&self, path: &[String]) -> bool {
+        if path.first().filter(|segment| !segment.is_empty()).is_none() {
+            return !self.endpoints.is_empty() || self.any_method_endpoint.is_some();
+        }
+
+        self.subroute_candidates(path)
+            .into_iter()
+            .any(|(key, rest)| self.subroutes.get(key).is_some_and(|router| router.has_route(rest)))
+    }
+
+    /// Returns true if the path leads to an endpoint of the router or one of its scopes that
+    /// handles the method, including the automatic `HEAD` and `OPTIONS` responses
+    fn has_endpoint(&self, method: &HttpMethod, path: &[String], inherited: AutoMethods) -> bool {
+        let auto = self.auto_methods(inherited);
+
+        if self.scopes.iter().any(|scope| scope.has_endpoint(method, path, auto)) {
+            return true;
+        }
+
+        if path.first().filter(|segment| !segment.is_empty()).is_none() {
+            return self.any_method_endpoint.is_some() ||
+                self.endpoints.contains_key(method) ||
+                match method {
+                    HttpMethod::HEAD => auto.head && self.endpoints.contains_key(&HttpMethod::GET),
+                    HttpMethod::OPTIONS => auto.options && !self.endpoints.is_empty(),
+                    _ => false,
+                };
+        }
+
+        self.subroute_candidates(path)
+            .into_iter()
+            .any(|(key, rest)| {
+                self.subroutes.get(key).is_some_and(|router| router.has_endpoint(method, rest, auto))
+            })
+    }
+
+    fn get_host_router(&self, request: &Request) -> Option<&Router> {
+        let host = request.get_header("host")?;
+        let host = host
+            .rsplit_once(':')
+            .filter(|(_, port)| port.bytes().all(|byte| byte.is_ascii_digit()))
+            .map_or(host.as_str(), |(name, _)| name)
+            .to_lowercase();
+
+        self.hosts
+            .iter()
+            .find(|(pattern, _)| {
+                match pattern.strip_prefix("*.") {
+                    Some(domain) => {
+                        host.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.'))
+                    }
+                    None => *pattern == host,
+                }
+            })
+            .map(|(_, router)| router)
+    }
+}
+
+impl Debug for Router {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmtResult {
+        write!(
+            f,
+            "Router {{ endpoints: {:?}, subroutes: {:?}, hosts: {:?} }}",
+            self.endpoints.keys().collect::<Vec<_>>(),
+            self.subroutes,
+            self.hosts
+        )
+    }
+}
+
+impl RouteHandler for Router {
+    fn handle(
+        &self,
+        request: &mut Request,
+        response: &mut Response,
+        path: &[String]
+    ) -> HandlerResult {
+        let state = request.route_state();
+        let result = self.handle_with(request, response, path, AutoMethods::default());
+
+        // The next handler gets the request as if it wasn't routed
+        if result == HandlerResult::Next && request.matched_route().is_none() {
+            request.restore_route_state(state);
+        }
+
+        result
     }
 }
 
@@ -280,3 +1325,48 @@ impl Default for Router {
         return Self::new();
     }
 }
+
+/// Creates a `Router` from a list of routes
+///
+/// Each route is a method, a path and a controller. Routes are added with `Router::route`, so
+/// nested paths create the sub-routers they need.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ routes, Request, Server, StatusCode };
+///
+/// let router = routes! {
+///   GET "/" => |_, res| {
+///     res.status(StatusCode::Ok).body_text("Home");
+///   },
+///   GET "/users/:id" => |req, res| {
+///     let id = req.get_param("id").unwrap();
+///     res.status(StatusCode::Ok).body_text(&format!("User {id}"));
+///   },
+///   POST "/users" => |_, res| {
+///     res.status(StatusCode::Created);
+///   },
+/// };
+///
+/// let mut server = Server::create();
+/// server.use_handler(router);
+///
+/// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// assert_eq!(server.dispatch(&mut request).get_body(), b"Home");
+///
+/// let mut request = Request::parse_from_bytes(b"GET /users/42 HTTP/1.1\r\n\r\n").unwrap();
+/// assert_eq!(server.dispatch(&mut request).get_body(), b"User 42");
+/// ```
+#[macro_export]
+macro_rules! routes {
+    ($($method:ident $path:literal => $controller:expr),* $(,)?) => {
+        {
+            let mut router = $crate::Router::new();
+            $(
+                router.route($crate::HttpMethod::$method, $path, $controller);
+            )*
+            router
+        }
+    };
+}
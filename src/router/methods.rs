@@ -3,7 +3,8 @@
 //! Each method is implemented as a function that takes a controller function and adds it to the router.
 //!
 //! A controller should be defined as `fn(&Request, &mut Response)`
-use super::{ Controller, HttpMethod, Router };
+use super::{ param::RouteParam, Controller, Endpoint, HttpMethod, Router };
+use crate::{ response::IntoResponse, Request };
 
 impl Router {
     /// Adds a GET endpoint to the router
@@ -19,8 +20,28 @@ impl Router {
     ///    res.status(StatusCode::Ok);
     /// });
     /// ```
+    ///
+    /// Endpoints of the router that is added to the server handle the root path
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut main_router = Router::new();
+    ///
+    /// main_router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Root");
+    /// });
+    /// server.use_handler(main_router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Ok);
+    /// assert_eq!(response.get_body(), b"Root");
+    /// ```
     pub fn get(&mut self, controller: Controller) -> &mut Self {
-        self.endpoints.insert(HttpMethod::GET, controller);
+        self.endpoints.insert(HttpMethod::GET, Box::new(controller));
         self
     }
 
@@ -38,7 +59,7 @@ impl Router {
     /// });
     /// ```
     pub fn post(&mut self, controller: Controller) -> &mut Self {
-        self.endpoints.insert(HttpMethod::POST, controller);
+        self.endpoints.insert(HttpMethod::POST, Box::new(controller));
         self
     }
 
@@ -56,7 +77,7 @@ impl Router {
     /// });
     /// ```
     pub fn put(&mut self, controller: Controller) -> &mut Self {
-        self.endpoints.insert(HttpMethod::PUT, controller);
+        self.endpoints.insert(HttpMethod::PUT, Box::new(controller));
         self
     }
 
@@ -74,7 +95,7 @@ impl Router {
     /// });
     /// ```
     pub fn delete(&mut self, controller: Controller) -> &mut Self {
-        self.endpoints.insert(HttpMethod::DELETE, controller);
+        self.endpoints.insert(HttpMethod::DELETE, Box::new(controller));
         self
     }
 
@@ -92,7 +113,122 @@ impl Router {
     /// });
     /// ```
     pub fn patch(&mut self, controller: Controller) -> &mut Self {
-        self.endpoints.insert(HttpMethod::PATCH, controller);
+        self.endpoints.insert(HttpMethod::PATCH, Box::new(controller));
+        self
+    }
+
+    /// Adds an endpoint that handles all methods which don't have their own endpoint
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router
+    ///   .get(|_, res| {
+    ///     res.status(StatusCode::Ok).body_text("get");
+    ///   })
+    ///   .all(|_, res| {
+    ///     res.status(StatusCode::Ok).body_text("all");
+    ///   });
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"PATCH / HTTP/1.1\r\n\r\n").unwrap();
+    /// assert_eq!(server.dispatch(&mut request).get_body(), b"all");
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// assert_eq!(server.dispatch(&mut request).get_body(), b"get");
+    /// ```
+    pub fn all(&mut self, controller: Controller) -> &mut Self {
+        self.any_method_endpoint = Some(Box::new(controller));
         self
     }
+
+    /// Adds an endpoint for the method at the path, creating the sub-routers of the path
+    ///
+    /// Segments of the path can be parameters as in `use_router`. Existing sub-routers are reused,
+    /// so routes sharing a prefix end up in the same router.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraint of a parameter is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, HttpMethod, StatusCode };
+    ///
+    /// let mut router = Router::new();
+    ///
+    /// router
+    ///   .route(HttpMethod::GET, "/users/:id", |req, res| {
+    ///     res.status(StatusCode::Ok);
+    ///   })
+    ///   .route(HttpMethod::DELETE, "/users/:id", |req, res| {
+    ///     res.status(StatusCode::NoContent);
+    ///   });
+    /// ```
+    pub fn route(&mut self, method: HttpMethod, path: &str, controller: Controller) -> &mut Self {
+        self.add_endpoint(method, path, Box::new(controller))
+    }
+
+    /// Adds an endpoint for the method at the path with a handler that returns the response
+    ///
+    /// The returned value is written into the response with `IntoResponse`. Unlike controllers,
+    /// handlers can be closures that capture their environment.
+    ///
+    /// Like `route`, it returns the router at the path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraint of a parameter is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Router, Request, Server, StatusCode, HttpMethod, response::Json };
+    /// use serde_json::json;
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let version = "1.0.0";
+    ///
+    /// router.respond(HttpMethod::GET, "/version", move |_| Json(json!({ "version": version })));
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /version HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Ok);
+    /// assert_eq!(response.get_body(), br#"{"version":"1.0.0"}"#);
+    /// ```
+    pub fn respond<F, R>(&mut self, method: HttpMethod, path: &str, handler: F) -> &mut Self
+        where F: Fn(&Request) -> R + Send + Sync + 'static, R: IntoResponse
+    {
+        self.add_endpoint(
+            method,
+            path,
+            Box::new(move |request, response| handler(request).into_response(response))
+        )
+    }
+
+    fn add_endpoint(&mut self, method: HttpMethod, path: &str, endpoint: Endpoint) -> &mut Self {
+        let mut router = self;
+
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            if let Some(param) = RouteParam::parse(segment) {
+                if let Err(err) = param.validate() {
+                    panic!("{}", err);
+                }
+            }
+
+            router = router.subroutes.entry(segment.to_string()).or_default();
+        }
+
+        router.endpoints.insert(method, endpoint);
+        router
+    }
 }
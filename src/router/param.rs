@@ -0,0 +1,61 @@
+//! Route parameters and their constraints
+
+/// A route parameter like `:id` or `:id:int`, or a wildcard like `*path`
+pub(super) struct RouteParam<'a> {
+    pub(super) name: &'a str,
+    constraint: Option<&'a str>,
+    pub(super) is_wildcard: bool,
+}
+
+impl<'a> RouteParam<'a> {
+    /// Parses a subroute key into a parameter. Returns `None` if the key is a literal path.
+    pub(super) fn parse(key: &'a str) -> Option<Self> {
+        if let Some(name) = key.strip_prefix('*') {
+            return Some(Self { name, constraint: None, is_wildcard: true });
+        }
+
+        let param = key.strip_prefix(':')?;
+
+        match param.split_once(':') {
+            Some((name, constraint)) => {
+                Some(Self { name, constraint: Some(constraint), is_wildcard: false })
+            }
+            None => Some(Self { name: param, constraint: None, is_wildcard: false }),
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), String> {
+        match self.constraint {
+            None | Some("int" | "alpha" | "alnum") => Ok(()),
+            Some(constraint) =>
+                Err(format!("Unknown constraint for route parameter {}: {}", self.name, constraint)),
+        }
+    }
+
+    /// Returns the precedence of the parameter among the subroutes of a router, lower first
+    ///
+    /// Constrained parameters are more specific than the others, and wildcards are the least
+    /// specific since they match the rest of the path. Literal paths come before all of them.
+    pub(super) fn precedence(&self) -> u8 {
+        match (self.is_wildcard, self.constraint) {
+            (false, Some(_)) => 1,
+            (false, None) => 2,
+            (true, _) => 3,
+        }
+    }
+
+    /// Returns true if the path segment satisfies the constraint of the parameter
+    pub(super) fn matches(&self, segment: &str) -> bool {
+        if segment.is_empty() {
+            return false;
+        }
+
+        match self.constraint {
+            None => true,
+            Some("int") => segment.bytes().all(|byte| byte.is_ascii_digit()),
+            Some("alpha") => segment.bytes().all(|byte| byte.is_ascii_alphabetic()),
+            Some("alnum") => segment.bytes().all(|byte| byte.is_ascii_alphanumeric()),
+            Some(_) => false,
+        }
+    }
+}
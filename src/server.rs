@@ -77,19 +77,66 @@
 //! }
 //!
 //! impl Middleware for AddHeader {
-//!   fn middleware(&mut self, _: &Request, res: &mut Response) -> HandlerResult {
+//!   fn middleware(&self, _: &Request, res: &mut Response) -> HandlerResult {
 //!     res.insert_header(&self.key, &self.value);
 //!     HandlerResult::Next
 //!   }
 //! }
 //! ```
 
-use std::{ fmt::{ Debug, Formatter }, io::Write, net::{ TcpListener, TcpStream } };
-use crate::{ Request, Response, StatusCode };
+use std::{
+    collections::HashMap,
+    fmt::{ Debug, Formatter },
+    io::{ BufRead, BufReader, Result as IoResult, Write },
+    net::{ IpAddr, SocketAddr, TcpListener, ToSocketAddrs },
+    panic::{ self, AssertUnwindSafe },
+    sync::{ atomic::{ AtomicUsize, Ordering }, mpsc, Arc, Mutex, PoisonError },
+    thread,
+    time::{ Duration, Instant },
+};
+use crate::{
+    request::{
+        body::{ BodyParser, BodyParsers, JsonLimits, JsonOptions },
+        multipart::ConnectionBody,
+        ParseHttpRequestError,
+    },
+    response::{ status_code::StatusRange, Upgraded },
+    HttpMethod,
+    Request,
+    Response,
+    StatusCode,
+};
 
 pub mod route_handler;
+pub mod builder;
+pub mod config;
+pub mod panic_counter;
+pub(crate) mod connection;
+pub(crate) mod metrics;
+
+use connection::Connection;
+use metrics::Metrics;
 use route_handler::{ HandlerResult, RouteHandler };
 
+pub use builder::{ ServerBuildError, ServerBuilder };
+pub use config::ServerConfig;
+pub use panic_counter::PanicCounter;
+
+/// Default number of worker threads
+const DEFAULT_WORKERS: usize = 64;
+
+/// Time to wait before accepting again after a connection couldn't be accepted
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// A function that renders the response for a status code
+pub type StatusHandler = fn(&Request, &mut Response);
+
+/// A function that decides whether an accepted connection is handled
+pub type ConnectionFilter = fn(&SocketAddr) -> bool;
+
+/// A function that runs before the handlers and can end the request, see `Server::add_guard`
+pub type Guard = fn(&Request, &mut Response) -> HandlerResult;
+
 /// A server for handling requests
 ///
 /// # Example
@@ -110,8 +157,20 @@ use route_handler::{ HandlerResult, RouteHandler };
 /// // server.listen((127, 0, 0, 1), 8080);
 /// ```
 pub struct Server {
-    route_handlers: Vec<Box<dyn RouteHandler>>,
+    route_handlers: Vec<Box<dyn RouteHandler + Send + Sync>>,
     address: String,
+    config: ServerConfig,
+    status_handlers: Vec<(StatusRange, StatusHandler)>,
+    body_parsers: BodyParsers,
+    max_connections: Option<usize>,
+    max_connections_per_ip: Option<usize>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    on_connect: Option<ConnectionFilter>,
+    guards: Vec<Guard>,
+    metrics: Option<Metrics>,
+    panic_counter: PanicCounter,
+    workers: usize,
 }
 
 impl Server {
@@ -128,9 +187,87 @@ impl Server {
     /// // server.listen((127, 0, 0, 1), 8080)
     /// ```
     pub fn create() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    /// Creates a builder for a server with several settings
+    ///
+    /// The settings are validated when the server is built, see `ServerBuilder`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Server;
+    ///
+    /// let server = Server::builder().max_connections(100).max_connections_per_ip(10).build().unwrap();
+    /// ```
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::new()
+    }
+
+    /// Creates a new server instance with the given configuration
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, server::ServerConfig, json::json };
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   pretty_json: true,
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.body_json(json!({ "message": "Hello" }));
+    /// });
+    ///
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_body(), b"{\n  \"message\": \"Hello\"\n}");
+    /// ```
+    ///
+    /// Adding a default charset to text responses
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, server::ServerConfig };
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   default_charset: Some("utf-8".to_string()),
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/plain; charset=utf-8");
+    /// ```
+    pub fn with_config(config: ServerConfig) -> Self {
         Self {
             route_handlers: Vec::new(),
             address: String::from(""),
+            config,
+            status_handlers: Vec::new(),
+            body_parsers: BodyParsers::default(),
+            max_connections: None,
+            max_connections_per_ip: None,
+            read_timeout: None,
+            write_timeout: None,
+            on_connect: None,
+            guards: Vec::new(),
+            metrics: None,
+            panic_counter: PanicCounter::default(),
+            workers: DEFAULT_WORKERS,
         }
     }
 
@@ -150,13 +287,837 @@ impl Server {
         self.address = format!("{}.{}.{}.{}:{}", ip.0, ip.1, ip.2, ip.3, port);
         let listener = TcpListener::bind(&self.address).unwrap_or_else(|err| panic!("{}", err));
 
-        for stream_result in listener.incoming() {
-            let mut stream = stream_result.unwrap_or_else(|err| {
-                panic!("Error while listening: {}", err);
-            });
+        self.serve(listener);
+    }
+
+    /// Handles the incoming connections of an already bound listener
+    ///
+    /// Connections are handled by a pool of worker threads, see `Server::set_workers`. The handlers
+    /// are shared by the workers, so the requests of different connections run through them at
+    /// the same time.
+    ///
+    /// If a connection panics, it is closed and counted in the `panic_counter`. Its slot and its
+    /// worker are released, so the connection limit and the pool aren't reduced by panics. Panics can only be
+    /// caught when the crate is built with `panic = "unwind"`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// ```
+    pub fn serve(&mut self, listener: TcpListener) {
+        if let Ok(address) = listener.local_addr() {
+            self.address = address.to_string();
+        }
+
+        self.serve_connections(vec![listener.incoming()]);
+    }
+
+    /// Binds to all of the addresses and handles their connections with the same handlers
+    ///
+    /// # Panics
+    ///
+    /// Panics if an address can't be bound.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::Server;
+    ///
+    /// let mut server = Server::create();
+    ///
+    /// // server.bind_many(&["0.0.0.0:80", "127.0.0.1:8080"]);
+    /// ```
+    pub fn bind_many<A: ToSocketAddrs + Debug>(&mut self, addrs: &[A]) {
+        let listeners = addrs
+            .iter()
+            .map(|addr| {
+                TcpListener::bind(addr).unwrap_or_else(|err| panic!("{:?}: {}", addr, err))
+            })
+            .collect();
+
+        self.serve_many(listeners);
+    }
+
+    /// Handles the incoming connections of several already bound listeners
+    ///
+    /// Each listener accepts connections on its own thread, and the connections share the
+    /// handlers and the connection limit of the server.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let public = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let admin = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let addresses = [public.local_addr().unwrap(), admin.local_addr().unwrap()];
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve_many(vec![public, admin]));
+    ///
+    /// for address in addresses {
+    ///   let mut stream = TcpStream::connect(address).unwrap();
+    ///   let mut response = String::new();
+    ///
+    ///   stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///
+    ///   assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// }
+    /// ```
+    pub fn serve_many(&mut self, listeners: Vec<TcpListener>) {
+        self.address = listeners
+            .iter()
+            .filter_map(|listener| listener.local_addr().ok())
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
 
-            self.handle_stream(&mut stream);
+        self.serve_connections(listeners.iter().map(TcpListener::incoming).collect());
+    }
+
+    /// Listens on a Unix domain socket at the path
+    ///
+    /// A stale socket file at the path, one that no server accepts on anymore, is removed before
+    /// binding. Requests are handled the same way as TCP connections, and their peer address is
+    /// `0.0.0.0:0`. Since Unix peers have no IP address, `set_max_connections_per_ip` and
+    /// `RateLimiter` don't limit them, only `set_max_connections` does.
+    ///
+    /// The server runs until the process exits, so the socket file is left at the path. It is
+    /// removed the next time a server binds to the path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path can't be bound, e.g. if another server is listening on it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{
+    ///   io::{ Read, Write },
+    ///   os::unix::net::{ UnixListener, UnixStream },
+    ///   thread,
+    ///   time::Duration,
+    /// };
+    ///
+    /// let path = std::env::temp_dir().join(format!("krustie-bind-unix-{}.sock", std::process::id()));
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    ///
+    /// // Leaves a stale socket file behind, as a stopped server does
+    /// drop(UnixListener::bind(&path).unwrap());
+    ///
+    /// let socket = path.clone();
+    /// thread::spawn(move || server.bind_unix(socket));
+    ///
+    /// let mut stream = loop {
+    ///   match UnixStream::connect(&path) {
+    ///     Ok(stream) => break stream,
+    ///     Err(_) => thread::sleep(Duration::from_millis(10)),
+    ///   }
+    /// };
+    /// let mut response = String::new();
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// assert!(response.ends_with("Hello"));
+    ///
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn bind_unix(&mut self, path: impl AsRef<std::path::Path>) {
+        use std::{
+            fs,
+            io::ErrorKind,
+            os::unix::{ fs::FileTypeExt, net::{ UnixListener, UnixStream } },
+        };
+
+        let path = path.as_ref();
+        let is_socket = fs::symlink_metadata(path).is_ok_and(|metadata| {
+            metadata.file_type().is_socket()
+        });
+
+        // A socket is stale if nothing accepts connections on it
+        let is_stale = is_socket && UnixStream::connect(path).is_err_and(|err| {
+            err.kind() == ErrorKind::ConnectionRefused
+        });
+
+        if is_stale {
+            let _ = fs::remove_file(path);
         }
+
+        let listener = UnixListener::bind(path).unwrap_or_else(|err| panic!("{}", err));
+
+        self.address = path.display().to_string();
+        self.serve_connections(vec![listener.incoming()]);
+    }
+
+    /// Handles the connections of the acceptors with a pool of worker threads
+    ///
+    /// Each acceptor runs on its own thread and passes the accepted connections to the workers,
+    /// which share the handlers and the limits. When all workers are busy, the connections wait in
+    /// a queue as long as the pool, and the acceptors stop accepting while the queue is full.
+    fn serve_connections<S, I>(&mut self, acceptors: Vec<I>)
+        where S: Connection, I: Iterator<Item = IoResult<S>> + Send
+    {
+        let server = &*self;
+        let active_connections = AtomicUsize::new(0);
+        let connections_per_ip = Mutex::new(HashMap::<IpAddr, usize>::new());
+        let (sender, receiver) = mpsc::sync_channel::<(S, Option<IpAddr>)>(server.workers);
+        let receiver = Mutex::new(receiver);
+
+        let (active_connections, connections_per_ip, receiver) = (
+            &active_connections,
+            &connections_per_ip,
+            &receiver,
+        );
+
+        thread::scope(|scope| {
+            for _ in 0..server.workers {
+                scope.spawn(move || {
+                    loop {
+                        // The lock is released before the connection is handled
+                        let next = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+
+                        let Ok((mut stream, peer_ip)) = next else {
+                            return;
+                        };
+
+                        let result = panic::catch_unwind(
+                            AssertUnwindSafe(|| server.handle_stream(&mut stream))
+                        );
+
+                        if result.is_err() {
+                            log::error!("Connection thread panicked");
+                            server.panic_counter.increment();
+                        }
+
+                        active_connections.fetch_sub(1, Ordering::SeqCst);
+
+                        if let Some(ip) = peer_ip {
+                            let mut connections = connections_per_ip
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner);
+
+                            if let Some(count) = connections.get_mut(&ip) {
+                                *count -= 1;
+
+                                if *count == 0 {
+                                    connections.remove(&ip);
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+
+            for incoming in acceptors {
+                let sender = sender.clone();
+
+                scope.spawn(move || {
+                    for stream_result in incoming {
+                        // Errors like running out of file descriptors are temporary, so accepting
+                        // is retried after a pause instead of stopping the server
+                        let mut stream = match stream_result {
+                            Ok(stream) => stream,
+                            Err(err) => {
+                                log::error!("Error while accepting a connection: {}", err);
+                                thread::sleep(ACCEPT_RETRY_DELAY);
+                                continue;
+                            }
+                        };
+
+                        if let Some(on_connect) = server.on_connect {
+                            let is_allowed = stream
+                                .peer_addr()
+                                .is_ok_and(|peer_addr| on_connect(&peer_addr));
+
+                            // The stream is dropped, so the socket is closed without a response
+                            if !is_allowed {
+                                continue;
+                            }
+                        }
+
+                        let is_full = server.max_connections.is_some_and(|max| {
+                            active_connections.load(Ordering::SeqCst) >= max
+                        });
+
+                        if is_full {
+                            Self::reject(&mut stream);
+                            continue;
+                        }
+
+                        // Connections without a peer address are only limited by the total count
+                        let peer_ip = server.max_connections_per_ip.and_then(|_| stream.peer_ip());
+
+                        if let (Some(max), Some(ip)) = (server.max_connections_per_ip, peer_ip) {
+                            let mut connections = connections_per_ip
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner);
+                            let count = connections.entry(ip).or_insert(0);
+
+                            if *count >= max {
+                                drop(connections);
+                                Self::reject(&mut stream);
+                                continue;
+                            }
+
+                            *count += 1;
+                        }
+
+                        active_connections.fetch_add(1, Ordering::SeqCst);
+
+                        if let Err(err) = stream.set_read_timeout(server.read_timeout) {
+                            log::warn!("Error while setting the read timeout: {}", err);
+                        }
+
+                        if let Err(err) = stream.set_write_timeout(server.write_timeout) {
+                            log::warn!("Error while setting the write timeout: {}", err);
+                        }
+
+                        // Blocks while the queue is full, so no more connections are accepted
+                        if sender.send((stream, peer_ip)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+
+            drop(sender);
+        });
+    }
+
+    /// Sets the number of worker threads that handle the connections
+    ///
+    /// Each worker handles one connection at a time, and the requests of different connections are
+    /// handled at the same time. When all workers are busy, new connections wait in a queue as long
+    /// as the pool, and no more connections are accepted while the queue is full. `64` by default.
+    ///
+    /// A keep-alive connection keeps its worker until it is closed, and so does an upgraded one
+    /// until its callback returns.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, sync::Barrier, thread };
+    ///
+    /// // Both requests have to be handled at the same time to pass the barrier
+    /// static BARRIER: Barrier = Barrier::new(2);
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::builder().workers(2).build().unwrap();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   BARRIER.wait();
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let clients = (0..2).map(|_| {
+    ///   thread::spawn(move || {
+    ///     let mut stream = TcpStream::connect(address).unwrap();
+    ///     let mut response = String::new();
+    ///
+    ///     stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///     stream.read_to_string(&mut response).unwrap();
+    ///     response
+    ///   })
+    /// }).collect::<Vec<_>>();
+    ///
+    /// for client in clients {
+    ///   assert!(client.join().unwrap().starts_with("HTTP/1.1 200 OK"));
+    /// }
+    /// ```
+    ///
+    /// Connections wait for a free worker instead of being rejected
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// server.set_workers(1);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // The slow connection takes the only worker until it sends its request
+    /// let mut slow = TcpStream::connect(address).unwrap();
+    /// let mut queued = TcpStream::connect(address).unwrap();
+    ///
+    /// queued.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// slow.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// for stream in [&mut slow, &mut queued] {
+    ///   let mut response = String::new();
+    ///
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///   assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// }
+    /// ```
+    pub fn set_workers(&mut self, workers: usize) {
+        self.workers = workers;
+    }
+
+    /// Sets the maximum number of connections that are handled at the same time
+    ///
+    /// When the limit is reached, new connections are answered with `503 Service Unavailable` and
+    /// closed until a connection finishes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// server.set_max_connections(1);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // The slow connection takes the only slot until it sends its request
+    /// let mut slow = TcpStream::connect(address).unwrap();
+    /// let mut excess = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// excess.read_to_string(&mut response).unwrap();
+    /// assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+    ///
+    /// let mut response = String::new();
+    ///
+    /// slow.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// slow.read_to_string(&mut response).unwrap();
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// ```
+    pub fn set_max_connections(&mut self, max_connections: usize) {
+        self.max_connections = Some(max_connections);
+    }
+
+    /// Sets the maximum number of connections from a single IP address that are handled at the
+    /// same time
+    ///
+    /// When a client reaches the limit, its new connections are answered with
+    /// `503 Service Unavailable` and closed until one of its connections finishes. Connections
+    /// from other addresses are not affected. Connections of Unix sockets have no IP address, so
+    /// they are only limited by `set_max_connections`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// server.set_max_connections_per_ip(2);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // Both slots of 127.0.0.1 are taken until the requests are sent
+    /// let mut first = TcpStream::connect(address).unwrap();
+    /// let mut second = TcpStream::connect(address).unwrap();
+    /// let mut excess = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// excess.read_to_string(&mut response).unwrap();
+    /// assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+    ///
+    /// for stream in [&mut first, &mut second] {
+    ///   let mut response = String::new();
+    ///
+    ///   stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///   assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// }
+    ///
+    /// // The slots are free again after the connections are closed
+    /// let mut response = String::new();
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// ```
+    pub fn set_max_connections_per_ip(&mut self, max_connections: usize) {
+        self.max_connections_per_ip = Some(max_connections);
+    }
+
+    /// Sets the time reading from a connection can block before the connection is closed
+    ///
+    /// Without a timeout, a client that sends its request very slowly or keeps an idle keep-alive
+    /// connection open holds a worker as long as it wants. When the timeout expires while a
+    /// request is being read, it is answered with `408 Request Timeout`. An idle connection is
+    /// closed without a response.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread, time::Duration };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::builder().read_timeout(Duration::from_millis(100)).build().unwrap();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok);
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // The headers are never finished
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n").unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 408 Request Timeout"));
+    ///
+    /// // Nothing is sent
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.is_empty());
+    /// ```
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.read_timeout = Some(timeout);
+    }
+
+    /// Sets the time writing a response can block before the connection is closed
+    ///
+    /// Without a timeout, a client that stops reading the response keeps its connection thread
+    /// blocked. When the timeout expires, the rest of the response is dropped and the connection
+    /// is closed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ self, Read, Write }, net::{ TcpListener, TcpStream }, thread, time::Duration };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// // A single worker, so the second connection waits until the first one is closed
+    /// let mut server = Server::builder().workers(1).build().unwrap();
+    /// let mut router = Router::new();
+    /// let mut endless = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    /// endless.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_stream(Box::new(io::repeat(b'x')), None);
+    /// });
+    ///
+    /// router.use_router("endless", endless);
+    /// server.use_handler(router);
+    /// server.set_write_timeout(Duration::from_millis(100));
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // The client never reads the endless response, so writing it blocks until the timeout
+    /// let mut stalled = TcpStream::connect(address).unwrap();
+    /// stalled.write_all(b"GET /endless HTTP/1.1\r\n\r\n").unwrap();
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// stream.read_to_string(&mut response).unwrap();
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// assert!(response.ends_with("Hello"));
+    ///
+    /// // The stalled connection was closed, so its response ends
+    /// let mut buffer = [0; 64 * 1024];
+    ///
+    /// while let Ok(read @ 1..) = stalled.read(&mut buffer) {
+    ///   assert!(buffer[..read].iter().all(|byte| byte.is_ascii()));
+    /// }
+    /// ```
+    pub fn set_write_timeout(&mut self, timeout: Duration) {
+        self.write_timeout = Some(timeout);
+    }
+
+    /// Sets a function that runs once for every accepted connection
+    ///
+    /// The function receives the address of the client before any request is read. If it returns
+    /// false, the socket is closed without a response. Connections to Unix sockets have the
+    /// address `0.0.0.0:0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::Read, net::{ IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream }, thread };
+    ///
+    /// const ALLOW_LIST: [IpAddr; 1] = [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+    ///
+    /// fn allow_listed(peer_addr: &SocketAddr) -> bool {
+    ///   ALLOW_LIST.contains(&peer_addr.ip())
+    /// }
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// server.on_connect(allow_listed);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // 127.0.0.1 is not in the allow list, so the connection is closed before it is read
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut response = String::new();
+    ///
+    /// stream.read_to_string(&mut response).unwrap();
+    /// assert!(response.is_empty());
+    /// ```
+    pub fn on_connect(&mut self, filter: ConnectionFilter) {
+        self.on_connect = Some(filter);
+    }
+
+    /// Adds a guard that runs for every request before the routers and middlewares
+    ///
+    /// Guards run in the order they were added. If a guard returns `HandlerResult::End`, the
+    /// remaining guards and all handlers are skipped and the response of the guard is sent. It is
+    /// useful for global checks like IP allow lists or authentication.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, Response, StatusCode, HttpMethod, server::route_handler::HandlerResult };
+    /// use std::sync::atomic::{ AtomicBool, Ordering };
+    ///
+    /// static ROUTED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// fn block_admin(req: &Request, res: &mut Response) -> HandlerResult {
+    ///   if req.get_path().starts_with("/admin") {
+    ///     res.status(StatusCode::Forbidden).body_text("Forbidden");
+    ///     return HandlerResult::End;
+    ///   }
+    ///
+    ///   HandlerResult::Next
+    /// }
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.route(HttpMethod::GET, "/admin/users", |_, res| {
+    ///   ROUTED.store(true, Ordering::SeqCst);
+    ///   res.status(StatusCode::Ok).body_text("Users");
+    /// });
+    /// server.add_guard(block_admin);
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /admin/users HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::Forbidden);
+    /// assert_eq!(response.get_body(), b"Forbidden");
+    /// assert!(!ROUTED.load(Ordering::SeqCst));
+    /// ```
+    pub fn add_guard(&mut self, guard: Guard) {
+        self.guards.push(guard);
+    }
+
+    /// Serves request metrics in the Prometheus text format at the given path
+    ///
+    /// `GET` requests to the path get the metrics instead of being handled by the handlers. The
+    /// metrics are:
+    ///
+    /// - `krustie_http_requests_total`: a counter of the requests by method, route and status
+    /// - `krustie_http_request_duration_seconds`: a histogram of the time spent in the handlers
+    /// - `krustie_http_requests_in_flight`: the number of requests that are read but whose
+    ///   response isn't written yet, including the scrape itself
+    ///
+    /// Requests are labeled with the matched route pattern instead of the path, e.g.
+    /// `/users/:id`, so the number of series is bounded. Requests that didn't match a route are
+    /// labeled `unmatched`. Guards run before the metrics are served, so they can protect the
+    /// endpoint.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let mut users = Router::new();
+    /// let mut user = Router::new();
+    ///
+    /// user.get(|req, res| {
+    ///   res.status(StatusCode::Ok).body_text(req.get_param("id").unwrap());
+    /// });
+    /// users.use_router(":id", user);
+    /// router.use_router("users", users);
+    /// server.use_handler(router);
+    /// server.enable_metrics("/metrics");
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let request = |raw: &str| {
+    ///   let mut stream = TcpStream::connect(address).unwrap();
+    ///   let mut response = String::new();
+    ///
+    ///   stream.write_all(raw.as_bytes()).unwrap();
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///   response
+    /// };
+    ///
+    /// request("GET /users/1 HTTP/1.1\r\nConnection: close\r\n\r\n");
+    /// request("GET /users/2 HTTP/1.1\r\nConnection: close\r\n\r\n");
+    /// request("GET /missing HTTP/1.1\r\nConnection: close\r\n\r\n");
+    ///
+    /// let response = request("GET /metrics HTTP/1.1\r\nConnection: close\r\n\r\n");
+    /// let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    ///
+    /// assert!(head.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    /// assert!(body.contains("# TYPE krustie_http_requests_total counter\n"));
+    /// assert!(body.contains("krustie_http_requests_total{method=\"GET\",route=\"/users/:id\",status=\"200\"} 2\n"));
+    /// assert!(body.contains("krustie_http_requests_total{method=\"GET\",route=\"unmatched\",status=\"404\"} 1\n"));
+    /// assert!(body.contains("krustie_http_request_duration_seconds_bucket{method=\"GET\",route=\"/users/:id\",le=\"+Inf\"} 2\n"));
+    /// assert!(body.contains("krustie_http_request_duration_seconds_count{method=\"GET\",route=\"/users/:id\"} 2\n"));
+    /// assert!(body.contains("krustie_http_requests_in_flight 1\n"));
+    ///
+    /// // Every sample is a series followed by a number
+    /// for line in body.lines().filter(|line| !line.starts_with('#')) {
+    ///   let (series, value) = line.rsplit_once(' ').unwrap();
+    ///   assert!(series.starts_with("krustie_http_"), "{line}");
+    ///   assert!(value.parse::<f64>().is_ok(), "{line}");
+    /// }
+    /// ```
+    pub fn enable_metrics(&mut self, path: &str) {
+        self.metrics = Some(Metrics::new(path));
+    }
+
+    /// Returns a counter of the connection threads that panicked
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    /// let mut panic_router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::Ok).body_text("Hello");
+    /// });
+    /// panic_router.get(|_, _| panic!("Controller failed"));
+    /// router.use_router("panic", panic_router);
+    ///
+    /// server.use_handler(router);
+    /// server.set_max_connections(1);
+    ///
+    /// let panics = server.panic_counter();
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let request = |raw: &[u8]| {
+    ///   let mut stream = TcpStream::connect(address).unwrap();
+    ///   let mut response = String::new();
+    ///
+    ///   stream.write_all(raw).unwrap();
+    ///   let _ = stream.read_to_string(&mut response);
+    ///   response
+    /// };
+    ///
+    /// assert_eq!(request(b"GET /panic HTTP/1.1\r\n\r\n"), "");
+    /// assert!(request(b"GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 200 OK"));
+    /// assert_eq!(panics.count(), 1);
+    /// ```
+    pub fn panic_counter(&self) -> PanicCounter {
+        self.panic_counter.clone()
     }
 
     /// Adds a middleware or a router to the server
@@ -178,33 +1139,440 @@ impl Server {
     /// server.use_handler(router);
     /// server.use_handler(GzipEncoder);
     /// ```
-    pub fn use_handler(&mut self, handler: impl RouteHandler + 'static) {
+    pub fn use_handler(&mut self, handler: impl RouteHandler + Send + Sync + 'static) {
         self.route_handlers.push(Box::new(handler));
     }
 
-    fn handle_stream(&mut self, stream: &mut TcpStream) {
-        let mut response = Response::default();
+    /// Sets a handler that renders the responses with the given status code or status class
+    ///
+    /// The handler is called when a response is finalized with a status in the range and without a
+    /// body, so it can set the body and the content type. Handlers for a single status code take
+    /// precedence over handlers for a status class.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, StatusCode, response::status_code::StatusRange, json::json };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.status(StatusCode::InternalServerError);
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// server.set_status_handler(StatusCode::NotFound, |_, res| {
+    ///   res.body(b"<h1>Not Found</h1>".to_vec(), krustie::response::ContentType::Html);
+    /// });
+    /// server.set_status_handler(StatusRange::try_from("5xx").unwrap(), |req, res| {
+    ///   let wants_json = req.get_header("accept").is_some_and(|accept| accept.contains("json"));
+    ///
+    ///   if wants_json {
+    ///     res.body_json(json!({ "error": "Something went wrong" }));
+    ///   } else {
+    ///     res.body_text("Something went wrong");
+    ///   }
+    /// });
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET /missing HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/html");
+    /// assert_eq!(response.get_body(), b"<h1>Not Found</h1>");
+    ///
+    /// let raw = b"GET / HTTP/1.1\r\nAccept: application/json\r\n\r\n";
+    /// let mut request = Request::parse_from_bytes(raw).unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::InternalServerError);
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/json");
+    /// assert_eq!(response.get_body(), br#"{"error":"Something went wrong"}"#);
+    /// ```
+    pub fn set_status_handler(
+        &mut self,
+        range: impl Into<StatusRange>,
+        handler: StatusHandler
+    ) {
+        self.status_handlers.push((range.into(), handler));
+    }
 
-        match Request::parse(stream) {
-            Ok(request) => {
-                for handler in &mut self.route_handlers {
-                    let result = handler.handle(&request, &mut response, request.get_path_array());
-                    if result == HandlerResult::End {
-                        break;
-                    }
+    /// Registers a parser for request bodies with the given content type
+    ///
+    /// Parsers of the built-in content types can be replaced the same way. Requests with a content
+    /// type that has no parser are rejected with `415 Unsupported Media Type`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, request::RequestBody };
+    ///
+    /// let mut server = Server::create();
+    ///
+    /// server.add_body_parser("application/msgpack", RequestBody::Bytes);
+    /// ```
+    pub fn add_body_parser(&mut self, content_type: &str, parser: BodyParser) {
+        self.body_parsers.register(content_type, parser);
+    }
+
+    /// Sets the limits of JSON request bodies
+    ///
+    /// Bodies exceeding them are rejected with `400 Bad Request` before they are parsed. See
+    /// `JsonLimits` for the defaults.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, request::body::JsonLimits };
+    ///
+    /// let mut server = Server::create();
+    ///
+    /// server.set_json_limits(JsonLimits { max_depth: 16, max_elements: 10_000 });
+    /// ```
+    pub fn set_json_limits(&mut self, limits: JsonLimits) {
+        self.body_parsers.set_json_limits(limits);
+    }
+
+    /// Sets the options of the typed deserialization of JSON request bodies with
+    /// `Request::body_json`
+    ///
+    /// Values have to match the types they are deserialized into by default. See `JsonOptions`
+    /// for the options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, request::body::JsonOptions };
+    ///
+    /// let mut server = Server::create();
+    ///
+    /// server.set_json_options(JsonOptions { coerce_numbers: true });
+    /// ```
+    pub fn set_json_options(&mut self, options: JsonOptions) {
+        self.body_parsers.set_json_options(options);
+    }
+
+    /// Decodes request bodies with a `Content-Encoding` up to the maximum decoded size
+    ///
+    /// Request bodies are not decoded by default. Requests that would inflate beyond the limit
+    /// are rejected with `413 Payload Too Large` without decoding the rest of the body. See
+    /// `BodyParsers::set_decompression_limit` for the details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, StatusCode, middleware::gzip::encode_body };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|req: &Request, res| {
+    ///   res.status(StatusCode::Ok).body_text(&req.body_text().unwrap().len().to_string());
+    /// });
+    /// server.use_handler(router);
+    /// server.set_decompression_limit(64 * 1024);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let send = |body: Vec<u8>| {
+    ///   let mut stream = TcpStream::connect(address).unwrap();
+    ///   let mut response = String::new();
+    ///
+    ///   write!(
+    ///     stream,
+    ///     "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+    ///     body.len()
+    ///   ).unwrap();
+    ///   stream.write_all(&body).unwrap();
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///   response
+    /// };
+    ///
+    /// let response = send(encode_body(&[b'a'; 1024], "gzip").unwrap());
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    /// assert!(response.ends_with("1024"));
+    ///
+    /// let response = send(encode_body(&vec![b'a'; 100 * 1024 * 1024], "gzip").unwrap());
+    ///
+    /// assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    /// ```
+    pub fn set_decompression_limit(&mut self, max_size: usize) {
+        self.body_parsers.set_decompression_limit(max_size);
+    }
+
+    /// Runs the request through the handlers and returns the response without writing it to a
+    /// connection.
+    ///
+    /// This is the same pipeline that is used for requests coming from the network, so it can be
+    /// used to test the server.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request };
+    ///
+    /// let mut server = Server::create();
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|_, res| {
+    ///   res.body_text("Hello, World!");
+    /// });
+    ///
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_body(), b"Hello, World!");
+    /// ```
+    ///
+    /// `OPTIONS *` asks about the server as a whole, so it is answered with the supported methods
+    /// without routing
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Request, StatusCode };
+    ///
+    /// let mut server = Server::create();
+    ///
+    /// let mut request = Request::parse_from_bytes(b"OPTIONS * HTTP/1.1\r\n\r\n").unwrap();
+    /// let mut response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_status(), StatusCode::NoContent);
+    /// assert_eq!(response.get_header("Allow").unwrap(), "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS");
+    /// assert!(response.get_body().is_empty());
+    ///
+    /// assert!(Request::parse_from_bytes(b"GET * HTTP/1.1\r\n\r\n").is_err());
+    /// ```
+    pub fn dispatch(&self, request: &mut Request) -> Response {
+        self.dispatch_with(request, Response::with_config(&self.config))
+    }
+
+    /// Runs the request through the handlers, starting with the given response
+    fn dispatch_with(&self, request: &mut Request, mut response: Response) -> Response {
+        if request.get_method() == &HttpMethod::OPTIONS && request.get_path() == "*" {
+            let allow = HttpMethod::ALL.map(|method| method.to_string()).join(", ");
+            response.no_content().insert_header("Allow", &allow);
+            return response;
+        }
+
+        request.apply_forwarded(&self.config.trusted_proxies);
+
+        let started = Instant::now();
+        let is_guarded = self.guards
+            .iter()
+            .any(|guard| guard(request, &mut response) == HandlerResult::End);
+
+        let is_metrics_request = self.metrics
+            .as_ref()
+            .is_some_and(|metrics| {
+                request.get_method() == &HttpMethod::GET && request.get_path() == metrics.path()
+            });
+
+        if let Some(metrics) = self.metrics.as_ref().filter(|_| is_metrics_request && !is_guarded) {
+            response
+                .status(StatusCode::Ok)
+                .body_text(&metrics.render())
+                .insert_header("Content-Type", "text/plain; version=0.0.4");
+            return response;
+        }
+
+        if !is_guarded {
+            let path = request.get_path_array().clone();
+
+            for handler in &self.route_handlers {
+                let result = handler.handle(request, &mut response, &path);
+                if result == HandlerResult::End {
+                    break;
                 }
             }
-            Err(err) => {
-                response.status(StatusCode::BadRequest).debug_msg(&err.to_string());
+        }
+
+        if !response.has_body() {
+            let status = response.get_status();
+            let handler = self.status_handlers
+                .iter()
+                .find(|(range, _)| matches!(range, StatusRange::Code(code) if *code == status))
+                .or_else(|| self.status_handlers.iter().find(|(range, _)| range.contains(status)));
+
+            if let Some((_, handler)) = handler {
+                handler(request, &mut response);
             }
         }
-        let response_stream: Vec<u8> = response.into();
 
-        match stream.write_all(&response_stream) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("error: {}", e);
+        if self.config.content_sniffing {
+            response.sniff_content_type();
+        }
+
+        if let Some(charset) = &self.config.default_charset {
+            response.add_default_charset(charset);
+        }
+
+        if request.get_method() == &HttpMethod::HEAD {
+            response.omit_body();
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.observe(
+                &request.get_method().to_string(),
+                request.matched_route(),
+                response.get_status(),
+                started.elapsed()
+            );
+        }
+
+        response
+    }
+
+    fn handle_stream(&self, stream: &mut impl Connection) {
+        let config = &self.config;
+        let metrics = self.metrics.as_ref();
+        let peer_addr = stream.peer_addr().ok();
+        // Interim responses are written while the handlers run, so they get their own handle
+        let connection = stream
+            .try_clone_socket()
+            .map(Arc::new)
+            .inspect_err(|e| log::warn!("Error while cloning the connection: {}", e))
+            .ok();
+        // The reader is kept for the whole connection, since it may buffer the next request
+        let mut reader = BufReader::new(stream);
+
+        loop {
+            let parsed = match peer_addr {
+                Some(peer_addr) => {
+                    Request::parse(
+                        &mut reader,
+                        peer_addr,
+                        &self.body_parsers,
+                        config.max_request_line_length,
+                        config.max_body_size
+                    )
+                }
+                None => Err(ParseHttpRequestError::Io),
+            };
+
+            let parsed = parsed.and_then(|mut request| {
+                Self::stream_body(&mut reader, &mut request).map_err(|e| {
+                    log::warn!("Error while streaming the request body: {}", e);
+                    ParseHttpRequestError::Io
+                })?;
+
+                Ok(request)
+            });
+
+            // The request is in flight until its response is written
+            let _in_flight = metrics.filter(|_| parsed.is_ok()).map(Metrics::track);
+
+            let (mut response, keep_alive) = match parsed {
+                Ok(mut request) => {
+                    let mut response = Response::with_config(config);
+
+                    if let Some(connection) = &connection {
+                        response.set_connection(Arc::clone(connection));
+                    }
+
+                    let response = self.dispatch_with(&mut request, response);
+                    // The next request starts after the body, so the part the handlers didn't
+                    // read is skipped
+                    let is_body_skipped = request.skip_unread_body();
+
+                    (response, is_body_skipped && config.keep_alive && !Self::wants_close(&request))
+                }
+                Err(ParseHttpRequestError::ConnectionClosed) => {
+                    return;
+                }
+                Err(err) => {
+                    let mut response = Response::default();
+                    response.status(err.status_code()).debug_msg(&err.to_string());
+                    // The rest of the request can't be skipped reliably, so the connection is closed
+                    (response, false)
+                }
+            };
+
+            let upgrade = response.take_upgrade();
+
+            if config.keep_alive && !keep_alive && upgrade.is_none() {
+                response.insert_header("Connection", "close");
+            }
+
+            let deferred = response.take_deferred();
+            let written = response.write_to(reader.get_mut());
+
+            // The connection belongs to the callback after an upgrade, so it isn't reused
+            if let Some(upgrade) = upgrade.filter(|_| written.is_ok()) {
+                match reader.get_ref().try_clone_socket() {
+                    Ok(socket) => upgrade(Upgraded::new(reader.buffer().to_vec(), socket)),
+                    Err(e) => log::warn!("Error while upgrading the connection: {}", e),
+                }
+
+                deferred.into_iter().for_each(|task| task());
+                return;
             }
+
+            if !deferred.is_empty() {
+                // The client shouldn't wait for the end of the stream while the tasks run
+                if !keep_alive || written.is_err() {
+                    if let Err(e) = reader.get_ref().shutdown_write() {
+                        log::debug!("Error while shutting down the connection: {}", e);
+                    }
+                }
+
+                deferred.into_iter().for_each(|task| task());
+            }
+
+            // A partially written response can't be completed, so the connection is closed
+            if let Err(e) = written {
+                log::warn!("Error while writing the response: {}", e);
+                return;
+            }
+
+            if !keep_alive {
+                return;
+            }
+        }
+    }
+
+    /// Hands the body that the parser left on the connection over to the request, so the handlers
+    /// read it from the connection instead of it being received in full first
+    fn stream_body<S: Connection>(
+        reader: &mut BufReader<&mut S>,
+        request: &mut Request
+    ) -> IoResult<()> {
+        let Some(length) = request.take_unread_body_length() else {
+            return Ok(());
+        };
+
+        let buffered = reader.buffer();
+        let buffered = buffered[..length.min(buffered.len())].to_vec();
+
+        reader.consume(buffered.len());
+
+        let socket = reader.get_ref().try_clone_socket()?;
+        let remaining = length - buffered.len();
+
+        request.set_body_reader(ConnectionBody::new(buffered, socket, remaining));
+        Ok(())
+    }
+
+    /// Returns true if the `Connection` header of the request has the `close` option
+    fn wants_close(request: &Request) -> bool {
+        request
+            .get_header("connection")
+            .is_some_and(|value| value.split(',').any(|option| option.trim().eq_ignore_ascii_case("close")))
+    }
+
+    fn reject(stream: &mut impl Write) {
+        let mut response = Response::default();
+
+        response
+            .status(StatusCode::ServiceUnavailable)
+            .insert_header("Connection", "close");
+
+        if let Err(e) = response.write_to(stream) {
+            log::warn!("Error while writing the response: {}", e);
         }
     }
 }
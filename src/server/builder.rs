@@ -0,0 +1,234 @@
+//! This module contains the `ServerBuilder` for configuring a server in a single expression.
+
+use std::{ fmt::{ Display, Formatter, Result as fResult }, time::Duration };
+
+use super::{ ConnectionFilter, Guard, Server, ServerConfig, StatusHandler };
+use crate::{ request::body::{ BodyParser, JsonLimits, JsonOptions }, response::status_code::StatusRange };
+
+/// A builder for creating a configured server
+///
+/// Created by `Server::builder`. Each method corresponds to a setter of `Server`, and the
+/// settings are validated together by `build`.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, Router, StatusCode, server::ServerConfig };
+/// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread, time::Duration };
+///
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let address = listener.local_addr().unwrap();
+///
+/// let mut server = Server::builder()
+///   .config(ServerConfig { default_charset: Some("utf-8".to_string()), ..ServerConfig::default() })
+///   .max_connections(1)
+///   .write_timeout(Duration::from_secs(5))
+///   .build()
+///   .unwrap();
+/// let mut router = Router::new();
+///
+/// router.get(|_, res| {
+///   res.status(StatusCode::Ok).body_text("Hello");
+/// });
+///
+/// server.use_handler(router);
+/// thread::spawn(move || server.serve(listener));
+///
+/// // The only connection slot is taken until the request is sent
+/// let mut slow = TcpStream::connect(address).unwrap();
+/// let mut excess = TcpStream::connect(address).unwrap();
+/// let mut response = String::new();
+///
+/// excess.read_to_string(&mut response).unwrap();
+/// assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+///
+/// let mut response = String::new();
+///
+/// slow.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+/// slow.read_to_string(&mut response).unwrap();
+/// assert!(response.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+/// ```
+///
+/// Invalid settings are rejected
+///
+/// ```rust
+/// use krustie::{ Server, server::ServerBuildError };
+/// use std::time::Duration;
+///
+/// let result = Server::builder().max_connections(10).max_connections_per_ip(20).build();
+/// assert_eq!(result.unwrap_err(), ServerBuildError::PerIpLimitAboveTotal);
+///
+/// let result = Server::builder().workers(0).build();
+/// assert_eq!(result.unwrap_err(), ServerBuildError::ZeroWorkers);
+///
+/// let result = Server::builder().write_timeout(Duration::ZERO).build();
+/// assert_eq!(result.unwrap_err(), ServerBuildError::ZeroWriteTimeout);
+/// ```
+#[derive(Debug)]
+pub struct ServerBuilder {
+    server: Server,
+}
+
+impl ServerBuilder {
+    pub(super) fn new() -> Self {
+        Self {
+            server: Server::create(),
+        }
+    }
+
+    /// Sets the configuration of the server, see `Server::with_config`
+    pub fn config(mut self, config: ServerConfig) -> Self {
+        self.server.config = config;
+        self
+    }
+
+    /// Sets the maximum size of request bodies in bytes, see `ServerConfig::max_body_size`
+    pub fn max_body_size(mut self, max_size: usize) -> Self {
+        self.server.config.max_body_size = max_size;
+        self
+    }
+
+    /// Sets the number of worker threads, see `Server::set_workers`
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.server.set_workers(workers);
+        self
+    }
+
+    /// Sets the maximum number of connections, see `Server::set_max_connections`
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.server.set_max_connections(max_connections);
+        self
+    }
+
+    /// Sets the maximum number of connections of a single IP address, see
+    /// `Server::set_max_connections_per_ip`
+    pub fn max_connections_per_ip(mut self, max_connections: usize) -> Self {
+        self.server.set_max_connections_per_ip(max_connections);
+        self
+    }
+
+    /// Sets the read timeout of the connections, see `Server::set_read_timeout`
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.server.set_read_timeout(timeout);
+        self
+    }
+
+    /// Sets the write timeout of the connections, see `Server::set_write_timeout`
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.server.set_write_timeout(timeout);
+        self
+    }
+
+    /// Sets the function that filters the accepted connections, see `Server::on_connect`
+    pub fn on_connect(mut self, filter: ConnectionFilter) -> Self {
+        self.server.on_connect(filter);
+        self
+    }
+
+    /// Adds a guard that runs before the handlers, see `Server::add_guard`
+    pub fn guard(mut self, guard: Guard) -> Self {
+        self.server.add_guard(guard);
+        self
+    }
+
+    /// Serves request metrics at the given path, see `Server::enable_metrics`
+    pub fn metrics(mut self, path: &str) -> Self {
+        self.server.enable_metrics(path);
+        self
+    }
+
+    /// Sets the limits of JSON request bodies, see `Server::set_json_limits`
+    pub fn json_limits(mut self, limits: JsonLimits) -> Self {
+        self.server.set_json_limits(limits);
+        self
+    }
+
+    /// Sets the options of `Request::body_json`, see `Server::set_json_options`
+    pub fn json_options(mut self, options: JsonOptions) -> Self {
+        self.server.set_json_options(options);
+        self
+    }
+
+    /// Decodes compressed request bodies up to the maximum size, see
+    /// `Server::set_decompression_limit`
+    pub fn decompression_limit(mut self, max_size: usize) -> Self {
+        self.server.set_decompression_limit(max_size);
+        self
+    }
+
+    /// Registers a request body parser, see `Server::add_body_parser`
+    pub fn body_parser(mut self, content_type: &str, parser: BodyParser) -> Self {
+        self.server.add_body_parser(content_type, parser);
+        self
+    }
+
+    /// Sets a status handler, see `Server::set_status_handler`
+    pub fn status_handler(mut self, range: impl Into<StatusRange>, handler: StatusHandler) -> Self {
+        self.server.set_status_handler(range, handler);
+        self
+    }
+
+    /// Creates the server
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the settings can't work together, see `ServerBuildError`.
+    pub fn build(self) -> Result<Server, ServerBuildError> {
+        let server = self.server;
+
+        if server.workers == 0 {
+            return Err(ServerBuildError::ZeroWorkers);
+        }
+
+        if server.max_connections == Some(0) || server.max_connections_per_ip == Some(0) {
+            return Err(ServerBuildError::ZeroConnectionLimit);
+        }
+
+        if let (Some(total), Some(per_ip)) = (server.max_connections, server.max_connections_per_ip) {
+            if per_ip > total {
+                return Err(ServerBuildError::PerIpLimitAboveTotal);
+            }
+        }
+
+        if server.read_timeout == Some(Duration::ZERO) {
+            return Err(ServerBuildError::ZeroReadTimeout);
+        }
+
+        if server.write_timeout == Some(Duration::ZERO) {
+            return Err(ServerBuildError::ZeroWriteTimeout);
+        }
+
+        Ok(server)
+    }
+}
+
+/// Error returned by `ServerBuilder::build` for settings that can't work together
+#[derive(Debug, PartialEq, Eq)]
+pub enum ServerBuildError {
+    /// The number of workers is zero, so no connection would be handled
+    ZeroWorkers,
+    /// A connection limit is zero, so no connection would be handled
+    ZeroConnectionLimit,
+    /// The limit of connections per IP address is above the limit of all connections
+    PerIpLimitAboveTotal,
+    /// The read timeout is zero, which sockets don't accept
+    ZeroReadTimeout,
+    /// The write timeout is zero, which sockets don't accept
+    ZeroWriteTimeout,
+}
+
+impl Display for ServerBuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fResult {
+        match self {
+            Self::ZeroWorkers => write!(f, "Number of workers must be greater than zero"),
+            Self::ZeroConnectionLimit => write!(f, "Connection limit must be greater than zero"),
+            Self::PerIpLimitAboveTotal => {
+                write!(f, "Connection limit per IP address is above the total connection limit")
+            }
+            Self::ZeroReadTimeout => write!(f, "Read timeout must be greater than zero"),
+            Self::ZeroWriteTimeout => write!(f, "Write timeout must be greater than zero"),
+        }
+    }
+}
+
+impl std::error::Error for ServerBuildError {}
@@ -0,0 +1,272 @@
+//! Server configuration module
+//!
+//! This module contains the `ServerConfig` struct which holds the server-wide defaults for
+//! requests and responses.
+
+use std::net::IpAddr;
+
+use crate::request::parser::{ DEFAULT_MAX_BODY_SIZE, DEFAULT_MAX_REQUEST_LINE_LENGTH };
+
+/// Server-wide defaults for requests and responses
+///
+/// `ServerConfig::default()` keeps the default behavior of the server, so only the fields that
+/// need to change have to be set.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::{ Server, server::ServerConfig };
+///
+/// let config = ServerConfig {
+///   default_charset: Some("utf-8".to_string()),
+///   compression_threshold: 1024,
+///   ..ServerConfig::default()
+/// };
+///
+/// let server = Server::with_config(config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Guesses the `Content-Type` of the responses that have a body but no `Content-Type`.
+    ///
+    /// Bodies that are valid JSON get `application/json`, bodies that look like HTML get
+    /// `text/html` and all others get `application/octet-stream`. Disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, server::ServerConfig };
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   content_sniffing: true,
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router
+    ///   .get(|_, res| {
+    ///     res.get_body_mut().extend_from_slice(br#"{"message": "Hello"}"#);
+    ///   })
+    ///   .post(|_, res| {
+    ///     res.get_body_mut().extend_from_slice(b"<!DOCTYPE html><html><body>Hi</body></html>");
+    ///   });
+    ///
+    /// server.use_handler(router);
+    ///
+    /// let mut request = Request::parse_from_bytes(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "application/json");
+    ///
+    /// let mut request = Request::parse_from_bytes(b"POST / HTTP/1.1\r\n\r\n").unwrap();
+    /// let response = server.dispatch(&mut request);
+    ///
+    /// assert_eq!(response.get_header("Content-Type").unwrap(), "text/html");
+    /// ```
+    pub content_sniffing: bool,
+    /// Charset that is appended to `text/*` content types which don't have one. `None` by default.
+    pub default_charset: Option<String>,
+    /// Serializes `Response::body_json` bodies with indentation. Disabled by default.
+    pub pretty_json: bool,
+    /// Bodies smaller than this number of bytes are not compressed by `GzipEncoder`. `0` by
+    /// default.
+    pub compression_threshold: usize,
+    /// Writes the response header names as they are set instead of their canonical form.
+    ///
+    /// By default, header names are written in title case (`Content-Type`) with the known special
+    /// cases like `ETag` and `WWW-Authenticate`.
+    pub preserve_header_case: bool,
+    /// Maximum length of the request line in bytes. Requests with a longer request line are
+    /// rejected with `414 URI Too Long`. `8192` by default.
+    pub max_request_line_length: usize,
+    /// Maximum size of the request body in bytes. `10485760` (10 MiB) by default.
+    ///
+    /// Requests with a larger `Content-Length` are rejected with `413 Payload Too Large` before
+    /// the body is read, and the connection is closed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode };
+    /// use std::{ io::{ Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::builder().max_body_size(16).build().unwrap();
+    /// let mut router = Router::new();
+    ///
+    /// router.post(|_, res| {
+    ///   res.status(StatusCode::Ok);
+    /// });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// let send = |raw: &str| {
+    ///   let mut stream = TcpStream::connect(address).unwrap();
+    ///   let mut response = String::new();
+    ///
+    ///   stream.write_all(raw.as_bytes()).unwrap();
+    ///   stream.read_to_string(&mut response).unwrap();
+    ///   response
+    /// };
+    ///
+    /// let response = send("POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nHello");
+    /// assert!(response.starts_with("HTTP/1.1 200 OK"));
+    ///
+    /// // Only the headers are sent, the body is never read
+    /// let response = send("POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 1000000000000\r\n\r\n");
+    /// assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    /// ```
+    pub max_body_size: usize,
+    /// Keeps connections open to read more requests after a response. Disabled by default, so
+    /// each connection handles a single request.
+    ///
+    /// A connection is closed when the client sends `Connection: close`, closes its side or sends
+    /// a request that can't be parsed. Request bodies are always read completely before the
+    /// handlers run, so a request rejected early doesn't leave its body in the connection.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, StatusCode, server::ServerConfig };
+    /// use std::{ io::{ BufRead, BufReader, Read, Write }, net::{ TcpListener, TcpStream }, thread };
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   keep_alive: true,
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router
+    ///   .get(|_, res| {
+    ///     res.status(StatusCode::Ok).body_text("Hello");
+    ///   })
+    ///   .post(|req, res| {
+    ///     // Rejected without looking at the body
+    ///     if req.get_header("authorization").is_none() {
+    ///       res.status(StatusCode::Unauthorized);
+    ///     }
+    ///   });
+    ///
+    /// server.use_handler(router);
+    /// thread::spawn(move || server.serve(listener));
+    ///
+    /// // Reads a single response with a Content-Length from the connection
+    /// fn read_response(reader: &mut impl BufRead) -> (String, Vec<u8>) {
+    ///   let mut status_line = String::new();
+    ///   reader.read_line(&mut status_line).unwrap();
+    ///
+    ///   let mut length = 0;
+    ///   loop {
+    ///     let mut line = String::new();
+    ///     reader.read_line(&mut line).unwrap();
+    ///     if line == "\r\n" {
+    ///       break;
+    ///     }
+    ///     if let Some(value) = line.strip_prefix("Content-Length: ") {
+    ///       length = value.trim().parse().unwrap();
+    ///     }
+    ///   }
+    ///
+    ///   let mut body = vec![0; length];
+    ///   reader.read_exact(&mut body).unwrap();
+    ///   (status_line, body)
+    /// }
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// let mut reader = BufReader::new(stream.try_clone().unwrap());
+    /// let body = "x".repeat(64 * 1024);
+    /// let upload = format!(
+    ///   "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{body}",
+    ///   body.len()
+    /// );
+    ///
+    /// stream.write_all(upload.as_bytes()).unwrap();
+    /// let (status_line, _) = read_response(&mut reader);
+    /// assert!(status_line.starts_with("HTTP/1.1 401"));
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    /// let (status_line, body) = read_response(&mut reader);
+    /// assert!(status_line.starts_with("HTTP/1.1 200"));
+    /// assert_eq!(body, b"Hello");
+    ///
+    /// stream.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+    /// let mut rest = String::new();
+    /// reader.read_to_string(&mut rest).unwrap();
+    ///
+    /// assert!(rest.contains("Connection: close\r\n"));
+    /// assert!(rest.ends_with("Hello"));
+    /// ```
+    pub keep_alive: bool,
+    /// Addresses of the proxies whose `Forwarded` header is honored. Empty by default.
+    ///
+    /// For requests from these addresses, the peer address, `Request::is_secure` and
+    /// `Request::host` are taken from the `Forwarded` header. The header of other peers is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use krustie::{ Server, Router, Request, HttpMethod, server::ServerConfig };
+    /// use std::net::{ IpAddr, Ipv4Addr, SocketAddr };
+    ///
+    /// let proxy = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    ///
+    /// let mut server = Server::with_config(ServerConfig {
+    ///   trusted_proxies: vec![proxy],
+    ///   ..ServerConfig::default()
+    /// });
+    /// let mut router = Router::new();
+    ///
+    /// router.get(|req, res| {
+    ///   res.body_text(&format!(
+    ///     "{} {} {}",
+    ///     req.get_peer_addr().ip(),
+    ///     req.is_secure(),
+    ///     req.host().unwrap_or("-")
+    ///   ));
+    /// });
+    /// server.use_handler(router);
+    ///
+    /// // The first element is added by another proxy, the last one by the trusted proxy
+    /// let forwarded = "for=192.0.2.43;proto=http, for=\"[2001:db8:cafe::17]:4711\";proto=https;host=example.com";
+    /// let request = |peer: IpAddr| {
+    ///   Request::builder()
+    ///     .method(HttpMethod::GET)
+    ///     .header("Host", "internal:8080")
+    ///     .header("Forwarded", forwarded)
+    ///     .peer_addr(SocketAddr::new(peer, 52000))
+    ///     .build()
+    /// };
+    ///
+    /// let mut response = server.dispatch(&mut request(proxy));
+    ///
+    /// assert_eq!(response.get_body(), b"2001:db8:cafe::17 true example.com");
+    ///
+    /// // Other peers can't spoof the header
+    /// let mut response = server.dispatch(&mut request(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))));
+    ///
+    /// assert_eq!(response.get_body(), b"192.0.2.1 false internal:8080");
+    /// ```
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            content_sniffing: false,
+            default_charset: None,
+            pretty_json: false,
+            compression_threshold: 0,
+            preserve_header_case: false,
+            max_request_line_length: DEFAULT_MAX_REQUEST_LINE_LENGTH,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            keep_alive: false,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! This module contains the abstraction over the stream types the server can handle.
+
+use std::{
+    io::{ Read, Result as IoResult, Write },
+    net::{ IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpStream },
+    time::Duration,
+};
+
+/// A stream of a client connection
+pub(crate) trait Connection: Read + Write + Send {
+    /// Returns the address of the client
+    fn peer_addr(&self) -> IoResult<SocketAddr>;
+
+    /// Returns the IP address of the client, or `None` if the connection has none
+    fn peer_ip(&self) -> Option<IpAddr> {
+        self.peer_addr().ok().map(|peer_addr| peer_addr.ip())
+    }
+
+    /// Sets the time a read can block before it fails
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()>;
+
+    /// Sets the time a write can block before it fails
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()>;
+
+    /// Shuts down the writing half, so the client reads the end of the stream
+    fn shutdown_write(&self) -> IoResult<()>;
+
+    /// Returns another handle to the socket, e.g. to hand it over to an upgrade callback
+    fn try_clone_socket(&self) -> IoResult<Socket>;
+}
+
+/// An owned socket of a client connection
+#[derive(Debug)]
+pub(crate) enum Socket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl Socket {
+    /// Returns another handle to the same socket
+    pub(crate) fn try_clone(&self) -> IoResult<Self> {
+        match self {
+            Self::Tcp(stream) => stream.try_clone().map(Self::Tcp),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.try_clone().map(Self::Unix),
+        }
+    }
+
+    /// Sets the time a read can block before it fails
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    /// Sets the time a write can block before it fails
+    pub(crate) fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    /// Shuts down the reading half, the writing half or both
+    pub(crate) fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.shutdown(how),
+        }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+// Writing only needs a shared handle, e.g. for interim responses while the request is handled
+impl Write for &Socket {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Socket::Tcp(stream) => Write::write(&mut &*stream, buf),
+            #[cfg(unix)]
+            Socket::Unix(stream) => Write::write(&mut &*stream, buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Socket::Tcp(stream) => Write::flush(&mut &*stream),
+            #[cfg(unix)]
+            Socket::Unix(stream) => Write::flush(&mut &*stream),
+        }
+    }
+}
+
+impl Connection for TcpStream {
+    fn peer_addr(&self) -> IoResult<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+
+    fn shutdown_write(&self) -> IoResult<()> {
+        TcpStream::shutdown(self, Shutdown::Write)
+    }
+
+    fn try_clone_socket(&self) -> IoResult<Socket> {
+        TcpStream::try_clone(self).map(Socket::Tcp)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for std::os::unix::net::UnixStream {
+    /// Unix sockets don't have an IP address, so `0.0.0.0:0` is returned
+    fn peer_addr(&self) -> IoResult<SocketAddr> {
+        Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0))
+    }
+
+    fn peer_ip(&self) -> Option<IpAddr> {
+        None
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        std::os::unix::net::UnixStream::set_write_timeout(self, timeout)
+    }
+
+    fn shutdown_write(&self) -> IoResult<()> {
+        std::os::unix::net::UnixStream::shutdown(self, Shutdown::Write)
+    }
+
+    fn try_clone_socket(&self) -> IoResult<Socket> {
+        std::os::unix::net::UnixStream::try_clone(self).map(Socket::Unix)
+    }
+}
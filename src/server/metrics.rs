@@ -0,0 +1,163 @@
+//! This module contains the request metrics of the server, exposed in the Prometheus text format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{ atomic::{ AtomicUsize, Ordering }, Arc, Mutex, PoisonError },
+    time::Duration,
+};
+
+use crate::StatusCode;
+
+/// Upper bounds of the latency histogram buckets in seconds
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Request counters, latencies and in-flight requests of the server
+///
+/// The counters are shared between the clones, so the connection threads can track the requests
+/// they are handling.
+#[derive(Debug, Clone)]
+pub(crate) struct Metrics {
+    path: String,
+    in_flight: Arc<AtomicUsize>,
+    routes: Arc<Mutex<BTreeMap<(String, String), RouteMetrics>>>,
+}
+
+/// Metrics of the requests of a method and a route pattern
+#[derive(Debug, Default)]
+struct RouteMetrics {
+    statuses: BTreeMap<u16, u64>,
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// Counts a request as in flight until it is dropped
+pub(crate) struct InFlight<'a> {
+    metrics: &'a Metrics,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Metrics {
+    pub(crate) fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            routes: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Returns the path the metrics are served at
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Counts a request as in flight until the returned guard is dropped
+    pub(crate) fn track(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight { metrics: self }
+    }
+
+    /// Records a handled request
+    ///
+    /// `route` is the matched route pattern, so the number of series is bounded by the routes.
+    pub(crate) fn observe(
+        &self,
+        method: &str,
+        route: Option<&str>,
+        status: StatusCode,
+        duration: Duration
+    ) {
+        let route = route.unwrap_or("unmatched");
+        let seconds = duration.as_secs_f64();
+        let mut routes = self.routes.lock().unwrap_or_else(PoisonError::into_inner);
+        let metrics = routes.entry((method.to_string(), route.to_string())).or_default();
+
+        *metrics.statuses.entry(u16::from(&status)).or_insert(0) += 1;
+        metrics.count += 1;
+        metrics.sum += seconds;
+
+        for (bucket, bound) in metrics.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub(crate) fn render(&self) -> String {
+        let routes = self.routes.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut output = String::new();
+
+        output.push_str("# HELP krustie_http_requests_total Number of handled requests\n");
+        output.push_str("# TYPE krustie_http_requests_total counter\n");
+
+        for ((method, route), metrics) in routes.iter() {
+            let labels = labels(method, route);
+
+            for (status, count) in &metrics.statuses {
+                let _ = writeln!(
+                    output,
+                    "krustie_http_requests_total{{{labels},status=\"{status}\"}} {count}"
+                );
+            }
+        }
+
+        output.push_str(
+            "# HELP krustie_http_request_duration_seconds Time spent handling requests\n"
+        );
+        output.push_str("# TYPE krustie_http_request_duration_seconds histogram\n");
+
+        for ((method, route), metrics) in routes.iter() {
+            let labels = labels(method, route);
+
+            for (count, bound) in metrics.buckets.iter().zip(BUCKETS) {
+                let _ = writeln!(
+                    output,
+                    "krustie_http_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {count}"
+                );
+            }
+
+            let _ = writeln!(
+                output,
+                "krustie_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                metrics.count
+            );
+            let _ = writeln!(
+                output,
+                "krustie_http_request_duration_seconds_sum{{{labels}}} {}",
+                metrics.sum
+            );
+            let _ = writeln!(
+                output,
+                "krustie_http_request_duration_seconds_count{{{labels}}} {}",
+                metrics.count
+            );
+        }
+
+        output.push_str("# HELP krustie_http_requests_in_flight Number of requests being handled\n");
+        output.push_str("# TYPE krustie_http_requests_in_flight gauge\n");
+        let _ = writeln!(
+            output,
+            "krustie_http_requests_in_flight {}",
+            self.in_flight.load(Ordering::SeqCst)
+        );
+
+        output
+    }
+}
+
+/// Returns the method and route labels of a series
+fn labels(method: &str, route: &str) -> String {
+    format!("method=\"{}\",route=\"{}\"", escape(method), escape(route))
+}
+
+/// Escapes a label value, where backslashes, quotes and line feeds are not allowed
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,33 @@
+//! This module contains the counter of the panicked connection threads.
+
+use std::sync::{ atomic::{ AtomicUsize, Ordering }, Arc };
+
+/// A shared counter of the connection threads that panicked
+///
+/// The counter can be cloned and read from other threads while the server is running.
+///
+/// # Example
+///
+/// ```rust
+/// use krustie::Server;
+///
+/// let server = Server::create();
+/// let panics = server.panic_counter();
+///
+/// assert_eq!(panics.count(), 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PanicCounter {
+    count: Arc<AtomicUsize>,
+}
+
+impl PanicCounter {
+    /// Returns the number of connection threads that panicked
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn increment(&self) {
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+}
@@ -14,8 +14,8 @@ use crate::{ Request, Response };
 pub trait RouteHandler {
     /// Handles the request and returns the result of the handler. It is used to define the handler for the routes and middlewares.
     fn handle(
-        &mut self,
-        request: &Request,
+        &self,
+        request: &mut Request,
         response: &mut Response,
         path: &[String]
     ) -> HandlerResult;